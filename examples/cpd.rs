@@ -18,6 +18,11 @@ fn main() {
     data_1.extend_from_slice(&section_6);
     data_1.iter_mut().for_each(|x| *x += rng.gen_range(0.0..3.0));
 
-    let cp = e_divisive(&data_1, 7, 0.05, 100);
-    println!("{:?}", cp);
+    let cp = e_divisive(&data_1, 7, 10, 0.05, 100);
+    for change_point in cp {
+        println!(
+            "{}: statistic {:.2}, p-value {:.3}",
+            change_point.index, change_point.statistic, change_point.p_value
+        );
+    }
 }
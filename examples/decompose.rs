@@ -3,8 +3,8 @@ use csv::Reader;
 use plotters::prelude::*;
 use std::error::Error;
 
-use mathbox::app::signal::filter::{dft_filter_lowpass, moving_median};
-use mathbox::app::signal::outlier::normal_outlier;
+use mathbox::app::signal::decompose::{stl, StlOptions};
+use mathbox::app::signal::outlier::{seasonal_hybrid_esd, SeasonalEsdOptions};
 
 #[derive(Parser, Debug)]
 #[clap(name = "decompose")]
@@ -32,18 +32,11 @@ fn plot(outfile: &str, name: &str, signal: &[f64]) -> Result<(), Box<dyn std::er
     chart.configure_mesh().draw()?;
 
     chart
-        .draw_series(LineSeries::new(
-            signal.iter().enumerate().map(|(i, x)| (i as u32, *x as f64)),
-            &RED,
-        ))?
+        .draw_series(LineSeries::new(signal.iter().enumerate().map(|(i, x)| (i as u32, *x)), &RED))?
         .label(name)
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
 
-    chart
-        .configure_series_labels()
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
-        .draw()?;
+    chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
 
     Ok(())
 }
@@ -58,17 +51,12 @@ fn main() -> Result<(), Box<dyn Error>> {
         .collect::<Vec<_>>();
     plot("origin.png", "origin", &signal)?;
 
-    let trend = moving_median(&signal, 7);
-    plot("trend.png", "trend", &trend)?;
-
-    let moved_trand = signal.iter().zip(trend.iter()).map(|(x, y)| x - y).collect::<Vec<_>>();
-    let (_, seasonality) = dft_filter_lowpass(&moved_trand, 86400.0, 0.015 / 86400.0, 3);
-    plot("seasonality.png", "seasonality", &seasonality)?;
-
-    let noise = moved_trand.iter().zip(seasonality.iter()).map(|(x, y)| x - y).collect::<Vec<_>>();
-    plot("noise.png", "noise", &noise)?;
-    let outlier = normal_outlier(&noise, 3.0);
-    println!("outliers: {:?}", outlier);
+    let result = stl(&signal, 7, &StlOptions::robust())?;
+    plot("trend.png", "trend", &result.trend)?;
+    plot("seasonality.png", "seasonality", &result.seasonal)?;
+    plot("noise.png", "noise", &result.residual)?;
+    let anomalies = seasonal_hybrid_esd(&signal, 7, &SeasonalEsdOptions::default())?;
+    println!("anomalies: {:?} {:?}", anomalies.lower, anomalies.upper);
 
     Ok(())
 }
@@ -1,5 +1,5 @@
 //! Applications
-//! 
+//!
 //! `signal`: time series signals
 
 pub mod signal;
@@ -0,0 +1,376 @@
+//! Bayesian online change point detection
+//!
+//! `Bocpd` keeps the posterior distribution of the run length, the number of samples since the
+//! last change point, and updates it with every new sample. A change point makes the run start
+//! over: the `Hazard` gives its prior probability after a run of some length, and a `Conjugate`
+//! model gives the predictive probability of the next sample given the samples of the run.
+//!
+//! Here the run length counts the latest sample, so a run length of 1 means that the latest
+//! sample is the first of a new segment.
+//!
+//! Reference: [Adams & MacKay](https://arxiv.org/abs/0710.3742)
+
+use crate::error::{Error, Result};
+use crate::stats::distribution::ln_gamma;
+use std::f64::consts::PI;
+
+/// Prior probability of a change point after a run.
+pub trait Hazard {
+    /// Probability that a run of `run_length` samples ends before the next sample.
+    fn hazard(&self, run_length: usize) -> f64;
+}
+
+/// Memoryless change points, with geometrically distributed run lengths.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConstantHazard {
+    /// Expected number of samples between change points
+    pub expected_run_length: f64,
+}
+
+impl ConstantHazard {
+    pub fn new(expected_run_length: f64) -> Self {
+        ConstantHazard { expected_run_length }
+    }
+}
+
+impl Hazard for ConstantHazard {
+    fn hazard(&self, _run_length: usize) -> f64 {
+        1.0 / self.expected_run_length
+    }
+}
+
+impl<F: Fn(usize) -> f64> Hazard for F {
+    fn hazard(&self, run_length: usize) -> f64 {
+        self(run_length)
+    }
+}
+
+/// Observation model with a conjugate prior, so that the posterior of a run stays in closed form.
+pub trait Conjugate {
+    type Posterior: Clone;
+
+    /// Posterior before any sample, or an error for invalid hyperparameters.
+    fn prior(&self) -> Result<Self::Posterior>;
+
+    /// Posterior after observing `x`.
+    fn update(&self, posterior: &Self::Posterior, x: f64) -> Self::Posterior;
+
+    /// Log of the posterior predictive density of `x`.
+    fn log_predictive(&self, posterior: &Self::Posterior, x: f64) -> f64;
+
+    /// Returns an error if `x` is outside of the support.
+    fn check(&self, _x: f64) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Gaussian samples of unknown mean and variance, with a normal-gamma prior: the precision
+/// follows Gamma(`alpha`, `beta`) and the mean given the precision `tau` follows
+/// N(`mean`, 1 / (`kappa` tau)).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalGamma {
+    pub mean: f64,
+    pub kappa: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl NormalGamma {
+    pub fn new(mean: f64, kappa: f64, alpha: f64, beta: f64) -> Self {
+        NormalGamma { mean, kappa, alpha, beta }
+    }
+}
+
+impl Default for NormalGamma {
+    fn default() -> Self {
+        NormalGamma::new(0.0, 1.0, 1.0, 1.0)
+    }
+}
+
+impl Conjugate for NormalGamma {
+    type Posterior = NormalGamma;
+
+    fn prior(&self) -> Result<NormalGamma> {
+        if self.mean.is_nan()
+            || [self.kappa, self.alpha, self.beta].iter().any(|&p| p.is_nan() || p <= 0.0)
+        {
+            return Err(Error::InvalidParameter(
+                "kappa, alpha and beta must be positive".to_string(),
+            ));
+        }
+        Ok(*self)
+    }
+
+    fn update(&self, p: &NormalGamma, x: f64) -> NormalGamma {
+        NormalGamma {
+            mean: (p.kappa * p.mean + x) / (p.kappa + 1.0),
+            kappa: p.kappa + 1.0,
+            alpha: p.alpha + 0.5,
+            beta: p.beta + p.kappa * (x - p.mean).powi(2) / (2.0 * (p.kappa + 1.0)),
+        }
+    }
+
+    /// Student's t with `2 alpha` degrees of freedom.
+    fn log_predictive(&self, p: &NormalGamma, x: f64) -> f64 {
+        let df = 2.0 * p.alpha;
+        let scale2 = p.beta * (p.kappa + 1.0) / (p.alpha * p.kappa);
+        ln_gamma((df + 1.0) / 2.0)
+            - ln_gamma(df / 2.0)
+            - 0.5 * (df * PI * scale2).ln()
+            - (df + 1.0) / 2.0 * (1.0 + (x - p.mean).powi(2) / (df * scale2)).ln()
+    }
+}
+
+/// Poisson counts with a Gamma(`alpha`, `beta`) prior on the rate, `beta` being the rate of the
+/// gamma distribution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GammaPoisson {
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl GammaPoisson {
+    pub fn new(alpha: f64, beta: f64) -> Self {
+        GammaPoisson { alpha, beta }
+    }
+}
+
+impl Default for GammaPoisson {
+    fn default() -> Self {
+        GammaPoisson::new(1.0, 1.0)
+    }
+}
+
+impl Conjugate for GammaPoisson {
+    type Posterior = GammaPoisson;
+
+    fn prior(&self) -> Result<GammaPoisson> {
+        if [self.alpha, self.beta].iter().any(|&p| p.is_nan() || p <= 0.0) {
+            return Err(Error::InvalidParameter("alpha and beta must be positive".to_string()));
+        }
+        Ok(*self)
+    }
+
+    fn update(&self, p: &GammaPoisson, x: f64) -> GammaPoisson {
+        GammaPoisson { alpha: p.alpha + x, beta: p.beta + 1.0 }
+    }
+
+    /// Negative binomial.
+    fn log_predictive(&self, p: &GammaPoisson, x: f64) -> f64 {
+        ln_gamma(x + p.alpha) - ln_gamma(p.alpha) - ln_gamma(x + 1.0)
+            + p.alpha * (p.beta / (p.beta + 1.0)).ln()
+            - x * (p.beta + 1.0).ln()
+    }
+
+    fn check(&self, x: f64) -> Result<()> {
+        if x < 0.0 {
+            return Err(Error::InvalidParameter("counts must not be negative".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of `Bocpd::update` for the latest sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Update {
+    /// Posterior probability that the latest sample starts a new segment, 1 for the first sample
+    pub change_point_probability: f64,
+    /// Most probable run length
+    pub run_length: usize,
+}
+
+/// Online change point detector, updated one sample at a time.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::change_points::bocpd::{Bocpd, ConstantHazard, NormalGamma};
+/// let mut detector = Bocpd::new(ConstantHazard::new(100.0), NormalGamma::default()).unwrap();
+/// let signal = [0.1, -0.2, 0.0, 0.2, -0.1, 0.1, 5.1, 4.9, 5.0, 5.2];
+/// let updates = signal.iter().map(|&x| detector.update(x).unwrap()).collect::<Vec<_>>();
+/// assert!(updates[6].change_point_probability > 0.5);
+/// assert_eq!(updates[9].run_length, 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bocpd<H: Hazard, M: Conjugate> {
+    hazard: H,
+    model: M,
+    prior: M::Posterior,
+    max_run_length: Option<usize>,
+    /// Probability of the run length `i + 1` at index `i`
+    probabilities: Vec<f64>,
+    /// Posterior of the model after the run of length `i + 1` at index `i`
+    posteriors: Vec<M::Posterior>,
+}
+
+impl<H: Hazard, M: Conjugate> Bocpd<H, M> {
+    /// Returns an error for invalid hyperparameters of `model`.
+    pub fn new(hazard: H, model: M) -> Result<Self> {
+        let prior = model.prior()?;
+        Ok(Bocpd {
+            hazard,
+            model,
+            prior,
+            max_run_length: None,
+            probabilities: vec![],
+            posteriors: vec![],
+        })
+    }
+
+    /// Forget the run lengths beyond `max_run_length`, so that an update takes constant time.
+    pub fn with_max_run_length(mut self, max_run_length: usize) -> Self {
+        self.max_run_length = Some(max_run_length.max(1));
+        self
+    }
+
+    /// Update the run length distribution with the next sample.
+    ///
+    /// Returns an error for a NaN, an infinity or a sample outside of the support of the model,
+    /// a hazard outside [0, 1], or a sample that no run length can explain, and leaves the
+    /// detector unchanged then.
+    pub fn update(&mut self, x: f64) -> Result<Update> {
+        if !x.is_finite() {
+            return Err(Error::InvalidParameter("samples must be finite".to_string()));
+        }
+        self.model.check(x)?;
+        // the first sample starts a run for sure
+        let mut log_joint = vec![0.0];
+        if !self.probabilities.is_empty() {
+            let mut change = 0.0;
+            for (i, (p, posterior)) in self.probabilities.iter().zip(&self.posteriors).enumerate() {
+                let h = self.hazard.hazard(i + 1);
+                if h.is_nan() || !(0.0..=1.0).contains(&h) {
+                    return Err(Error::InvalidParameter("hazard must be in [0, 1]".to_string()));
+                }
+                change += p * h;
+                log_joint.push((p * (1.0 - h)).ln() + self.model.log_predictive(posterior, x));
+            }
+            log_joint[0] = change.ln() + self.model.log_predictive(&self.prior, x);
+        }
+        if let Some(max_run_length) = self.max_run_length {
+            log_joint.truncate(max_run_length);
+        }
+        let max = log_joint.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        if max == f64::NEG_INFINITY {
+            return Err(Error::InvalidParameter(
+                "the sample has zero probability under every run length".to_string(),
+            ));
+        }
+        let mut probabilities = log_joint.iter().map(|l| (l - max).exp()).collect::<Vec<f64>>();
+        let total = probabilities.iter().sum::<f64>();
+        probabilities.iter_mut().for_each(|p| *p /= total);
+
+        let mut posteriors = Vec::with_capacity(probabilities.len());
+        posteriors.push(self.model.update(&self.prior, x));
+        posteriors.extend(
+            self.posteriors.iter().take(probabilities.len() - 1).map(|p| self.model.update(p, x)),
+        );
+        self.probabilities = probabilities;
+        self.posteriors = posteriors;
+        Ok(Update {
+            change_point_probability: self.probabilities[0],
+            run_length: self.run_length(),
+        })
+    }
+
+    /// Posterior probabilities of the run lengths `1, 2, ...` after the samples so far.
+    ///
+    /// The probability of the run length `d + 1` is the probability that the change point was
+    /// `d` samples ago, which is more reliable than the change point probability of the latest
+    /// sample for a small delay `d`.
+    pub fn run_length_distribution(&self) -> &[f64] {
+        &self.probabilities
+    }
+
+    /// Most probable run length, 0 before the first sample.
+    pub fn run_length(&self) -> usize {
+        self.probabilities
+            .iter()
+            .enumerate()
+            .fold(
+                (0, f64::NEG_INFINITY),
+                |best, (i, &p)| if p > best.1 { (i + 1, p) } else { best },
+            )
+            .0
+    }
+
+    /// Forget all samples.
+    pub fn reset(&mut self) {
+        self.probabilities.clear();
+        self.posteriors.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_predictive() {
+        // the predictive densities integrate to 1
+        let model = NormalGamma::new(1.0, 2.0, 3.0, 0.5);
+        let posterior = model.update(&model.prior().unwrap(), 2.0);
+        let integral = (-4000..4000)
+            .map(|i| model.log_predictive(&posterior, i as f64 / 100.0).exp() / 100.0)
+            .sum::<f64>();
+        assert_relative_eq!(integral, 1.0, epsilon = 1e-6);
+        let model = GammaPoisson::new(2.0, 0.5);
+        let total = (0..200).map(|k| model.log_predictive(&model, k as f64).exp()).sum::<f64>();
+        assert_relative_eq!(total, 1.0, epsilon = 1e-9);
+        // Gamma(1, 1) gives the geometric distribution with p = 1/2
+        let model = GammaPoisson::default();
+        assert_relative_eq!(model.log_predictive(&model, 2.0).exp(), 0.125, epsilon = 1e-12);
+
+        assert!(matches!(
+            NormalGamma::new(0.0, 0.0, 1.0, 1.0).prior(),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(GammaPoisson::new(1.0, -1.0).prior(), Err(Error::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_bocpd() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut detector = Bocpd::new(ConstantHazard::new(200.0), NormalGamma::default()).unwrap();
+        let mut alarms = vec![];
+        for i in 0..300 {
+            let noise = rng.gen::<f64>() - 0.5;
+            let x = if i < 150 { noise } else { 3.0 + noise };
+            let update = detector.update(x).unwrap();
+            if update.change_point_probability > 0.5 {
+                alarms.push(i);
+            }
+        }
+        // the first sample always starts a segment
+        assert_eq!(alarms, vec![0, 150]);
+        assert_eq!(detector.run_length(), 150);
+        assert_relative_eq!(
+            detector.run_length_distribution().iter().sum::<f64>(),
+            1.0,
+            epsilon = 1e-12
+        );
+
+        let mut detector =
+            Bocpd::new(|_| 0.01, GammaPoisson::default()).unwrap().with_max_run_length(50);
+        let counts = [2., 1., 3., 2., 2., 1., 2., 3., 2., 1., 15., 12., 14., 16., 13., 15.];
+        let updates = counts.iter().map(|&x| detector.update(x).unwrap()).collect::<Vec<_>>();
+        assert!(updates[10].change_point_probability > 0.5);
+        assert!(updates[..10].iter().skip(1).all(|u| u.change_point_probability < 0.5));
+        assert_eq!(updates[15].run_length, 6);
+        assert!(matches!(detector.update(-1.0), Err(Error::InvalidParameter(_))));
+        assert!(matches!(detector.update(f64::NAN), Err(Error::InvalidParameter(_))));
+        let distribution = detector.run_length_distribution().to_vec();
+        assert!(matches!(detector.update(f64::INFINITY), Err(Error::InvalidParameter(_))));
+        assert_eq!(detector.run_length_distribution(), distribution);
+        assert_eq!(detector.run_length(), 6);
+
+        let mut detector = Bocpd::new(|_| 2.0, NormalGamma::default()).unwrap();
+        detector.update(0.0).unwrap();
+        assert!(matches!(detector.update(0.0), Err(Error::InvalidParameter(_))));
+        detector.reset();
+        assert_eq!(detector.run_length(), 0);
+    }
+}
@@ -0,0 +1,244 @@
+//! Segment costs for change point detection
+//!
+//! A `Cost` is fitted to a signal once and then gives the cost of any segment of it in constant
+//! time, so that a segmentation minimizes the sum of the costs of its segments plus a penalty
+//! per change point. The likelihood costs are twice the negative log likelihood of the segment.
+
+use crate::error::{check_len, check_no_nan, Error, Result};
+use crate::stats::estimator::try_median;
+use std::f64::consts::PI;
+
+/// Cost of a segment of a signal, to be minimized by a segmentation.
+pub trait Cost {
+    /// Precompute what `segment` needs for `signal`.
+    fn fit(&mut self, signal: &[f64]) -> Result<()>;
+
+    /// Cost of the samples `start..end` of the fitted signal.
+    fn segment(&self, start: usize, end: usize) -> f64;
+
+    /// Parameters estimated per segment, which scale the BIC and MBIC penalties.
+    fn parameters(&self) -> usize {
+        1
+    }
+
+    /// Shortest segment the cost is defined for.
+    fn min_size(&self) -> usize {
+        1
+    }
+}
+
+/// Cumulative sums of `x` and `x^2` with a leading 0.
+fn cumulative_sums(signal: &[f64]) -> (Vec<f64>, Vec<f64>) {
+    let mut sum = vec![0.0; signal.len() + 1];
+    let mut sum_squares = vec![0.0; signal.len() + 1];
+    for (i, x) in signal.iter().enumerate() {
+        sum[i + 1] = sum[i] + x;
+        sum_squares[i + 1] = sum_squares[i] + x * x;
+    }
+    (sum, sum_squares)
+}
+
+/// Shifts of the mean under Gaussian noise: the residual sum of squares of the segment mean,
+/// divided by the noise variance estimated from the median absolute difference of successive
+/// samples.
+#[derive(Debug, Clone, Default)]
+pub struct L2 {
+    sum: Vec<f64>,
+    sum_squares: Vec<f64>,
+    variance: f64,
+}
+
+impl L2 {
+    pub fn new() -> Self {
+        L2::default()
+    }
+}
+
+impl Cost for L2 {
+    fn fit(&mut self, signal: &[f64]) -> Result<()> {
+        check_no_nan(signal)?;
+        (self.sum, self.sum_squares) = cumulative_sums(signal);
+        // successive differences of Gaussian noise of variance s^2 have variance 2 s^2
+        let differences = signal.windows(2).map(|w| (w[1] - w[0]).abs()).collect::<Vec<f64>>();
+        let sigma = try_median(&differences).unwrap_or(0.0) * 1.4826 / 2f64.sqrt();
+        self.variance = if sigma > 0.0 { sigma * sigma } else { 1.0 };
+        Ok(())
+    }
+
+    fn segment(&self, start: usize, end: usize) -> f64 {
+        let n = (end - start) as f64;
+        let sum = self.sum[end] - self.sum[start];
+        let rss = self.sum_squares[end] - self.sum_squares[start] - sum * sum / n;
+        rss.max(0.0) / self.variance
+    }
+}
+
+/// Changes of the mean and the variance of Gaussian samples.
+#[derive(Debug, Clone, Default)]
+pub struct NormalMeanVar {
+    sum: Vec<f64>,
+    sum_squares: Vec<f64>,
+}
+
+impl NormalMeanVar {
+    pub fn new() -> Self {
+        NormalMeanVar::default()
+    }
+}
+
+impl Cost for NormalMeanVar {
+    fn fit(&mut self, signal: &[f64]) -> Result<()> {
+        check_no_nan(signal)?;
+        (self.sum, self.sum_squares) = cumulative_sums(signal);
+        Ok(())
+    }
+
+    fn segment(&self, start: usize, end: usize) -> f64 {
+        let n = (end - start) as f64;
+        let mean = (self.sum[end] - self.sum[start]) / n;
+        let variance = (self.sum_squares[end] - self.sum_squares[start]) / n - mean * mean;
+        n * ((2.0 * PI * variance.max(f64::EPSILON)).ln() + 1.0)
+    }
+
+    fn parameters(&self) -> usize {
+        2
+    }
+
+    fn min_size(&self) -> usize {
+        2
+    }
+}
+
+/// Changes of the rate of Poisson counts, without the terms that do not depend on the
+/// segmentation.
+#[derive(Debug, Clone, Default)]
+pub struct Poisson {
+    sum: Vec<f64>,
+}
+
+impl Poisson {
+    pub fn new() -> Self {
+        Poisson::default()
+    }
+}
+
+impl Cost for Poisson {
+    /// Returns an error if the signal has a negative value.
+    fn fit(&mut self, signal: &[f64]) -> Result<()> {
+        check_no_nan(signal)?;
+        if signal.iter().any(|&x| x < 0.0) {
+            return Err(Error::InvalidParameter("counts must not be negative".to_string()));
+        }
+        self.sum = cumulative_sums(signal).0;
+        Ok(())
+    }
+
+    fn segment(&self, start: usize, end: usize) -> f64 {
+        let sum = self.sum[end] - self.sum[start];
+        if sum <= 0.0 {
+            return 0.0;
+        }
+        2.0 * (sum - sum * (sum / (end - start) as f64).ln())
+    }
+}
+
+/// Changes of the distribution, by the kernel cost with the Gaussian kernel
+/// `exp(-gamma (x - y)^2)`.
+///
+/// Fitting stores cumulative sums of the Gram matrix, O(n^2) in time and memory.
+#[derive(Debug, Clone, Default)]
+pub struct Rbf {
+    /// Kernel bandwidth, the inverse of the median squared distance between samples if `None`
+    pub gamma: Option<f64>,
+    gram: Vec<Vec<f64>>,
+}
+
+impl Rbf {
+    pub fn new(gamma: Option<f64>) -> Self {
+        Rbf { gamma, gram: vec![] }
+    }
+}
+
+impl Cost for Rbf {
+    fn fit(&mut self, signal: &[f64]) -> Result<()> {
+        check_len(signal.len(), 1)?;
+        check_no_nan(signal)?;
+        let gamma = match self.gamma {
+            Some(gamma) if gamma <= 0.0 => {
+                return Err(Error::InvalidParameter("gamma must be positive".to_string()))
+            }
+            Some(gamma) => gamma,
+            None => {
+                let distances = signal
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, x)| signal[i + 1..].iter().map(move |y| (x - y).powi(2)))
+                    .collect::<Vec<f64>>();
+                match try_median(&distances) {
+                    Ok(median) if median > 0.0 => 1.0 / median,
+                    _ => 1.0,
+                }
+            }
+        };
+        let n = signal.len();
+        self.gram = vec![vec![0.0; n + 1]; n + 1];
+        for i in 0..n {
+            for j in 0..n {
+                let k = (-gamma * (signal[i] - signal[j]).powi(2)).exp();
+                self.gram[i + 1][j + 1] =
+                    k + self.gram[i][j + 1] + self.gram[i + 1][j] - self.gram[i][j];
+            }
+        }
+        Ok(())
+    }
+
+    fn segment(&self, start: usize, end: usize) -> f64 {
+        let g = &self.gram;
+        let sum = g[end][end] - g[start][end] - g[end][start] + g[start][start];
+        let n = (end - start) as f64;
+        (n - sum / n).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_costs() {
+        let signal = [1.0, 3.0, 2.0, 6.0, 4.0, 5.0];
+        let mut l2 = L2::new();
+        l2.fit(&signal).unwrap();
+        // median absolute difference 2
+        let variance = (2.0 * 1.4826f64 / 2f64.sqrt()).powi(2);
+        assert_relative_eq!(l2.segment(0, 3), 2.0 / variance, epsilon = 1e-12);
+        assert_relative_eq!(l2.segment(3, 4), 0.0);
+
+        let mut normal = NormalMeanVar::new();
+        normal.fit(&signal).unwrap();
+        assert_relative_eq!(
+            normal.segment(3, 6),
+            3.0 * ((2.0 * PI * 2.0 / 3.0).ln() + 1.0),
+            epsilon = 1e-12
+        );
+        assert_eq!((normal.parameters(), normal.min_size()), (2, 2));
+
+        let mut poisson = Poisson::new();
+        poisson.fit(&[0.0, 0.0, 2.0, 4.0]).unwrap();
+        assert_eq!(poisson.segment(0, 2), 0.0);
+        assert_relative_eq!(poisson.segment(2, 4), 2.0 * (6.0 - 6.0 * 3f64.ln()), epsilon = 1e-12);
+        assert!(matches!(poisson.fit(&[1.0, -1.0]), Err(Error::InvalidParameter(_))));
+
+        let mut rbf = Rbf::new(Some(0.5));
+        rbf.fit(&signal).unwrap();
+        let k = (-0.5f64 * 4.0).exp();
+        assert_relative_eq!(rbf.segment(0, 2), 2.0 - (2.0 + 2.0 * k) / 2.0, epsilon = 1e-12);
+        assert_relative_eq!(rbf.segment(2, 3), 0.0, epsilon = 1e-12);
+        let mut rbf = Rbf::new(None);
+        rbf.fit(&[0.0; 5]).unwrap();
+        assert_relative_eq!(rbf.segment(0, 5), 0.0, epsilon = 1e-12);
+        assert!(matches!(Rbf::new(Some(0.0)).fit(&signal), Err(Error::InvalidParameter(_))));
+        assert_eq!(L2::new().fit(&[1.0, f64::NAN]), Err(Error::NanValue { index: 1 }));
+    }
+}
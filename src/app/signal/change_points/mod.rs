@@ -0,0 +1,351 @@
+//! Change point detection
+//!
+//! `e_divisive` tests for changes of the distribution by energy statistics. `segmentation`
+//! minimizes penalized segment costs from `cost`, which is much faster on long signals. Both
+//! are offline, while `bocpd` updates its detection with every new sample.
+
+pub mod bocpd;
+pub mod cost;
+pub mod segmentation;
+
+use crate::error::{check_len, check_no_nan, Error, Result};
+use crate::stats::missing::{valid_samples, NanPolicy};
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+/// A change point found by `e_divisive`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChangePoint {
+    /// Last index of the segment before the change
+    pub index: usize,
+    /// Normalized energy distance between the samples before and after the change, scaled by
+    /// `n_a n_b / (n_a + n_b)`
+    pub statistic: f64,
+    /// Share of the permutations of the segment, counting the segment itself, whose best split
+    /// has a statistic at least as large
+    pub p_value: f64,
+}
+
+/// Sums of the absolute differences to the samples inserted so far, from a Fenwick tree over
+/// the ranks of the samples.
+struct DistanceSums {
+    count: Vec<usize>,
+    sum: Vec<f64>,
+    total: f64,
+    inserted: usize,
+}
+
+impl DistanceSums {
+    fn new(n: usize) -> Self {
+        DistanceSums { count: vec![0; n + 1], sum: vec![0.0; n + 1], total: 0.0, inserted: 0 }
+    }
+
+    fn insert(&mut self, rank: usize, x: f64) {
+        let mut i = rank + 1;
+        while i < self.count.len() {
+            self.count[i] += 1;
+            self.sum[i] += x;
+            i += i & i.wrapping_neg();
+        }
+        self.total += x;
+        self.inserted += 1;
+    }
+
+    /// Sum of `|x - y|` over the inserted samples `y`, where `rank` is the rank of `x`.
+    fn distance(&self, rank: usize, x: f64) -> f64 {
+        let (mut below, mut below_sum) = (0, 0.0);
+        let mut i = rank + 1;
+        while i > 0 {
+            below += self.count[i];
+            below_sum += self.sum[i];
+            i -= i & i.wrapping_neg();
+        }
+        let above = self.inserted - below;
+        x * below as f64 - below_sum + (self.total - below_sum) - x * above as f64
+    }
+}
+
+/// Sums of the distances between all pairs among the first `k` samples, for `k` in 0..=n.
+fn within_sums<'a>(ranked: impl Iterator<Item = &'a (usize, f64)>, n: usize) -> Vec<f64> {
+    let mut sums = DistanceSums::new(n);
+    let mut within = Vec::with_capacity(n + 1);
+    within.push(0.0);
+    for &(rank, x) in ranked {
+        within.push(within[within.len() - 1] + sums.distance(rank, x));
+        sums.insert(rank, x);
+    }
+    within
+}
+
+/// Best split of `ranked` (rank, value) samples among the first segment lengths
+/// `min_size, min_size + jump, ...` that leave at least `min_size` samples after it, as
+/// (length of the first segment, statistic).
+///
+/// The statistic is the normalized energy distance between both sides scaled by
+/// `n_a n_b / (n_a + n_b)`. The within sums of both sides are accumulated as the split moves, so
+/// all splits take O(n log n) together.
+fn best_split(ranked: &[(usize, f64)], jump: usize, min_size: usize) -> Option<(usize, f64)> {
+    let n = ranked.len();
+    if n < 2 * min_size {
+        return None;
+    }
+    let left = within_sums(ranked.iter(), n);
+    let right = within_sums(ranked.iter().rev(), n);
+    (min_size..=n - min_size)
+        .step_by(jump)
+        .map(|k| {
+            let (na, nb) = (k as f64, (n - k) as f64);
+            let (within_a, within_b) = (left[k], right[n - k]);
+            let between = (left[n] - within_a - within_b).max(0.0) / (na * nb);
+            let e_dis = 2.0 * between - 2.0 * within_a / (na * na) - 2.0 * within_b / (nb * nb);
+            (k, e_dis / (2.0 * between + f64::EPSILON) * na * nb / (na + nb))
+        })
+        .fold(None, |best: Option<(usize, f64)>, (k, t)| match best {
+            Some((_, b)) if b >= t => best,
+            _ => Some((k, t)),
+        })
+}
+
+/// Samples of `signal` paired with their ranks, ties broken by position.
+fn ranked(signal: &[f64]) -> Vec<(usize, f64)> {
+    let mut order = (0..signal.len()).collect::<Vec<usize>>();
+    order.sort_by(|&i, &j| signal[i].total_cmp(&signal[j]));
+    let mut ranked = vec![(0, 0.0); signal.len()];
+    for (rank, &i) in order.iter().enumerate() {
+        ranked[i] = (rank, signal[i]);
+    }
+    ranked
+}
+
+/// Best split of `signal` with its permutation p-value, `None` if it is not significant.
+fn calculate_t_stats(
+    signal: &[f64],
+    jump: usize,
+    min_size: usize,
+    p_value: f64,
+    permutations: usize,
+) -> Option<ChangePoint> {
+    // permuting the samples permutes their ranks along, so they are sorted only once
+    let mut shuffled = ranked(signal);
+    let (k, statistic) = best_split(&shuffled, jump, min_size)?;
+    let mut rng = thread_rng();
+    let mut larger = 1;
+    for _ in 0..permutations {
+        shuffled.shuffle(&mut rng);
+        if best_split(&shuffled, jump, min_size).is_some_and(|(_, t)| t >= statistic) {
+            larger += 1;
+        }
+    }
+    let probability = larger as f64 / (permutations + 1) as f64;
+    if probability <= p_value {
+        Some(ChangePoint { index: k - 1, statistic, p_value: probability })
+    } else {
+        None
+    }
+}
+
+fn check_parameters(jump: usize, min_size: usize, p_value: f64) -> Result<()> {
+    if jump == 0 || min_size == 0 {
+        return Err(Error::InvalidParameter("jump and min_size must be positive".to_string()));
+    }
+    if !(0.0..=1.0).contains(&p_value) {
+        return Err(Error::InvalidParameter("p_value must be in [0, 1]".to_string()));
+    }
+    Ok(())
+}
+
+/// Detect change points from a &[<Into<f64> + Copy>] vector.
+///
+/// Segments are split recursively at the split with the largest energy statistic while a
+/// permutation test with `permutations` shuffles finds it significant at `p_value`. Only every
+/// `jump`-th split is tried and segments keep at least `min_size` samples. The change points
+/// are sorted by index.
+///
+/// The statistics of all splits of a segment of `n` samples take O(n log n), so each level of
+/// the recursion costs O(n log n) per permutation.
+///
+/// References: [Energy distance](https://doi.org/10.1002/wics.1375) & [MongoDB paper](https://dl.acm.org/doi/10.1145/3358960.3375791)
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::change_points::e_divisive;
+/// let data_1 = [0., 0., 0., 0., 0., 0., 1., 1., 1., 1., 1., 5., 5., 5., 5., 5.];
+/// let cp = e_divisive(&data_1, 1, 3, 0.05, 100);
+/// assert_eq!(cp.iter().map(|c| c.index).collect::<Vec<_>>(), vec![5, 10]);
+/// assert!(cp.iter().all(|c| c.p_value <= 0.05));
+/// ```
+//#[no_mangle]
+pub fn e_divisive<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    signal: &[X],
+    jump: usize,
+    min_size: usize,
+    p_value: Y,
+    permutations: usize,
+) -> Vec<ChangePoint> {
+    try_e_divisive(signal, jump, min_size, p_value, permutations)
+        .unwrap_or_else(|e| panic!("Cannot detect change points: {}", e))
+}
+
+/// Same as `e_divisive`, but returns an error for an empty signal, a `jump` or `min_size` of 0
+/// or a `p_value` outside [0, 1].
+pub fn try_e_divisive<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    signal: &[X],
+    jump: usize,
+    min_size: usize,
+    p_value: Y,
+    permutations: usize,
+) -> Result<Vec<ChangePoint>> {
+    check_len(signal.len(), 1)?;
+    let p_value = p_value.into();
+    check_parameters(jump, min_size, p_value)?;
+    let signal = signal.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    let mut change_points = vec![];
+    let mut segments = vec![(0, signal.len())];
+    while let Some((start, end)) = segments.pop() {
+        let found = calculate_t_stats(&signal[start..end], jump, min_size, p_value, permutations);
+        if let Some(cp) = found {
+            let split = start + cp.index + 1;
+            change_points.push(ChangePoint { index: start + cp.index, ..cp });
+            segments.push((start, split));
+            segments.push((split, end));
+        }
+    }
+    change_points.sort_by_key(|cp| cp.index);
+    Ok(change_points)
+}
+
+/// `e_divisive` under a `NanPolicy`.
+///
+/// With `Propagate` a NaN makes every energy distance NaN so no change point is found, with
+/// `Skip` the detection runs on the non-NaN samples. Change points always index `signal`.
+pub fn e_divisive_with_nan_policy<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    signal: &[X],
+    jump: usize,
+    min_size: usize,
+    p_value: Y,
+    permutations: usize,
+    policy: NanPolicy,
+) -> Result<Vec<ChangePoint>> {
+    check_len(signal.len(), 1)?;
+    let (indices, values) = valid_samples(signal);
+    if indices.len() == signal.len() {
+        return try_e_divisive(&values, jump, min_size, p_value, permutations);
+    }
+    match policy {
+        NanPolicy::Propagate => check_parameters(jump, min_size, p_value.into()).map(|_| vec![]),
+        NanPolicy::Skip => Ok(try_e_divisive(&values, jump, min_size, p_value, permutations)?
+            .into_iter()
+            .map(|cp| ChangePoint { index: indices[cp.index], ..cp })
+            .collect()),
+        NanPolicy::Error => check_no_nan(signal).map(|_| vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::estimator::energy_distance;
+    use approx::assert_relative_eq;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn indices(change_points: &[ChangePoint]) -> Vec<usize> {
+        change_points.iter().map(|cp| cp.index).collect()
+    }
+
+    #[test]
+    fn test_best_split() {
+        let signal = [3.0, -1.0, 2.5, 2.5, 7.0, 0.0, 4.0, 8.0, 6.5, 9.0, 2.5];
+        let n = signal.len();
+        let ranked = ranked(&signal);
+        assert_eq!((ranked[2], ranked[3], ranked[10]), ((2, 2.5), (3, 2.5), (4, 2.5)));
+        // a jump of n leaves the single split after min_size samples
+        for k in 1..=n / 2 {
+            let (x, y) = signal.split_at(k);
+            let expected = energy_distance(x, y, true) * (k * (n - k)) as f64 / n as f64;
+            assert_eq!(best_split(&ranked, n, k).unwrap().0, k);
+            assert_relative_eq!(best_split(&ranked, n, k).unwrap().1, expected, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_calculate_t_stats() {
+        let data_1 = [0., 0., 0., 0., 0., 0., 1., 1., 1., 1., 1., 5., 5., 5., 5., 5.];
+        let cp = calculate_t_stats(&data_1, 1, 1, 0.05, 100).unwrap();
+        assert_eq!(cp.index, 10); // maybe 5
+        assert!(cp.statistic > 0.0 && cp.p_value <= 0.05);
+        // only every third split is tried
+        let data_1 = ranked(&data_1);
+        assert_eq!(best_split(&data_1, 3, 1).unwrap().0, 10);
+        assert_eq!(best_split(&data_1, 3, 2).unwrap().0, 11);
+        assert_eq!(best_split(&data_1, 1, 9), None);
+        assert!(calculate_t_stats(&[1.0; 16], 1, 1, 0.05, 100).is_none());
+    }
+
+    #[test]
+    fn test_try_e_divisive() {
+        let empty: Vec<f64> = vec![];
+        assert_eq!(
+            try_e_divisive(&empty, 3, 1, 0.05, 10),
+            Err(Error::InsufficientData { required: 1, actual: 0 })
+        );
+        assert!(matches!(
+            try_e_divisive(&[1.0, 2.0], 3, 1, 1.5, 10),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            try_e_divisive(&[1.0, 2.0], 0, 1, 0.05, 10),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            try_e_divisive(&[1.0, 2.0], 1, 0, 0.05, 10),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert_eq!(try_e_divisive(&[1.0, 2.0], 1, 2, 0.05, 10), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_e_divisive_with_nan_policy() {
+        let nan = f64::NAN;
+        let data_1 = [0., 0., 0., nan, 0., 0., 0., 1., 1., 1., 1., 1., nan, 5., 5., 5., 5., 5.];
+        let cp = e_divisive_with_nan_policy(&data_1, 1, 3, 0.05, 100, NanPolicy::Skip).unwrap();
+        assert_eq!(indices(&cp), vec![6, 11]);
+        assert_eq!(
+            e_divisive_with_nan_policy(&data_1, 1, 3, 0.05, 100, NanPolicy::Propagate),
+            Ok(vec![])
+        );
+        assert_eq!(
+            e_divisive_with_nan_policy(&data_1, 1, 3, 0.05, 100, NanPolicy::Error),
+            Err(Error::NanValue { index: 3 })
+        );
+    }
+
+    #[test]
+    fn test_e_divisive() {
+        let data_1 = [0., 0., 0., 0., 0., 0., 1., 1., 1., 1., 1., 5., 5., 5., 5., 5.];
+        let cp = e_divisive(&data_1, 1, 3, 0.05, 100);
+        assert_eq!(indices(&cp), vec![5, 10]);
+        let data_1 = [0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 5, 5, 5, 5, 5];
+        let cp = e_divisive(&data_1, 1, 3, 0.05, 100);
+        assert_eq!(indices(&cp), vec![5, 10]);
+        assert!(cp[0].statistic < cp[1].statistic);
+
+        // segments keep min_size samples, so the last five samples cannot be split off alone
+        let cp = e_divisive(&data_1, 1, 6, 0.05, 100);
+        assert_eq!(indices(&cp), vec![9]);
+        // with a jump of 4 the splits after 4, 8 and 12 samples are tried
+        let cp = e_divisive(&data_1, 4, 4, 0.05, 100);
+        assert!(indices(&cp).iter().all(|&i| i == 7 || i == 11));
+    }
+
+    #[test]
+    fn test_e_divisive_long() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let signal = (0..10_000)
+            .map(|i| rng.gen::<f64>() + if i < 6000 { 0.0 } else { 0.5 })
+            .collect::<Vec<f64>>();
+        let cp = e_divisive(&signal, 1, 30, 0.05, 99);
+        assert_eq!(indices(&cp), vec![5999]);
+    }
+}
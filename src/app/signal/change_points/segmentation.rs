@@ -0,0 +1,373 @@
+//! Penalized segmentation
+//!
+//! `pelt` finds the segmentation that minimizes the total segment cost plus a penalty per
+//! change point exactly, pruning split candidates that can no longer be optimal. The binary and
+//! bottom-up segmentations approximate it greedily by splitting or merging segments while that
+//! lowers the penalized cost.
+//!
+//! Like `e_divisive`, change points are the last indices of every segment but the last.
+//!
+//! Reference: [PELT](https://doi.org/10.1080/01621459.2012.737745)
+
+use super::cost::Cost;
+use crate::error::{check_len, Error, Result};
+
+/// Penalty per change point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Penalty {
+    /// `(p + 1) ln(n)` for the `p` parameters per segment and the location of the change
+    Bic,
+    /// Modified BIC of Zhang and Siegmund, `(p + 2) ln(n)` plus `ln(len / n)` per segment
+    Mbic,
+    Manual(f64),
+}
+
+impl Penalty {
+    fn value(&self, n: usize, parameters: usize) -> f64 {
+        match self {
+            Penalty::Bic => (parameters + 1) as f64 * (n as f64).ln(),
+            Penalty::Mbic => (parameters + 2) as f64 * (n as f64).ln(),
+            Penalty::Manual(value) => *value,
+        }
+    }
+}
+
+/// Fitted cost with the MBIC segment term, the penalty and the effective minimum segment size.
+struct Problem<'a, C: Cost + ?Sized> {
+    cost: &'a C,
+    n: usize,
+    penalty: f64,
+    mbic: bool,
+    min_size: usize,
+}
+
+impl<C: Cost + ?Sized> Problem<'_, C> {
+    fn segment(&self, start: usize, end: usize) -> f64 {
+        let cost = self.cost.segment(start, end);
+        if self.mbic {
+            cost + ((end - start) as f64 / self.n as f64).ln()
+        } else {
+            cost
+        }
+    }
+}
+
+fn setup<'a, T: Into<f64> + Copy, C: Cost + ?Sized>(
+    signal: &[T],
+    cost: &'a mut C,
+    penalty: Penalty,
+    min_size: usize,
+) -> Result<Problem<'a, C>> {
+    if min_size == 0 {
+        return Err(Error::InvalidParameter("min_size must be positive".to_string()));
+    }
+    if let Penalty::Manual(value) = penalty {
+        if value.is_nan() || value < 0.0 {
+            return Err(Error::InvalidParameter("penalty must not be negative".to_string()));
+        }
+    }
+    let min_size = min_size.max(cost.min_size());
+    check_len(signal.len(), min_size)?;
+    cost.fit(&signal.iter().map(|&x| x.into()).collect::<Vec<f64>>())?;
+    let n = signal.len();
+    Ok(Problem {
+        penalty: penalty.value(n, cost.parameters()),
+        cost,
+        n,
+        mbic: penalty == Penalty::Mbic,
+        min_size,
+    })
+}
+
+/// Change points of the segment boundaries `ends`, the exclusive ends of the segments.
+fn to_change_points(mut ends: Vec<usize>, n: usize) -> Vec<usize> {
+    ends.sort();
+    ends.into_iter().filter(|&end| end < n).map(|end| end - 1).collect()
+}
+
+/// Exact penalized segmentation by Pruned Exact Linear Time.
+///
+/// Segments are at least `min_size` samples long, or the minimum size of `cost` if larger.
+/// Returns an error if the signal is shorter than that or contains NaN, `min_size` is 0, a
+/// manual penalty is negative, or `cost` cannot be fitted.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::change_points::cost::L2;
+/// use mathbox::app::signal::change_points::segmentation::{pelt, Penalty};
+/// let signal = [0.1, -0.2, 0.0, 0.2, -0.1, 5.1, 4.8, 5.0, 5.2, 4.9, 1.0, 1.2, 0.9, 1.1, 0.8];
+/// assert_eq!(pelt(&signal, &mut L2::new(), Penalty::Bic, 2).unwrap(), vec![4, 9]);
+/// ```
+pub fn pelt<T: Into<f64> + Copy, C: Cost + ?Sized>(
+    signal: &[T],
+    cost: &mut C,
+    penalty: Penalty,
+    min_size: usize,
+) -> Result<Vec<usize>> {
+    let problem = setup(signal, cost, penalty, min_size)?;
+    let (n, m) = (problem.n, problem.min_size);
+    // best[t] is the optimal penalized cost of the first t samples, last[t] its last segment start
+    let mut best = vec![f64::INFINITY; n + 1];
+    let mut last = vec![0; n + 1];
+    best[0] = -problem.penalty;
+    // segment starts with the end from which they are pruned
+    let mut candidates: Vec<(usize, usize)> = vec![];
+    for t in m..=n {
+        let newest = t - m;
+        if newest == 0 || newest >= m {
+            candidates.push((newest, usize::MAX));
+        }
+        candidates.retain(|&(_, pruned)| pruned > t);
+        let costs =
+            candidates.iter().map(|&(s, _)| best[s] + problem.segment(s, t)).collect::<Vec<f64>>();
+        for (&(s, _), &c) in candidates.iter().zip(costs.iter()) {
+            if c + problem.penalty < best[t] {
+                best[t] = c + problem.penalty;
+                last[t] = s;
+            }
+        }
+        // a start beaten at t loses to a last segment starting at t, which is only allowed for
+        // ends from t + m on
+        for ((_, pruned), c) in candidates.iter_mut().zip(costs) {
+            if c > best[t] {
+                *pruned = (*pruned).min(t + m);
+            }
+        }
+    }
+    let mut ends = vec![];
+    let mut t = n;
+    while t > 0 {
+        ends.push(t);
+        t = last[t];
+    }
+    Ok(to_change_points(ends, n))
+}
+
+/// Best split of `start..end` into two segments and the decrease of the cost by it.
+fn best_split<C: Cost + ?Sized>(
+    problem: &Problem<C>,
+    start: usize,
+    end: usize,
+) -> Option<(usize, f64)> {
+    let m = problem.min_size;
+    if end - start < 2 * m {
+        return None;
+    }
+    let whole = problem.segment(start, end);
+    (start + m..=end - m)
+        .map(|k| (k, whole - problem.segment(start, k) - problem.segment(k, end)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Binary segmentation: split the segment whose best split lowers the cost the most while that
+/// is by more than the penalty.
+///
+/// Takes the same arguments as `pelt`.
+pub fn binary_segmentation<T: Into<f64> + Copy, C: Cost + ?Sized>(
+    signal: &[T],
+    cost: &mut C,
+    penalty: Penalty,
+    min_size: usize,
+) -> Result<Vec<usize>> {
+    let problem = setup(signal, cost, penalty, min_size)?;
+    let mut ends = vec![];
+    let mut segments = vec![(0, problem.n)];
+    while let Some((start, end)) = segments.pop() {
+        if let Some((k, gain)) = best_split(&problem, start, end) {
+            if gain > problem.penalty {
+                ends.push(k);
+                segments.push((start, k));
+                segments.push((k, end));
+            }
+        }
+    }
+    Ok(to_change_points(ends, problem.n))
+}
+
+/// Bottom-up segmentation: start from segments of the minimum size and merge the neighbours
+/// whose merge raises the cost the least while that is by less than the penalty.
+///
+/// Takes the same arguments as `pelt`.
+pub fn bottom_up<T: Into<f64> + Copy, C: Cost + ?Sized>(
+    signal: &[T],
+    cost: &mut C,
+    penalty: Penalty,
+    min_size: usize,
+) -> Result<Vec<usize>> {
+    let problem = setup(signal, cost, penalty, min_size)?;
+    let (n, m) = (problem.n, problem.min_size);
+    // boundaries of the segments, the last segment takes the remainder
+    let mut bounds = (0..n / m).map(|i| i * m).collect::<Vec<usize>>();
+    bounds.push(n);
+    let merge_cost = |a: usize, b: usize, c: usize| {
+        problem.segment(a, c) - problem.segment(a, b) - problem.segment(b, c)
+    };
+    // costs[i] is the cost of merging the segments around bounds[i + 1]
+    let mut costs = bounds.windows(3).map(|w| merge_cost(w[0], w[1], w[2])).collect::<Vec<f64>>();
+    while let Some((i, &increase)) = costs.iter().enumerate().min_by(|a, b| a.1.total_cmp(b.1)) {
+        if increase >= problem.penalty {
+            break;
+        }
+        bounds.remove(i + 1);
+        costs.remove(i);
+        if i > 0 {
+            costs[i - 1] = merge_cost(bounds[i - 1], bounds[i], bounds[i + 1]);
+        }
+        if i < costs.len() {
+            costs[i] = merge_cost(bounds[i], bounds[i + 1], bounds[i + 2]);
+        }
+    }
+    Ok(to_change_points(bounds[1..].to_vec(), n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::signal::change_points::cost::{NormalMeanVar, Poisson, Rbf, L2};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn piecewise(levels: &[(usize, f64, f64)], seed: u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        levels
+            .iter()
+            .flat_map(|&(len, mean, scale)| vec![(mean, scale); len])
+            .map(|(mean, scale)| mean + scale * rng.gen_range(-1.0..1.0))
+            .collect()
+    }
+
+    /// Optimal partitioning without pruning.
+    fn optimal<C: Cost>(signal: &[f64], cost: &mut C, penalty: f64, m: usize) -> Vec<usize> {
+        let m = m.max(cost.min_size());
+        cost.fit(signal).unwrap();
+        let n = signal.len();
+        let mut best = vec![f64::INFINITY; n + 1];
+        let mut last = vec![0; n + 1];
+        best[0] = -penalty;
+        for t in m..=n {
+            for s in (0..=t - m).filter(|&s| s == 0 || s >= m) {
+                let c = best[s] + cost.segment(s, t) + penalty;
+                if c < best[t] {
+                    best[t] = c;
+                    last[t] = s;
+                }
+            }
+        }
+        let mut ends = vec![];
+        let mut t = n;
+        while t > 0 {
+            ends.push(t);
+            t = last[t];
+        }
+        to_change_points(ends, n)
+    }
+
+    #[test]
+    fn test_pelt() {
+        let signal = piecewise(&[(100, 0.0, 1.0), (80, 3.0, 1.0), (120, -1.0, 1.0)], 1);
+        assert_eq!(pelt(&signal, &mut L2::new(), Penalty::Bic, 5).unwrap(), vec![99, 179]);
+        assert_eq!(pelt(&signal, &mut L2::new(), Penalty::Mbic, 5).unwrap(), vec![99, 179]);
+        for (penalty, m) in [(2.0, 1), (5.0, 3), (20.0, 10)] {
+            assert_eq!(
+                pelt(&signal, &mut L2::new(), Penalty::Manual(penalty), m).unwrap(),
+                optimal(&signal, &mut L2::new(), penalty, m)
+            );
+        }
+        let result = pelt(&signal, &mut L2::new(), Penalty::Manual(0.0), 7).unwrap();
+        let mut ends = result.iter().map(|i| i + 1).collect::<Vec<usize>>();
+        ends.insert(0, 0);
+        ends.push(signal.len());
+        assert!(ends.windows(2).all(|w| w[1] - w[0] >= 7));
+
+        // a change of the variance only
+        let signal = piecewise(&[(150, 0.0, 0.5), (150, 0.0, 3.0)], 2);
+        assert_eq!(pelt(&signal, &mut NormalMeanVar::new(), Penalty::Bic, 10).unwrap(), vec![149]);
+        let result = pelt(&signal, &mut Rbf::new(None), Penalty::Manual(10.0), 5).unwrap();
+        assert_eq!(result.len(), 1);
+        assert!(result[0].abs_diff(149) <= 5);
+
+        // Poisson counts by Knuth's multiplication of uniform samples
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut poisson = |rate: f64| {
+            let (limit, mut product, mut k) = ((-rate).exp(), 1.0, 0.0);
+            loop {
+                product *= rng.gen::<f64>();
+                if product <= limit {
+                    return k;
+                }
+                k += 1.0;
+            }
+        };
+        let counts =
+            (0..200).map(|i| poisson(if i < 100 { 2.0 } else { 6.0 })).collect::<Vec<f64>>();
+        assert_eq!(pelt(&counts, &mut Poisson::new(), Penalty::Bic, 10).unwrap(), vec![99]);
+    }
+
+    #[test]
+    fn test_pelt_is_optimal() {
+        // short noisy signals where the minimum size matters for pruning
+        let mut rng = StdRng::seed_from_u64(6);
+        for _ in 0..300 {
+            let n = rng.gen_range(8..30);
+            let signal = (0..n).map(|_| rng.gen_range(-2.0..2.0)).collect::<Vec<f64>>();
+            let penalty = rng.gen_range(0.5..5.0);
+            let m = rng.gen_range(1..6);
+            assert_eq!(
+                pelt(&signal, &mut L2::new(), Penalty::Manual(penalty), m).unwrap(),
+                optimal(&signal, &mut L2::new(), penalty, m)
+            );
+            assert_eq!(
+                pelt(&signal, &mut NormalMeanVar::new(), Penalty::Manual(penalty), m).unwrap(),
+                optimal(&signal, &mut NormalMeanVar::new(), penalty, m)
+            );
+        }
+    }
+
+    #[test]
+    fn test_greedy_segmentation() {
+        let signal = piecewise(&[(100, 0.0, 1.0), (80, 3.0, 1.0), (120, -1.0, 1.0)], 4);
+        assert_eq!(
+            binary_segmentation(&signal, &mut L2::new(), Penalty::Bic, 5).unwrap(),
+            vec![99, 179]
+        );
+        assert_eq!(bottom_up(&signal, &mut L2::new(), Penalty::Bic, 5).unwrap(), vec![99, 179]);
+        let signal = piecewise(&[(150, 0.0, 0.5), (150, 0.0, 3.0)], 5);
+        let mut cost = NormalMeanVar::new();
+        // a change of the variance is located less precisely than one of the mean
+        for result in [
+            binary_segmentation(&signal, &mut cost, Penalty::Bic, 10).unwrap(),
+            bottom_up(&signal, &mut cost, Penalty::Bic, 10).unwrap(),
+        ] {
+            assert_eq!(result.len(), 1);
+            assert!(result[0].abs_diff(149) <= 5);
+        }
+        assert_eq!(bottom_up(&[1.0, 2.0, 3.0], &mut L2::new(), Penalty::Bic, 2).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_segmentation_errors() {
+        let signal = [1.0, 2.0, 3.0];
+        assert!(matches!(
+            pelt(&signal, &mut L2::new(), Penalty::Bic, 0),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            binary_segmentation(&signal, &mut L2::new(), Penalty::Manual(-1.0), 1),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert_eq!(
+            bottom_up(&signal, &mut L2::new(), Penalty::Bic, 4),
+            Err(Error::InsufficientData { required: 4, actual: 3 })
+        );
+        assert_eq!(
+            pelt(&[1.0], &mut NormalMeanVar::new(), Penalty::Bic, 1),
+            Err(Error::InsufficientData { required: 2, actual: 1 })
+        );
+        assert_eq!(
+            pelt(&[1.0, f64::NAN], &mut L2::new(), Penalty::Bic, 1),
+            Err(Error::NanValue { index: 1 })
+        );
+        assert_eq!(pelt(&[4.0], &mut L2::new(), Penalty::Bic, 1), Ok(vec![]));
+    }
+}
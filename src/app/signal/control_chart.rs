@@ -0,0 +1,373 @@
+//! Statistical process control
+//!
+//! Control charts watch a process that should stay at a `target` mean with a standard deviation
+//! `sigma` and raise an alarm when it drifts away. A Shewhart chart flags single samples beyond
+//! a limit, while the CUSUM and EWMA charts accumulate the deviations and detect small shifts
+//! much sooner. Charts are updated one sample at a time, and `monitor` runs a chart over a
+//! slice.
+//!
+//! After an alarm the chart starts over, and the run length of the next alarm counts the samples
+//! from there.
+
+use crate::error::{Error, Result};
+
+/// Which deviations from the target raise an alarm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Lower,
+    Upper,
+    Both,
+}
+
+impl Side {
+    fn lower(&self) -> bool {
+        matches!(self, Side::Lower | Side::Both)
+    }
+
+    fn upper(&self) -> bool {
+        matches!(self, Side::Upper | Side::Both)
+    }
+}
+
+/// An out-of-control signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alarm {
+    /// Index of the sample, counting every sample the chart has seen
+    pub index: usize,
+    /// Samples since the start or the previous alarm, including this one
+    pub run_length: usize,
+    /// `Lower` or `Upper`
+    pub side: Side,
+}
+
+/// A control chart that is updated one sample at a time.
+pub trait ControlChart {
+    /// Update the chart with the next sample, returns an error for a NaN.
+    fn update(&mut self, x: f64) -> Result<Option<Alarm>>;
+
+    /// Forget all samples.
+    fn reset(&mut self);
+}
+
+/// Run `chart` over `signal` and collect its alarms.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::control_chart::{monitor, Cusum};
+/// let signal = [0.2, -0.4, 0.1, 0.3, -0.1, 1.2, 0.9, 1.4, 1.1, 1.3];
+/// let mut chart = Cusum::new(0.0, 0.5, 0.5, 4.0).unwrap();
+/// let alarms = monitor(&mut chart, &signal).unwrap();
+/// assert_eq!(alarms.iter().map(|a| a.index).collect::<Vec<_>>(), vec![7]);
+/// ```
+pub fn monitor<C: ControlChart, T: Into<f64> + Copy>(
+    chart: &mut C,
+    signal: &[T],
+) -> Result<Vec<Alarm>> {
+    let mut alarms = vec![];
+    for &x in signal {
+        if let Some(alarm) = chart.update(x.into())? {
+            alarms.push(alarm);
+        }
+    }
+    Ok(alarms)
+}
+
+fn check_process(sigma: f64) -> Result<()> {
+    if sigma.is_nan() || sigma <= 0.0 {
+        return Err(Error::InvalidParameter("sigma must be positive".to_string()));
+    }
+    Ok(())
+}
+
+/// Samples seen and the start of the current run.
+#[derive(Debug, Clone, Copy, Default)]
+struct Counter {
+    seen: usize,
+    run_start: usize,
+}
+
+impl Counter {
+    fn next(&mut self, x: f64) -> Result<usize> {
+        if x.is_nan() {
+            return Err(Error::NanValue { index: self.seen });
+        }
+        self.seen += 1;
+        Ok(self.seen - 1)
+    }
+
+    fn alarm(&mut self, index: usize, side: Side) -> Alarm {
+        let run_length = index + 1 - self.run_start;
+        self.run_start = index + 1;
+        Alarm { index, run_length, side }
+    }
+}
+
+/// Shewhart chart, alarming when a sample is more than `limit` standard deviations from the
+/// target.
+#[derive(Debug, Clone)]
+pub struct Shewhart {
+    target: f64,
+    sigma: f64,
+    limit: f64,
+    side: Side,
+    counter: Counter,
+}
+
+impl Shewhart {
+    /// Returns an error unless `sigma` and `limit` are positive.
+    pub fn new(target: f64, sigma: f64, limit: f64) -> Result<Self> {
+        check_process(sigma)?;
+        if limit.is_nan() || limit <= 0.0 {
+            return Err(Error::InvalidParameter("limit must be positive".to_string()));
+        }
+        Ok(Shewhart { target, sigma, limit, side: Side::Both, counter: Counter::default() })
+    }
+
+    pub fn with_side(mut self, side: Side) -> Self {
+        self.side = side;
+        self
+    }
+}
+
+impl ControlChart for Shewhart {
+    fn update(&mut self, x: f64) -> Result<Option<Alarm>> {
+        let index = self.counter.next(x)?;
+        let z = (x - self.target) / self.sigma;
+        Ok(if self.side.upper() && z > self.limit {
+            Some(self.counter.alarm(index, Side::Upper))
+        } else if self.side.lower() && z < -self.limit {
+            Some(self.counter.alarm(index, Side::Lower))
+        } else {
+            None
+        })
+    }
+
+    fn reset(&mut self) {
+        self.counter = Counter::default();
+    }
+}
+
+/// Tabular CUSUM chart.
+///
+/// The upper sum accumulates the standardized deviations above the target minus the allowance
+/// `k`, the lower sum those below it, and an alarm is raised when a sum exceeds the decision
+/// interval `h`. Both are in units of `sigma`; `k` is usually half the shift to detect and `h`
+/// 4 or 5.
+#[derive(Debug, Clone)]
+pub struct Cusum {
+    target: f64,
+    sigma: f64,
+    k: f64,
+    h: f64,
+    side: Side,
+    upper: f64,
+    lower: f64,
+    counter: Counter,
+}
+
+impl Cusum {
+    /// Returns an error unless `sigma` and `h` are positive and `k` is not negative.
+    pub fn new(target: f64, sigma: f64, k: f64, h: f64) -> Result<Self> {
+        check_process(sigma)?;
+        if k.is_nan() || k < 0.0 || h.is_nan() || h <= 0.0 {
+            return Err(Error::InvalidParameter(
+                "k must not be negative and h must be positive".to_string(),
+            ));
+        }
+        Ok(Cusum {
+            target,
+            sigma,
+            k,
+            h,
+            side: Side::Both,
+            upper: 0.0,
+            lower: 0.0,
+            counter: Counter::default(),
+        })
+    }
+
+    /// One-sided chart with `Lower` or `Upper`.
+    pub fn with_side(mut self, side: Side) -> Self {
+        self.side = side;
+        self
+    }
+
+    /// The current (lower, upper) sums, in units of `sigma`.
+    pub fn sums(&self) -> (f64, f64) {
+        (self.lower, self.upper)
+    }
+}
+
+impl ControlChart for Cusum {
+    fn update(&mut self, x: f64) -> Result<Option<Alarm>> {
+        let index = self.counter.next(x)?;
+        let z = (x - self.target) / self.sigma;
+        self.upper = (self.upper + z - self.k).max(0.0);
+        self.lower = (self.lower - z - self.k).max(0.0);
+        let side = if self.side.upper() && self.upper > self.h {
+            Side::Upper
+        } else if self.side.lower() && self.lower > self.h {
+            Side::Lower
+        } else {
+            return Ok(None);
+        };
+        (self.upper, self.lower) = (0.0, 0.0);
+        Ok(Some(self.counter.alarm(index, side)))
+    }
+
+    fn reset(&mut self) {
+        (self.upper, self.lower) = (0.0, 0.0);
+        self.counter = Counter::default();
+    }
+}
+
+/// EWMA chart.
+///
+/// The chart smooths the samples by `z = lambda x + (1 - lambda) z`, starting from the target,
+/// and raises an alarm when `z` is more than `limit` of its standard deviations from the target.
+/// The standard deviation of `z` grows towards `sigma sqrt(lambda / (2 - lambda))` over the
+/// first samples of a run.
+#[derive(Debug, Clone)]
+pub struct Ewma {
+    target: f64,
+    sigma: f64,
+    lambda: f64,
+    limit: f64,
+    side: Side,
+    value: f64,
+    counter: Counter,
+}
+
+impl Ewma {
+    /// Returns an error unless `sigma` and `limit` are positive and `lambda` is in (0, 1].
+    pub fn new(target: f64, sigma: f64, lambda: f64, limit: f64) -> Result<Self> {
+        check_process(sigma)?;
+        if !(lambda > 0.0 && lambda <= 1.0) {
+            return Err(Error::InvalidParameter("lambda must be in (0, 1]".to_string()));
+        }
+        if limit.is_nan() || limit <= 0.0 {
+            return Err(Error::InvalidParameter("limit must be positive".to_string()));
+        }
+        Ok(Ewma {
+            target,
+            sigma,
+            lambda,
+            limit,
+            side: Side::Both,
+            value: target,
+            counter: Counter::default(),
+        })
+    }
+
+    pub fn with_side(mut self, side: Side) -> Self {
+        self.side = side;
+        self
+    }
+
+    /// The current smoothed value.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+impl ControlChart for Ewma {
+    fn update(&mut self, x: f64) -> Result<Option<Alarm>> {
+        let index = self.counter.next(x)?;
+        self.value = self.lambda * x + (1.0 - self.lambda) * self.value;
+        let t = (index + 1 - self.counter.run_start) as i32;
+        let variance = self.lambda / (2.0 - self.lambda) * (1.0 - (1.0 - self.lambda).powi(2 * t));
+        let z = (self.value - self.target) / (self.sigma * variance.sqrt());
+        let side = if self.side.upper() && z > self.limit {
+            Side::Upper
+        } else if self.side.lower() && z < -self.limit {
+            Side::Lower
+        } else {
+            return Ok(None);
+        };
+        self.value = self.target;
+        Ok(Some(self.counter.alarm(index, side)))
+    }
+
+    fn reset(&mut self) {
+        self.value = self.target;
+        self.counter = Counter::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_shewhart() {
+        let signal = [0.0, 3.5, -1.0, -3.2, 2.9, 4.0];
+        let mut chart = Shewhart::new(0.0, 1.0, 3.0).unwrap();
+        let alarms = monitor(&mut chart, &signal).unwrap();
+        assert_eq!(
+            alarms,
+            vec![
+                Alarm { index: 1, run_length: 2, side: Side::Upper },
+                Alarm { index: 3, run_length: 2, side: Side::Lower },
+                Alarm { index: 5, run_length: 2, side: Side::Upper },
+            ]
+        );
+        let mut chart = Shewhart::new(0.0, 1.0, 3.0).unwrap().with_side(Side::Lower);
+        assert_eq!(monitor(&mut chart, &signal).unwrap().len(), 1);
+        assert_eq!(chart.update(f64::NAN), Err(Error::NanValue { index: 6 }));
+        assert!(matches!(Shewhart::new(0.0, 0.0, 3.0), Err(Error::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_cusum() {
+        let mut chart = Cusum::new(10.0, 2.0, 0.5, 2.0).unwrap();
+        chart.update(13.0).unwrap();
+        assert_eq!(chart.sums(), (0.0, 1.0));
+        chart.update(7.0).unwrap();
+        assert_eq!(chart.sums(), (1.0, 0.0));
+        assert_eq!(chart.update(13.0).unwrap(), None);
+        let alarm = chart.update(15.0).unwrap().unwrap();
+        assert_eq!(alarm, Alarm { index: 3, run_length: 4, side: Side::Upper });
+        assert_eq!(chart.sums(), (0.0, 0.0));
+
+        // a shift of one sigma is missed by the Shewhart chart but caught by CUSUM
+        let signal = (0..60)
+            .map(|i| if i < 30 { 0.0 } else { 1.0 } + if i % 2 == 0 { 0.5 } else { -0.5 })
+            .collect::<Vec<f64>>();
+        let mut chart = Cusum::new(0.0, 1.0, 0.5, 4.0).unwrap();
+        let alarms = monitor(&mut chart, &signal).unwrap();
+        assert_eq!(alarms[0], Alarm { index: 38, run_length: 39, side: Side::Upper });
+        assert!(monitor(&mut Shewhart::new(0.0, 1.0, 3.0).unwrap(), &signal).unwrap().is_empty());
+        let mut chart = Cusum::new(0.0, 1.0, 0.5, 4.0).unwrap().with_side(Side::Lower);
+        assert!(monitor(&mut chart, &signal).unwrap().is_empty());
+        assert!(matches!(Cusum::new(0.0, 1.0, -0.5, 4.0), Err(Error::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_ewma() {
+        let mut chart = Ewma::new(0.0, 1.0, 0.2, 3.0).unwrap();
+        chart.update(1.0).unwrap();
+        assert_relative_eq!(chart.value(), 0.2);
+        chart.update(-1.0).unwrap();
+        assert_relative_eq!(chart.value(), -0.04, epsilon = 1e-12);
+        // the limit of the first sample is 3 lambda sigma
+        let mut chart = Ewma::new(0.0, 1.0, 0.2, 3.0).unwrap();
+        assert_eq!(chart.update(2.9).unwrap(), None);
+        chart.reset();
+        assert_eq!(
+            chart.update(3.1).unwrap(),
+            Some(Alarm { index: 0, run_length: 1, side: Side::Upper })
+        );
+        assert_eq!(chart.value(), 0.0);
+
+        let signal = (0..60)
+            .map(|i| if i < 30 { 0.0 } else { -1.5 } + if i % 2 == 0 { 0.5 } else { -0.5 })
+            .collect::<Vec<f64>>();
+        let mut chart = Ewma::new(0.0, 1.0, 0.2, 3.0).unwrap();
+        let alarms = monitor(&mut chart, &signal).unwrap();
+        assert_eq!(alarms[0].side, Side::Lower);
+        assert!(alarms[0].index > 30 && alarms[0].index < 40);
+        assert!(matches!(Ewma::new(0.0, 1.0, 0.0, 3.0), Err(Error::InvalidParameter(_))));
+    }
+}
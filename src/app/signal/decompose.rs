@@ -1,40 +1,485 @@
-/*
-pub struct Decomposor {
-    pub n: usize,
-    pub dt: f64,
-    pub freq: Vec<f64>,
-    pub dft: Vec<Complex<f64>>,
-    pub rdft: Vec<Complex<f64>>,
+//! Seasonal-trend decomposition
+//!
+//! `stl` splits a signal into trend, seasonal and residual components with STL, the
+//! LOESS-based procedure of Cleveland et al. `seasonal_decompose` is the classical moving
+//! average decomposition, like `seasonal_decompose` from Python statsmodels.
+//!
+//! Reference: [STL](https://www.wessa.net/download/stl.pdf)
+
+use crate::app::signal::filter::try_moving_average;
+use crate::error::{check_len, check_no_nan, Error, Result};
+use crate::stats::estimator::try_median;
+
+/// Components of a decomposed signal, `trend + seasonal + residual` is the signal for an
+/// additive decomposition and `trend * seasonal * residual` for a multiplicative one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decomposition {
+    pub trend: Vec<f64>,
+    pub seasonal: Vec<f64>,
+    pub residual: Vec<f64>,
 }
 
-impl Default for Decomposor {
+/// Settings of `stl`. Windows are odd numbers of samples, degrees are 0 or 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StlOptions {
+    /// LOESS window over the cycle-subseries, in periods
+    pub seasonal_window: usize,
+    /// LOESS window of the trend, the smallest odd number not below
+    /// `1.5 * period / (1 - 1.5 / seasonal_window)` when `None`
+    pub trend_window: Option<usize>,
+    /// LOESS window of the low-pass filter, the smallest odd number not below `period` when
+    /// `None`
+    pub low_pass_window: Option<usize>,
+    pub seasonal_degree: usize,
+    pub trend_degree: usize,
+    pub low_pass_degree: usize,
+    /// Passes of the inner loop updating the seasonal and the trend component
+    pub inner_iterations: usize,
+    /// Passes of the outer loop reweighting samples by their residuals, 0 for a non-robust fit
+    pub robust_iterations: usize,
+}
+
+impl Default for StlOptions {
     fn default() -> Self {
-        Decomposor {
-            n: 0,
-            dt: 0.0,
-            freq: vec![],
-            dft: vec![],
-            rdft: vec![],
+        StlOptions {
+            seasonal_window: 7,
+            trend_window: None,
+            low_pass_window: None,
+            seasonal_degree: 1,
+            trend_degree: 1,
+            low_pass_degree: 1,
+            inner_iterations: 2,
+            robust_iterations: 0,
+        }
+    }
+}
+
+impl StlOptions {
+    /// Options of a robust fit, one inner pass per robustness pass as recommended by STL.
+    pub fn robust() -> Self {
+        StlOptions { inner_iterations: 1, robust_iterations: 15, ..Default::default() }
+    }
+}
+
+fn next_odd(x: f64) -> usize {
+    let x = x.ceil() as usize;
+    if x.is_multiple_of(2) {
+        x + 1
+    } else {
+        x
+    }
+}
+
+fn check_window(name: &str, window: usize) -> Result<()> {
+    if window < 3 || window.is_multiple_of(2) {
+        return Err(Error::InvalidParameter(format!("{} must be odd and at least 3", name)));
+    }
+    Ok(())
+}
+
+fn check_degree(name: &str, degree: usize) -> Result<()> {
+    if degree > 1 {
+        return Err(Error::InvalidParameter(format!("{} must be 0 or 1", name)));
+    }
+    Ok(())
+}
+
+/// LOESS estimate at position `x` of the samples `y` at positions `0..y.len()`, weighted by
+/// `weights`.
+///
+/// Returns `None` if no sample in the window has a positive weight.
+fn loess_point(y: &[f64], weights: &[f64], x: f64, window: usize, degree: usize) -> Option<f64> {
+    let n = y.len();
+    let q = window.min(n);
+    let left = (x.round() as isize - (q as isize - 1) / 2).clamp(0, (n - q) as isize) as usize;
+    let right = left + q - 1;
+    let mut h = (x - left as f64).max(right as f64 - x);
+    if window > n {
+        h += ((window - n) / 2) as f64;
+    }
+    let mut w = vec![0.0; q];
+    for (j, wj) in w.iter_mut().enumerate() {
+        let r = (left as f64 + j as f64 - x).abs();
+        if r <= 0.999 * h {
+            *wj = if r <= 0.001 * h { 1.0 } else { (1.0 - (r / h).powi(3)).powi(3) };
+            *wj *= weights[left + j];
+        }
+    }
+    let total = w.iter().sum::<f64>();
+    if total <= 0.0 {
+        return None;
+    }
+    w.iter_mut().for_each(|wj| *wj /= total);
+    if degree > 0 && h > 0.0 {
+        let center = w.iter().enumerate().map(|(j, wj)| wj * (left + j) as f64).sum::<f64>();
+        let spread = w
+            .iter()
+            .enumerate()
+            .map(|(j, wj)| wj * ((left + j) as f64 - center).powi(2))
+            .sum::<f64>();
+        if spread.sqrt() > 0.001 * (n - 1) as f64 {
+            let slope = (x - center) / spread;
+            for (j, wj) in w.iter_mut().enumerate() {
+                *wj *= slope * ((left + j) as f64 - center) + 1.0;
+            }
+        }
+    }
+    Some(w.iter().zip(y[left..=right].iter()).map(|(wj, yj)| wj * yj).sum())
+}
+
+/// LOESS smoothing of `y`, fitted every `jump` samples and linearly interpolated in between.
+fn loess(y: &[f64], weights: &[f64], window: usize, degree: usize, jump: usize) -> Vec<f64> {
+    let n = y.len();
+    let mut fitted = (0..n).step_by(jump).collect::<Vec<usize>>();
+    if fitted[fitted.len() - 1] != n - 1 {
+        fitted.push(n - 1);
+    }
+    let mut smooth = vec![0.0; n];
+    for &i in fitted.iter() {
+        smooth[i] = loess_point(y, weights, i as f64, window, degree).unwrap_or(y[i]);
+    }
+    for pair in fitted.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let slope = (smooth[b] - smooth[a]) / (b - a) as f64;
+        for i in a + 1..b {
+            smooth[i] = smooth[a] + slope * (i - a) as f64;
         }
     }
+    smooth
+}
+
+/// Smooth every cycle-subseries of `y` and extend it by one period on both ends.
+fn cycle_subseries(
+    y: &[f64],
+    weights: &[f64],
+    period: usize,
+    window: usize,
+    degree: usize,
+) -> Vec<f64> {
+    let n = y.len();
+    let jump = window.div_ceil(10);
+    let mut cycle = vec![0.0; n + 2 * period];
+    for phase in 0..period {
+        let sub_y = y[phase..].iter().step_by(period).copied().collect::<Vec<f64>>();
+        let sub_w = weights[phase..].iter().step_by(period).copied().collect::<Vec<f64>>();
+        let m = sub_y.len();
+        let smooth = loess(&sub_y, &sub_w, window, degree, jump);
+        let before = loess_point(&sub_y, &sub_w, -1.0, window, degree).unwrap_or(smooth[0]);
+        let after = loess_point(&sub_y, &sub_w, m as f64, window, degree).unwrap_or(smooth[m - 1]);
+        cycle[phase] = before;
+        for (j, value) in smooth.into_iter().enumerate() {
+            cycle[phase + (j + 1) * period] = value;
+        }
+        cycle[phase + (m + 1) * period] = after;
+    }
+    cycle
+}
+
+/// Moving average over `window` samples without the partial windows at the start.
+fn full_moving_average(signal: &[f64], window: usize) -> Result<Vec<f64>> {
+    Ok(try_moving_average(signal, window)?.split_off(window - 1))
 }
 
-pub fn decompose(signal: &[f64], dt: f64, config: Decomposor) -> (Vec<f64>, Vec<f64>) {
-    let n = signal.len();
-    let val = 1.0 / (n as f64 * dt);
-    let mut result: Vec<f64> = vec![];
-    let mid = (n - 1) / 2 + 1;
-    result.append(&mut (0..mid).map(|x| val * x as f64).collect::<Vec<f64>>());
-    result.append(&mut (-(n as isize / 2)..0).map(|x| val * x as f64).collect::<Vec<f64>>());
-    let mut dft_signal: Vec<Complex<f64>> = vec![];
-    for i in 0..signal.len() {
-        dft_signal.push(Complex::new(signal[i], 0.0));
+/// Decompose `signal` with STL, `period` is the number of samples of one seasonal cycle.
+///
+/// Returns an error if the signal is shorter than two periods or contains NaN, or if a
+/// window or degree in `options` is invalid.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::decompose::{stl, StlOptions};
+/// let signal = (0..48).map(|i| 0.5 * i as f64 + [1.0, -1.0, 2.0, -2.0][i % 4]).collect::<Vec<_>>();
+/// let result = stl(&signal, 4, &StlOptions::default()).unwrap();
+/// assert!((result.seasonal[2] - 2.0).abs() < 1e-6);
+/// assert!((result.trend[20] - 10.0).abs() < 1e-6);
+/// ```
+pub fn stl<T: Into<f64> + Copy>(
+    signal: &[T],
+    period: usize,
+    options: &StlOptions,
+) -> Result<Decomposition> {
+    if period < 2 {
+        return Err(Error::InvalidParameter("period must be at least 2".to_string()));
     }
-    let dft_signal = dft(&dft_signal);
-    let mut dft_result: Vec<f64> = vec![];
-    for i in 0..dft_signal.len() {
-        dft_result.push(dft_signal[i].re);
+    check_len(signal.len(), 2 * period)?;
+    check_no_nan(signal)?;
+    let seasonal_window = options.seasonal_window;
+    let trend_window = options
+        .trend_window
+        .unwrap_or_else(|| next_odd(1.5 * period as f64 / (1.0 - 1.5 / seasonal_window as f64)));
+    let low_pass_window = options.low_pass_window.unwrap_or_else(|| next_odd(period as f64));
+    check_window("seasonal_window", seasonal_window)?;
+    check_window("trend_window", trend_window)?;
+    check_window("low_pass_window", low_pass_window)?;
+    check_degree("seasonal_degree", options.seasonal_degree)?;
+    check_degree("trend_degree", options.trend_degree)?;
+    check_degree("low_pass_degree", options.low_pass_degree)?;
+
+    let y = signal.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    let n = y.len();
+    let mut trend = vec![0.0; n];
+    let mut seasonal = vec![0.0; n];
+    let mut weights = vec![1.0; n];
+    let unit_weights = vec![1.0; n];
+    for outer in 0..=options.robust_iterations {
+        for _ in 0..options.inner_iterations {
+            let detrended = y.iter().zip(trend.iter()).map(|(a, b)| a - b).collect::<Vec<f64>>();
+            let cycle = cycle_subseries(
+                &detrended,
+                &weights,
+                period,
+                seasonal_window,
+                options.seasonal_degree,
+            );
+            let low_pass = full_moving_average(&cycle, period)?;
+            let low_pass = full_moving_average(&low_pass, period)?;
+            let low_pass = full_moving_average(&low_pass, 3)?;
+            let low_pass = loess(
+                &low_pass,
+                &unit_weights,
+                low_pass_window,
+                options.low_pass_degree,
+                low_pass_window.div_ceil(10),
+            );
+            for i in 0..n {
+                seasonal[i] = cycle[period + i] - low_pass[i];
+            }
+            let deseasonalized =
+                y.iter().zip(seasonal.iter()).map(|(a, b)| a - b).collect::<Vec<f64>>();
+            trend = loess(
+                &deseasonalized,
+                &weights,
+                trend_window,
+                options.trend_degree,
+                trend_window.div_ceil(10),
+            );
+        }
+        if outer == options.robust_iterations {
+            break;
+        }
+        let residual = (0..n).map(|i| (y[i] - trend[i] - seasonal[i]).abs()).collect::<Vec<f64>>();
+        let h = 6.0 * try_median(&residual)?;
+        for (w, r) in weights.iter_mut().zip(residual.iter()) {
+            *w = if *r <= 0.001 * h {
+                1.0
+            } else if *r <= 0.999 * h {
+                (1.0 - (r / h).powi(2)).powi(2)
+            } else {
+                0.0
+            };
+        }
+    }
+    let residual = (0..n).map(|i| y[i] - trend[i] - seasonal[i]).collect();
+    Ok(Decomposition { trend, seasonal, residual })
+}
+
+/// How the components of `seasonal_decompose` combine into the signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeasonalModel {
+    Additive,
+    /// For positive signals whose seasonal swing grows with the trend
+    Multiplicative,
+}
+
+/// Classical decomposition of `signal` with `period` samples per seasonal cycle.
+///
+/// The trend is a moving average centered on each sample, over `period` samples for an odd
+/// period and over `period + 1` samples with halved end weights for an even one, so it and
+/// the residual are NaN for the first and last `period / 2` samples. The seasonal component
+/// is the average detrended value of each phase, centered to sum to zero (additive) or
+/// average to one (multiplicative).
+///
+/// Returns an error if the signal is shorter than two periods or contains NaN, or if it is
+/// not positive for a multiplicative model.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::decompose::{seasonal_decompose, SeasonalModel};
+/// let signal = (0..12).map(|i| i as f64 + [1.0, -1.0, 2.0, -2.0][i % 4]).collect::<Vec<_>>();
+/// let result = seasonal_decompose(&signal, 4, SeasonalModel::Additive).unwrap();
+/// assert!(result.trend[1].is_nan());
+/// assert_eq!(result.trend[2], 2.0);
+/// assert_eq!(result.seasonal[..4], [1.0, -1.0, 2.0, -2.0]);
+/// ```
+pub fn seasonal_decompose<T: Into<f64> + Copy>(
+    signal: &[T],
+    period: usize,
+    model: SeasonalModel,
+) -> Result<Decomposition> {
+    if period < 2 {
+        return Err(Error::InvalidParameter("period must be at least 2".to_string()));
+    }
+    check_len(signal.len(), 2 * period)?;
+    check_no_nan(signal)?;
+    let y = signal.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    if model == SeasonalModel::Multiplicative && y.iter().any(|&x| x <= 0.0) {
+        return Err(Error::InvalidParameter(
+            "signal must be positive for a multiplicative model".to_string(),
+        ));
+    }
+    let n = y.len();
+    let half = period / 2;
+    let average = full_moving_average(&y, period)?;
+    let mut trend = vec![f64::NAN; n];
+    if period.is_multiple_of(2) {
+        let centered = full_moving_average(&average, 2)?;
+        trend[half..n - half].copy_from_slice(&centered);
+    } else {
+        trend[half..n - half].copy_from_slice(&average);
+    }
+
+    let detrended = y
+        .iter()
+        .zip(trend.iter())
+        .map(|(a, b)| match model {
+            SeasonalModel::Additive => a - b,
+            SeasonalModel::Multiplicative => a / b,
+        })
+        .collect::<Vec<f64>>();
+    let mut phase_means = (0..period)
+        .map(|phase| {
+            let values = detrended[phase..]
+                .iter()
+                .step_by(period)
+                .filter(|x| !x.is_nan())
+                .collect::<Vec<_>>();
+            values.iter().copied().sum::<f64>() / values.len() as f64
+        })
+        .collect::<Vec<f64>>();
+    let center = phase_means.iter().sum::<f64>() / period as f64;
+    for mean in phase_means.iter_mut() {
+        match model {
+            SeasonalModel::Additive => *mean -= center,
+            SeasonalModel::Multiplicative => *mean /= center,
+        }
+    }
+    let seasonal = (0..n).map(|i| phase_means[i % period]).collect::<Vec<f64>>();
+    let residual = (0..n)
+        .map(|i| match model {
+            SeasonalModel::Additive => y[i] - trend[i] - seasonal[i],
+            SeasonalModel::Multiplicative => y[i] / (trend[i] * seasonal[i]),
+        })
+        .collect();
+    Ok(Decomposition { trend, seasonal, residual })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use std::f64::consts::PI;
+
+    fn synthetic(n: usize, period: usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+        let trend = (0..n).map(|i| 10.0 + 0.05 * i as f64).collect::<Vec<f64>>();
+        let seasonal =
+            (0..n).map(|i| 3.0 * (2.0 * PI * i as f64 / period as f64).sin()).collect::<Vec<f64>>();
+        let signal = (0..n)
+            .map(|i| trend[i] + seasonal[i] + 0.1 * ((i * 7919) % 13) as f64 / 13.0 - 0.05)
+            .collect();
+        (signal, trend, seasonal)
+    }
+
+    #[test]
+    fn test_loess_point() {
+        let y = [1.0, 3.0, 5.0, 7.0, 9.0];
+        let w = [1.0; 5];
+        assert_relative_eq!(loess_point(&y, &w, 2.0, 3, 1).unwrap(), 5.0, epsilon = 1e-12);
+        assert_relative_eq!(loess_point(&y, &w, -1.0, 3, 1).unwrap(), -1.0, epsilon = 1e-12);
+        assert_relative_eq!(loess_point(&y, &w, 5.0, 9, 1).unwrap(), 11.0, epsilon = 1e-12);
+        assert_relative_eq!(loess_point(&y, &w, 2.0, 5, 0).unwrap(), 5.0, epsilon = 1e-12);
+        assert_eq!(loess_point(&y, &[0.0; 5], 2.0, 3, 1), None);
+    }
+
+    #[test]
+    fn test_stl() {
+        let (signal, trend, seasonal) = synthetic(240, 12);
+        let result = stl(&signal, 12, &StlOptions::default()).unwrap();
+        for (i, &y) in signal.iter().enumerate() {
+            let sum = result.trend[i] + result.seasonal[i] + result.residual[i];
+            assert_relative_eq!(sum, y, epsilon = 1e-12);
+        }
+        for i in 12..228 {
+            assert_relative_eq!(result.trend[i], trend[i], epsilon = 0.1);
+            assert_relative_eq!(result.seasonal[i], seasonal[i], epsilon = 0.1);
+        }
+        assert!(result.residual.iter().all(|r| r.abs() < 0.2));
+    }
+
+    #[test]
+    fn test_stl_robust() {
+        let (mut signal, trend, seasonal) = synthetic(240, 12);
+        for i in [30, 95, 96, 150, 200] {
+            signal[i] += 40.0;
+        }
+        let robust = stl(&signal, 12, &StlOptions::robust()).unwrap();
+        let plain = stl(&signal, 12, &StlOptions::default()).unwrap();
+        let error = |r: &Decomposition| {
+            (12..228)
+                .map(|i| (r.trend[i] - trend[i]).abs() + (r.seasonal[i] - seasonal[i]).abs())
+                .fold(0.0, f64::max)
+        };
+        assert!(error(&robust) < 0.3);
+        assert!(error(&plain) > 1.0);
+        for i in [30, 95, 96, 150, 200] {
+            assert_relative_eq!(robust.residual[i], 40.0, epsilon = 0.5);
+        }
+    }
+
+    #[test]
+    fn test_seasonal_decompose() {
+        let (signal, trend, seasonal) = synthetic(240, 12);
+        let result = seasonal_decompose(&signal, 12, SeasonalModel::Additive).unwrap();
+        assert!(result.trend[..6].iter().chain(result.trend[234..].iter()).all(|t| t.is_nan()));
+        assert!(result.residual[..6].iter().all(|r| r.is_nan()));
+        for i in 6..234 {
+            assert_relative_eq!(result.trend[i], trend[i], epsilon = 0.05);
+            assert_relative_eq!(result.seasonal[i], seasonal[i], epsilon = 0.05);
+            let sum = result.trend[i] + result.seasonal[i] + result.residual[i];
+            assert_relative_eq!(sum, signal[i], epsilon = 1e-12);
+        }
+        assert_relative_eq!(result.seasonal[..12].iter().sum::<f64>(), 0.0, epsilon = 1e-12);
+
+        let trend = (0..77).map(|i| 100.0 + i as f64).collect::<Vec<f64>>();
+        let factors = [1.2, 0.9, 1.0, 0.8, 1.1, 1.0, 1.0];
+        let signal = (0..77).map(|i| trend[i] * factors[i % 7]).collect::<Vec<f64>>();
+        let result = seasonal_decompose(&signal, 7, SeasonalModel::Multiplicative).unwrap();
+        for i in 3..74 {
+            assert_relative_eq!(result.seasonal[i], factors[i % 7], epsilon = 0.01);
+            assert_relative_eq!(result.trend[i], trend[i], epsilon = 1.0);
+            let product = result.trend[i] * result.seasonal[i] * result.residual[i];
+            assert_relative_eq!(product, signal[i], epsilon = 1e-9);
+        }
+        assert_relative_eq!(result.seasonal[..7].iter().sum::<f64>(), 7.0, epsilon = 1e-12);
+        assert!(matches!(
+            seasonal_decompose(&[1.0, -1.0, 1.0, 1.0], 2, SeasonalModel::Multiplicative),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert_eq!(
+            seasonal_decompose(&[1.0; 3], 2, SeasonalModel::Additive),
+            Err(Error::InsufficientData { required: 4, actual: 3 })
+        );
+    }
+
+    #[test]
+    fn test_stl_errors() {
+        let signal = [1.0; 10];
+        assert_eq!(
+            stl(&signal, 6, &StlOptions::default()),
+            Err(Error::InsufficientData { required: 12, actual: 10 })
+        );
+        assert!(matches!(stl(&signal, 1, &StlOptions::default()), Err(Error::InvalidParameter(_))));
+        let options = StlOptions { seasonal_window: 8, ..Default::default() };
+        assert!(matches!(stl(&signal, 2, &options), Err(Error::InvalidParameter(_))));
+        let options = StlOptions { trend_degree: 2, ..Default::default() };
+        assert!(matches!(stl(&signal, 2, &options), Err(Error::InvalidParameter(_))));
+        assert_eq!(
+            stl(&[1.0, f64::NAN, 1.0, 1.0], 2, &StlOptions::default()),
+            Err(Error::NanValue { index: 1 })
+        );
+        let result = stl(&signal, 2, &StlOptions::default()).unwrap();
+        assert!(result.trend.iter().all(|t| (t - 1.0).abs() < 1e-12));
+        assert!(result.seasonal.iter().all(|s| s.abs() < 1e-12));
     }
-    (result, dft_result)
 }
-*/
\ No newline at end of file
@@ -0,0 +1,339 @@
+//! Dynamic time warping
+//!
+//! Dynamic time warping (DTW) aligns two series by repeating samples of either so that the sum
+//! of the squared differences along the alignment is the smallest, which compares shapes that
+//! are shifted or stretched in time. Global constraints keep the warping path near the diagonal,
+//! which avoids pathological alignments and saves time. The distance is the square root of the
+//! sum, so without warping it is the Euclidean distance.
+//!
+//! Reference: [Sakoe & Chiba (1978)](https://doi.org/10.1109/TASSP.1978.1163055)
+
+use crate::error::{check_len, check_no_nan, check_same_len, Error, Result};
+
+/// Global constraint on the warping path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constraint {
+    None,
+    /// Sakoe-Chiba band: the path stays within this many samples of the diagonal
+    SakoeChiba(usize),
+    /// Itakura parallelogram: the path keeps a slope between `1 / s` and `s` for this `s > 1`
+    /// from both ends, within one sample
+    Itakura(f64),
+}
+
+/// Optimal alignment of two series.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alignment {
+    pub distance: f64,
+    /// Pairs of aligned indices, from `(0, 0)` to the last samples of both series
+    pub path: Vec<(usize, usize)>,
+}
+
+/// Columns `lo..=hi` of row `i` that `constraint` allows for series of lengths `n` and `m`.
+fn band(constraint: Constraint, i: usize, n: usize, m: usize) -> (usize, usize) {
+    let (i, n1, m1) = (i as f64, (n - 1) as f64, (m - 1) as f64);
+    let (lo, hi) = match constraint {
+        Constraint::None => (0.0, m1),
+        Constraint::SakoeChiba(radius) => {
+            let center = if n > 1 { i * m1 / n1 } else { 0.0 };
+            ((center - radius as f64).ceil(), (center + radius as f64).floor())
+        }
+        Constraint::Itakura(s) => (
+            ((i - 1.0) / s).max(m1 - 1.0 - s * (n1 - i)).ceil(),
+            (s * i + 1.0).min(m1 - (n1 - 1.0 - i) / s).floor(),
+        ),
+    };
+    let (lo, hi) = (lo.max(0.0), hi.min(m1));
+    if hi < lo {
+        return (1, 0);
+    }
+    (lo as usize, hi as usize)
+}
+
+fn check_constraint(constraint: Constraint) -> Result<()> {
+    if let Constraint::Itakura(s) = constraint {
+        if s.is_nan() || s <= 1.0 {
+            return Err(Error::InvalidParameter("the Itakura slope must exceed 1".to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn to_f64<T: Into<f64> + Copy>(series: &[T]) -> Result<Vec<f64>> {
+    check_len(series.len(), 1)?;
+    check_no_nan(series)?;
+    Ok(series.iter().map(|&x| x.into()).collect())
+}
+
+/// Fill the columns `lo..=hi` of `row` with the cumulative costs from the previous row, the rest
+/// of `row` must be INFINITY already.
+fn fill_row(
+    x: f64,
+    y: &[f64],
+    previous: Option<&[f64]>,
+    (lo, hi): (usize, usize),
+    row: &mut [f64],
+) {
+    for j in lo..=hi {
+        let best = match previous {
+            None if j == 0 => 0.0,
+            None => row[j - 1],
+            Some(previous) if j == 0 => previous[0],
+            Some(previous) => previous[j - 1].min(previous[j]).min(row[j - 1]),
+        };
+        row[j] = best + (x - y[j]).powi(2);
+    }
+}
+
+fn no_path() -> Error {
+    Error::InvalidParameter("no warping path satisfies the constraint".to_string())
+}
+
+/// DTW distance between `x` and `y` under `constraint`, in O(n m) time, or O(n r) with a
+/// Sakoe-Chiba band of radius `r`, and O(m) memory.
+///
+/// Returns an error for an empty series, a NaN, an Itakura slope not above 1, or if no warping
+/// path satisfies the constraint, e.g. for a narrow band and series of very different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::dtw::{dtw, Constraint};
+/// let x = [0.0, 1.0, 2.0, 1.0, 0.0, 0.0];
+/// let y = [0.0, 0.0, 1.0, 2.0, 1.0, 0.0];
+/// assert_eq!(dtw(&x, &y, Constraint::None).unwrap(), 0.0);
+/// assert!(dtw(&x, &y, Constraint::SakoeChiba(0)).unwrap() > 1.0);
+/// ```
+pub fn dtw<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    x: &[X],
+    y: &[Y],
+    constraint: Constraint,
+) -> Result<f64> {
+    check_constraint(constraint)?;
+    let (x, y) = (to_f64(x)?, to_f64(y)?);
+    let (n, m) = (x.len(), y.len());
+    // two rows in turn with their bands, only the band of the row before last is reset
+    let (mut row, mut previous) = (vec![f64::INFINITY; m], vec![f64::INFINITY; m]);
+    let (mut row_band, mut previous_band) = (band(constraint, 0, n, m), (1, 0));
+    fill_row(x[0], &y, None, row_band, &mut row);
+    for (i, &xi) in x.iter().enumerate().skip(1) {
+        std::mem::swap(&mut row, &mut previous);
+        std::mem::swap(&mut row_band, &mut previous_band);
+        if row_band.0 <= row_band.1 {
+            row[row_band.0..=row_band.1].fill(f64::INFINITY);
+        }
+        row_band = band(constraint, i, n, m);
+        fill_row(xi, &y, Some(&previous), row_band, &mut row);
+    }
+    let cost = row[m - 1];
+    if cost.is_infinite() {
+        return Err(no_path());
+    }
+    Ok(cost.sqrt())
+}
+
+/// Same as `dtw`, but also returns the warping path, which takes O(n m) memory.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::dtw::{dtw_path, Constraint};
+/// let alignment = dtw_path(&[1.0, 3.0, 3.0], &[1.0, 3.0], Constraint::None).unwrap();
+/// assert_eq!(alignment.path, vec![(0, 0), (1, 1), (2, 1)]);
+/// ```
+pub fn dtw_path<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    x: &[X],
+    y: &[Y],
+    constraint: Constraint,
+) -> Result<Alignment> {
+    check_constraint(constraint)?;
+    let (x, y) = (to_f64(x)?, to_f64(y)?);
+    let (n, m) = (x.len(), y.len());
+    let mut cost = vec![vec![f64::INFINITY; m]; n];
+    fill_row(x[0], &y, None, band(constraint, 0, n, m), &mut cost[0]);
+    for (i, &xi) in x.iter().enumerate().skip(1) {
+        let (done, rest) = cost.split_at_mut(i);
+        fill_row(xi, &y, Some(&done[i - 1]), band(constraint, i, n, m), &mut rest[0]);
+    }
+    if cost[n - 1][m - 1].is_infinite() {
+        return Err(no_path());
+    }
+    let (mut i, mut j) = (n - 1, m - 1);
+    let mut path = vec![(i, j)];
+    while (i, j) != (0, 0) {
+        (i, j) = if i == 0 {
+            (0, j - 1)
+        } else if j == 0 {
+            (i - 1, 0)
+        } else {
+            [(i - 1, j - 1), (i - 1, j), (i, j - 1)].into_iter().fold(
+                (i - 1, j - 1),
+                |best, (a, b)| if cost[a][b] < cost[best.0][best.1] { (a, b) } else { best },
+            )
+        };
+        path.push((i, j));
+    }
+    path.reverse();
+    Ok(Alignment { distance: cost[n - 1][m - 1].sqrt(), path })
+}
+
+/// LB_Keogh lower bound of the DTW distance between `query` and `candidate` of the same length
+/// under a Sakoe-Chiba band of `radius`: the distance from the query to the envelope of the
+/// running minimum and maximum of the candidate over the band.
+///
+/// Returns an error for empty series, a NaN or series of different lengths.
+pub fn lb_keogh<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    query: &[X],
+    candidate: &[Y],
+    radius: usize,
+) -> Result<f64> {
+    let (query, candidate) = (to_f64(query)?, to_f64(candidate)?);
+    check_same_len(query.len(), candidate.len())?;
+    Ok(lower_bound(&query, &candidate, radius))
+}
+
+fn lower_bound(query: &[f64], candidate: &[f64], radius: usize) -> f64 {
+    let n = candidate.len();
+    let mut sum = 0.0;
+    for (i, &q) in query.iter().enumerate() {
+        let window = &candidate[i.saturating_sub(radius)..(i + radius + 1).min(n)];
+        let upper = window.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let lower = window.iter().cloned().fold(f64::INFINITY, f64::min);
+        if q > upper {
+            sum += (q - upper).powi(2);
+        } else if q < lower {
+            sum += (q - lower).powi(2);
+        }
+    }
+    sum.sqrt()
+}
+
+/// Nearest neighbor of `query` among `candidates` of the same length by the DTW distance under
+/// a Sakoe-Chiba band of `radius`, as (index, distance). Candidates whose LB_Keogh bound is not
+/// below the best distance so far are skipped without computing their DTW distance.
+///
+/// Returns an error for no candidates, an empty query, a NaN or candidates of another length.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::dtw::nearest_neighbor;
+/// let candidates = [[0.0, 0.0, 0.0, 0.0], [0.0, 1.0, 2.0, 2.0], [2.0, 2.0, 0.0, 0.0]];
+/// let (index, distance) = nearest_neighbor(&[0.0, 0.0, 1.0, 2.0], &candidates, 1).unwrap();
+/// assert_eq!((index, distance), (1, 0.0));
+/// ```
+pub fn nearest_neighbor<X: Into<f64> + Copy, R: AsRef<[f64]>>(
+    query: &[X],
+    candidates: &[R],
+    radius: usize,
+) -> Result<(usize, f64)> {
+    check_len(candidates.len(), 1)?;
+    let query = to_f64(query)?;
+    let mut best = (0, f64::INFINITY);
+    for (k, candidate) in candidates.iter().enumerate() {
+        let candidate = candidate.as_ref();
+        check_same_len(query.len(), candidate.len())?;
+        check_no_nan(candidate)?;
+        if lower_bound(&query, candidate, radius) >= best.1 {
+            continue;
+        }
+        let distance = dtw(&query, candidate, Constraint::SakoeChiba(radius))?;
+        if distance < best.1 {
+            best = (k, distance);
+        }
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_dtw() {
+        let x = [1.0, 2.0, 3.0, 4.0];
+        let y = [1.0, 1.0, 2.0, 3.0, 4.0, 4.0];
+        assert_eq!(dtw(&x, &y, Constraint::None).unwrap(), 0.0);
+        // without warping it is the Euclidean distance
+        let z = [2.0, 2.0, 5.0, 4.0];
+        assert_relative_eq!(dtw(&x, &z, Constraint::SakoeChiba(0)).unwrap(), 5f64.sqrt());
+        // 1, 2, 3, 4 against 2, 2, 5, 4 aligns 3 with both the second 2 and 5
+        assert_relative_eq!(dtw(&x, &z, Constraint::None).unwrap(), 3f64.sqrt());
+        assert_relative_eq!(dtw(&[0, 0, 1], &[1], Constraint::None).unwrap(), 2f64.sqrt());
+
+        let alignment = dtw_path(&x, &y, Constraint::SakoeChiba(1)).unwrap();
+        assert_eq!(alignment.distance, 0.0);
+        assert_eq!(alignment.path, vec![(0, 0), (0, 1), (1, 2), (2, 3), (3, 4), (3, 5)]);
+        let alignment = dtw_path(&y, &x, Constraint::Itakura(2.0)).unwrap();
+        assert_eq!(alignment.path.first(), Some(&(0, 0)));
+        assert_eq!(alignment.path.last(), Some(&(5, 3)));
+        assert!(alignment.path.windows(2).all(|w| w[1].0 - w[0].0 <= 1 && w[1].1 - w[0].1 <= 1));
+
+        assert!(matches!(
+            dtw(&[1.0; 10], &[1.0; 2], Constraint::SakoeChiba(0)),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            dtw(&[1.0; 10], &[1.0; 2], Constraint::Itakura(2.0)),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(dtw(&x, &y, Constraint::Itakura(1.0)), Err(Error::InvalidParameter(_))));
+        assert_eq!(dtw(&[1.0, f64::NAN], &y, Constraint::None), Err(Error::NanValue { index: 1 }));
+        assert_eq!(lb_keogh(&x, &[0.0, 0.0, f64::NAN, 0.0], 1), Err(Error::NanValue { index: 2 }));
+        let empty: [f64; 0] = [];
+        assert_eq!(
+            dtw(&x, &empty, Constraint::None),
+            Err(Error::InsufficientData { required: 1, actual: 0 })
+        );
+    }
+
+    #[test]
+    fn test_constraints() {
+        // the band only restricts the paths, so the distance can only grow as it narrows
+        let mut rng = StdRng::seed_from_u64(2);
+        let x = (0..40).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>();
+        let y = (0..40).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>();
+        let free = dtw(&x, &y, Constraint::None).unwrap();
+        let wide = dtw(&x, &y, Constraint::SakoeChiba(10)).unwrap();
+        let narrow = dtw(&x, &y, Constraint::SakoeChiba(2)).unwrap();
+        assert!(free <= wide && wide <= narrow);
+        assert_relative_eq!(dtw(&x, &y, Constraint::SakoeChiba(40)).unwrap(), free);
+        assert!(dtw(&x, &y, Constraint::Itakura(2.0)).unwrap() >= free);
+        let alignment = dtw_path(&x, &y, Constraint::SakoeChiba(2)).unwrap();
+        assert_relative_eq!(alignment.distance, narrow);
+        assert!(alignment.path.iter().all(|&(i, j)| i.abs_diff(j) <= 2));
+        for &(i, j) in &dtw_path(&x, &y, Constraint::Itakura(1.5)).unwrap().path {
+            assert!(j as f64 <= 1.5 * i as f64 + 1.0 && i as f64 <= 1.5 * j as f64 + 1.0);
+        }
+        // the two row buffers of `dtw` agree with the full cost matrix of `dtw_path`
+        let z = &y[..31];
+        for constraint in [Constraint::None, Constraint::SakoeChiba(3), Constraint::Itakura(1.5)] {
+            let distance = dtw(&x, z, constraint).unwrap();
+            assert_relative_eq!(distance, dtw_path(&x, z, constraint).unwrap().distance);
+        }
+    }
+
+    #[test]
+    fn test_lb_keogh() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let candidates = (0..30)
+            .map(|_| (0..32).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>())
+            .collect::<Vec<_>>();
+        let query = (0..32).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>();
+        for candidate in &candidates {
+            let bound = lb_keogh(&query, candidate, 3).unwrap();
+            assert!(bound <= dtw(&query, candidate, Constraint::SakoeChiba(3)).unwrap() + 1e-12);
+        }
+        let brute = candidates
+            .iter()
+            .map(|c| dtw(&query, c, Constraint::SakoeChiba(3)).unwrap())
+            .enumerate()
+            .fold((0, f64::INFINITY), |a, b| if b.1 < a.1 { b } else { a });
+        assert_eq!(nearest_neighbor(&query, &candidates, 3).unwrap(), brute);
+        assert_eq!(lb_keogh(&[1.0, 5.0, 1.0], &[1.0, 2.0, 1.0], 1).unwrap(), 3.0);
+        assert_eq!(lb_keogh(&query, &[1.0], 3), Err(Error::LengthMismatch { left: 32, right: 1 }));
+    }
+}
@@ -1,14 +1,33 @@
-use crate::app::signal::transform::{dftfreq, idft, rdft};
-use crate::opt::utils::{argsort, local_max};
-use crate::stats::estimator::median;
+use crate::app::signal::transform::{idft, rdft, try_dftfreq};
+use crate::error::{check_len, check_no_nan, Error, Result};
+use crate::opt::utils::{local_max, try_argsort};
+use crate::stats::estimator::try_median;
+use crate::stats::missing::{apply_nan_policy, NanPolicy};
 use num::Complex;
 
 //use crate::opt::utils::local_minimax;
 
-pub fn moving_average<T: Into<f64> + Copy>(signal: &[T], window: usize) -> Vec<f64> {
-    if window > signal.len() {
-        panic!("Window size must be smaller than signal length");
+fn check_window(window: usize, len: usize) -> Result<()> {
+    if window == 0 {
+        return Err(Error::InvalidParameter("window must be positive".to_string()));
+    }
+    if window > len {
+        return Err(Error::WindowTooLarge { window, len });
     }
+    Ok(())
+}
+
+pub fn moving_average<T: Into<f64> + Copy>(signal: &[T], window: usize) -> Vec<f64> {
+    try_moving_average(signal, window)
+        .unwrap_or_else(|e| panic!("Cannot calculate moving average: {}", e))
+}
+
+/// Same as `moving_average`, but returns an error if `window` is 0 or larger than the signal.
+///
+/// Like `try_mean`, a NaN is not rejected, it stays in the running sum and makes every later
+/// average NaN, see `moving_average_with_nan_policy` to confine, skip or reject NaN samples.
+pub fn try_moving_average<T: Into<f64> + Copy>(signal: &[T], window: usize) -> Result<Vec<f64>> {
+    check_window(window, signal.len())?;
     let mut sum = 0.0;
     let mut result = vec![0.0; signal.len()];
     for i in 0..window {
@@ -19,30 +38,125 @@ pub fn moving_average<T: Into<f64> + Copy>(signal: &[T], window: usize) -> Vec<f
         sum += signal[i].into() - signal[i - window].into();
         result[i] = sum / window as f64;
     }
-    result
+    Ok(result)
 }
 
-pub fn moving_median<T: Into<f64> + Copy>(signal: &[T], window: usize) -> Vec<f64> {
-    if window > signal.len() {
-        panic!("Window size must be smaller than signal length");
+/// Moving average under a `NanPolicy`.
+///
+/// With `Propagate` every window containing a NaN averages to NaN, with `Skip` the NaN samples
+/// are left out of their windows and a window of NaN only averages to NaN, and with `Error` a
+/// NaN is rejected with `Error::NanValue`.
+pub fn moving_average_with_nan_policy<T: Into<f64> + Copy>(
+    signal: &[T],
+    window: usize,
+    policy: NanPolicy,
+) -> Result<Vec<f64>> {
+    check_window(window, signal.len())?;
+    if policy == NanPolicy::Error {
+        check_no_nan(signal)?;
+    }
+    let mut sum = 0.0;
+    let mut nan_count = 0;
+    let mut result = vec![0.0; signal.len()];
+    for i in 0..signal.len() {
+        let x = signal[i].into();
+        if x.is_nan() {
+            nan_count += 1;
+        } else {
+            sum += x;
+        }
+        if i >= window {
+            let x = signal[i - window].into();
+            if x.is_nan() {
+                nan_count -= 1;
+            } else {
+                sum -= x;
+            }
+        }
+        let len = (i + 1).min(window);
+        result[i] = match policy {
+            NanPolicy::Skip if nan_count < len => sum / (len - nan_count) as f64,
+            _ if nan_count == 0 => sum / len as f64,
+            _ => f64::NAN,
+        };
     }
+    Ok(result)
+}
+
+pub fn moving_median<T: Into<f64> + Copy>(signal: &[T], window: usize) -> Vec<f64> {
+    try_moving_median(signal, window)
+        .unwrap_or_else(|e| panic!("Cannot calculate moving median: {}", e))
+}
+
+/// Same as `moving_median`, but returns an error if `window` is 0 or larger than the signal,
+/// or if the signal contains NaN, see `moving_median_with_nan_policy` to keep NaN samples.
+pub fn try_moving_median<T: Into<f64> + Copy>(signal: &[T], window: usize) -> Result<Vec<f64>> {
+    check_window(window, signal.len())?;
     let mut result = vec![0.0; signal.len()];
     for i in 0..signal.len() {
         if i < window {
-            result[i] = median(&signal[0..i + 1]);
+            result[i] = try_median(&signal[0..i + 1])?;
         } else {
-            result[i] = median(&signal[i - window + 1..i + 1]);
+            result[i] = try_median(&signal[i - window + 1..i + 1])?;
         }
     }
-    result
+    Ok(result)
+}
+
+/// Moving median under a `NanPolicy`.
+///
+/// With `Propagate` every window containing a NaN has a NaN median, with `Skip` the NaN samples
+/// are left out of their windows and a window of NaN only has a NaN median, and with `Error` a
+/// NaN is rejected like in `try_moving_median`.
+pub fn moving_median_with_nan_policy<T: Into<f64> + Copy>(
+    signal: &[T],
+    window: usize,
+    policy: NanPolicy,
+) -> Result<Vec<f64>> {
+    check_window(window, signal.len())?;
+    if policy == NanPolicy::Error {
+        check_no_nan(signal)?;
+    }
+    let mut result = vec![0.0; signal.len()];
+    for i in 0..signal.len() {
+        let start = (i + 1).saturating_sub(window);
+        result[i] = match apply_nan_policy(&signal[start..i + 1], policy)? {
+            Some(values) if !values.is_empty() => try_median(&values)?,
+            _ => f64::NAN,
+        };
+    }
+    Ok(result)
+}
+
+/// Exponential moving average `s_i = alpha x_i + (1 - alpha) s_(i-1)` from `s_0 = x_0`, the level
+/// of simple exponential smoothing.
+///
+/// Returns an error if the signal is empty or contains NaN, or `alpha` is not in (0, 1].
+pub fn exponential_moving_average<T: Into<f64> + Copy>(
+    signal: &[T],
+    alpha: f64,
+) -> Result<Vec<f64>> {
+    check_len(signal.len(), 1)?;
+    check_no_nan(signal)?;
+    if !(alpha > 0.0 && alpha <= 1.0) {
+        return Err(Error::InvalidParameter("alpha must be in (0, 1]".to_string()));
+    }
+    let mut level = signal[0].into();
+    Ok(signal
+        .iter()
+        .map(|&x| {
+            level += alpha * (x.into() - level);
+            level
+        })
+        .collect())
 }
 
 /// Fourier filter for `signal`.
-/// 
+///
 /// `dt`: sample time step
-/// 
+///
 /// `cutoff_frequency`: cut off low frequency components
-/// 
+///
 /// `top_n`: output the top n frequency components of the signal
 pub fn dft_filter_lowpass<X: Into<f64> + Copy, Y: Into<f64> + Copy, Z: Into<f64> + Copy>(
     signal: &[X],
@@ -50,6 +164,24 @@ pub fn dft_filter_lowpass<X: Into<f64> + Copy, Y: Into<f64> + Copy, Z: Into<f64>
     cutoff_frequency: Z,
     top_n: usize,
 ) -> (Vec<f64>, Vec<f64>) {
+    try_dft_filter_lowpass(signal, dt, cutoff_frequency, top_n)
+        .unwrap_or_else(|e| panic!("Cannot filter signal: {}", e))
+}
+
+/// Same as `dft_filter_lowpass`, but returns an error for an empty signal, a NaN or a
+/// non-positive `dt`.
+pub fn try_dft_filter_lowpass<X: Into<f64> + Copy, Y: Into<f64> + Copy, Z: Into<f64> + Copy>(
+    signal: &[X],
+    dt: Y,
+    cutoff_frequency: Z,
+    top_n: usize,
+) -> Result<(Vec<f64>, Vec<f64>)> {
+    check_len(signal.len(), 1)?;
+    check_no_nan(signal)?;
+    let dt = dt.into();
+    if dt.is_nan() || dt <= 0.0 {
+        return Err(Error::InvalidParameter("dt must be positive".to_string()));
+    }
     let mut f_sig = rdft(signal); // real fourier transform
     let mut fix_flag = true;
     if f_sig[f_sig.len() - 1].im < 1e-10 {
@@ -63,7 +195,7 @@ pub fn dft_filter_lowpass<X: Into<f64> + Copy, Y: Into<f64> + Copy, Z: Into<f64>
         * 0.001;
     let amp_max_pos = local_max(&f_amp, prominence); // open interval without dc component and the last point
     let amp_max = amp_max_pos.iter().map(|x| f_amp[*x]).collect::<Vec<_>>();
-    let mut top_amp_pos = argsort(&amp_max, false);
+    let mut top_amp_pos = try_argsort(&amp_max, false)?;
     if top_amp_pos.len() > top_n {
         top_amp_pos.truncate(top_n);
     }
@@ -78,7 +210,7 @@ pub fn dft_filter_lowpass<X: Into<f64> + Copy, Y: Into<f64> + Copy, Z: Into<f64>
 
     let sample_num = signal.len();
     let offset = f_amp[0] / sample_num as f64; // dc component
-    let f = dftfreq(sample_num, dt);
+    let f = try_dftfreq(sample_num, dt)?;
     let f_half = f[..((sample_num - 1) / 2 + 1)].to_vec();
     let freq = top_amp_pos.iter().map(|x| f_half[*x]).collect::<Vec<_>>();
 
@@ -98,7 +230,7 @@ pub fn dft_filter_lowpass<X: Into<f64> + Copy, Y: Into<f64> + Copy, Z: Into<f64>
     let mut filterd_sig = idft(&f_sig);
     filterd_sig = filterd_sig.iter().map(|x| x + offset).collect::<Vec<_>>(); // add dc component cuz it is not in top_n_amp_pos
                                                                               // is it possible that the last point is not in top_n_amp_pos?
-    (freq, filterd_sig)
+    Ok((freq, filterd_sig))
 }
 
 #[cfg(test)]
@@ -122,6 +254,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_try_moving_average() {
+        let signal = vec![1.0, 2.0, 3.0];
+        assert_eq!(
+            try_moving_average(&signal, 4),
+            Err(Error::WindowTooLarge { window: 4, len: 3 })
+        );
+        assert!(matches!(try_moving_average(&signal, 0), Err(Error::InvalidParameter(_))));
+        assert_eq!(try_moving_average(&signal, 3), Ok(vec![1.0, 1.5, 2.0]));
+        let result = try_moving_average(&[1.0, f64::NAN, 3.0], 1).unwrap();
+        assert_eq!(result[0], 1.0);
+        assert!(result[1].is_nan() && result[2].is_nan());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_moving_average_panic() {
+        moving_average(&[1.0, 2.0], 3);
+    }
+
+    #[test]
+    fn test_try_moving_median() {
+        let signal = vec![1.0, f64::NAN, 3.0];
+        assert_eq!(try_moving_median(&signal, 4), Err(Error::WindowTooLarge { window: 4, len: 3 }));
+        assert_eq!(try_moving_median(&signal, 2), Err(Error::NanValue { index: 1 }));
+        assert_eq!(try_moving_median(&[1.0, 2.0, 3.0], 2), Ok(vec![1.0, 1.5, 2.5]));
+    }
+
+    #[test]
+    fn test_try_dft_filter_lowpass() {
+        let empty: Vec<f64> = vec![];
+        assert_eq!(
+            try_dft_filter_lowpass(&empty, 1.0, 0.0, 3),
+            Err(Error::InsufficientData { required: 1, actual: 0 })
+        );
+        assert!(matches!(
+            try_dft_filter_lowpass(&[1.0, 2.0], 0.0, 0.0, 3),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            try_dft_filter_lowpass(&[1.0, 2.0], f64::NAN, 0.0, 3),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+
     #[test]
     fn test_moving_median() {
         let signal = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
@@ -138,6 +315,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_moving_with_nan_policy() {
+        let nan = f64::NAN;
+        let signal = [1.0, nan, 3.0, 4.0, nan, nan, 7.0];
+        let result = moving_average_with_nan_policy(&signal, 2, NanPolicy::Propagate).unwrap();
+        assert_eq!(result[0], 1.0);
+        assert!(result[1..3].iter().all(|x| x.is_nan()));
+        assert_eq!(result[3], 3.5);
+        assert!(result[4..].iter().all(|x| x.is_nan()));
+        let result = moving_average_with_nan_policy(&signal, 2, NanPolicy::Skip).unwrap();
+        let expected = [1.0, 1.0, 3.0, 3.5, 4.0, nan, 7.0];
+        for (r, e) in result.iter().zip(expected.iter()) {
+            assert!(r == e || r.is_nan() && e.is_nan());
+        }
+        let result = moving_median_with_nan_policy(&signal, 3, NanPolicy::Skip).unwrap();
+        let expected = [1.0, 1.0, 2.0, 3.5, 3.5, 4.0, 7.0];
+        assert_eq!(result, expected);
+        let result = moving_median_with_nan_policy(&signal, 3, NanPolicy::Propagate).unwrap();
+        assert_eq!(result[0], 1.0);
+        assert!(result[1..].iter().all(|x| x.is_nan()));
+        for policy in [NanPolicy::Propagate, NanPolicy::Skip] {
+            let signal = [1.0, 2.0, 3.0, 4.0];
+            assert_eq!(
+                moving_average_with_nan_policy(&signal, 3, policy),
+                Ok(moving_average(&signal, 3))
+            );
+            assert_eq!(
+                moving_median_with_nan_policy(&signal, 3, policy),
+                Ok(moving_median(&signal, 3))
+            );
+        }
+        assert_eq!(
+            moving_average_with_nan_policy(&signal, 2, NanPolicy::Error),
+            Err(Error::NanValue { index: 1 })
+        );
+        assert_eq!(
+            moving_median_with_nan_policy(&signal, 2, NanPolicy::Error),
+            Err(Error::NanValue { index: 1 })
+        );
+    }
+
+    #[test]
+    fn test_exponential_moving_average() {
+        let result = exponential_moving_average(&[2, 4, 4, 0], 0.5).unwrap();
+        assert_eq!(result, vec![2.0, 3.0, 3.5, 1.75]);
+        assert_eq!(exponential_moving_average(&[2.0, 4.0], 1.0), Ok(vec![2.0, 4.0]));
+        assert!(matches!(exponential_moving_average(&[1.0], 0.0), Err(Error::InvalidParameter(_))));
+        assert_eq!(
+            exponential_moving_average(&[1.0, f64::NAN], 0.5),
+            Err(Error::NanValue { index: 1 })
+        );
+        let empty: [f64; 0] = [];
+        assert_eq!(
+            exponential_moving_average(&empty, 0.5),
+            Err(Error::InsufficientData { required: 1, actual: 0 })
+        );
+    }
+
     #[test]
     fn test_dft_filter_lowpass() {
         let signal = vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
@@ -147,23 +382,25 @@ mod tests {
         }
         let signal = vec![1.0, 2.0, 3.0, 4.0, 3.0, 2.0, 1.0, 2.0, 3.0, 4.0, 3.0, 2.0, 1.0];
         let (_, result) = dft_filter_lowpass(&signal, 1.0, 0.0, 10000);
+        // the DC component plus the dominant frequency bin 2 of 13, symmetric like the signal
         let expected = vec![
             1.177546665236456,
-            2.220297731272131,
-            3.4049979717345273,
+            2.22029773127213,
+            3.404997971734526,
             3.708219789800072,
-            2.8680187999032034,
-            1.610219856779601,
+            2.868018799903207,
+            1.6102198567796018,
             1.021398370548015,
-            1.6102198567796013,
-            2.8680187999032127,
+            1.6102198567796018,
+            2.868018799903207,
             3.708219789800072,
-            3.4049979717345247,
-            2.2202977312721237,
-            1.1775466652364555,
+            3.404997971734526,
+            2.22029773127213,
+            1.177546665236456,
         ];
+        // the last bits depend on the DFT algorithm and the platform's sin and cos
         for i in 0..signal.len() {
-            assert_relative_eq!(result[i], expected[i]);
+            assert_relative_eq!(result[i], expected[i], max_relative = 1e-14);
         }
         let signal = vec![1, 1, 1, 1, 1, 1, 1, 1, 1];
         let (_, result) = dft_filter_lowpass(&signal, 1.0, 0.0, 10000);
@@ -174,21 +411,21 @@ mod tests {
         let (_, result) = dft_filter_lowpass(&signal, 1, 0.0, 10000);
         let expected = vec![
             1.177546665236456,
-            2.220297731272131,
-            3.4049979717345273,
+            2.22029773127213,
+            3.404997971734526,
             3.708219789800072,
-            2.8680187999032034,
-            1.610219856779601,
+            2.868018799903207,
+            1.6102198567796018,
             1.021398370548015,
-            1.6102198567796013,
-            2.8680187999032127,
+            1.6102198567796018,
+            2.868018799903207,
             3.708219789800072,
-            3.4049979717345247,
-            2.2202977312721237,
-            1.1775466652364555,
+            3.404997971734526,
+            2.22029773127213,
+            1.177546665236456,
         ];
         for i in 0..signal.len() {
-            assert_relative_eq!(result[i], expected[i]);
+            assert_relative_eq!(result[i], expected[i], max_relative = 1e-14);
         }
     }
 }
@@ -0,0 +1,720 @@
+//! ARIMA and seasonal ARIMA models
+//!
+//! SARIMA(p, d, q)(P, D, Q)s treats `(1 - B)^d (1 - B^s)^D y` as an ARMA process with the
+//! autoregressive polynomial `φ(B) Φ(B^s)` and the moving average polynomial `θ(B) Θ(B^s)`.
+//! Coefficients are mapped from partial autocorrelations so that every fitted model is
+//! stationary and invertible, and the exact likelihood is evaluated by a Kalman filter on the
+//! state space form of Gardner, Harvey and Phillips.
+//!
+//! Reference: [Gardner, Harvey and Phillips, Algorithm AS 154](https://doi.org/10.2307/2346910)
+
+use super::Forecast;
+use crate::error::{check_len, check_no_nan, Error, Result};
+use crate::na::differentiation::{lagged_diff, try_diff};
+use crate::opt::minimize::{nelder_mead, NelderMeadOptions};
+use crate::stats::estimator::is_constant;
+use std::f64::consts::PI;
+
+/// Objective minimized by `Arima::fit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitMethod {
+    /// Conditional sum of squares, which conditions on the first `p + sP` observations
+    Css,
+    /// Exact Gaussian likelihood, started from the conditional sum of squares estimates
+    #[default]
+    ExactLikelihood,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InformationCriterion {
+    Aic,
+    Bic,
+}
+
+/// Orders and settings of a SARIMA(p, d, q)(P, D, Q)s model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arima {
+    pub p: usize,
+    pub d: usize,
+    pub q: usize,
+    pub seasonal_p: usize,
+    pub seasonal_d: usize,
+    pub seasonal_q: usize,
+    pub period: usize,
+    /// Whether the differenced series has a nonzero mean, by default only without differencing
+    pub constant: Option<bool>,
+    pub method: FitMethod,
+}
+
+/// A fitted SARIMA model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArimaModel {
+    pub specification: Arima,
+    /// φ of `1 - φ_1 B - ... - φ_p B^p`
+    pub ar: Vec<f64>,
+    /// θ of `1 + θ_1 B + ... + θ_q B^q`
+    pub ma: Vec<f64>,
+    pub seasonal_ar: Vec<f64>,
+    pub seasonal_ma: Vec<f64>,
+    /// Mean of the differenced series, 0 without constant
+    pub mean: f64,
+    /// Innovation variance
+    pub sigma2: f64,
+    pub log_likelihood: f64,
+    pub aic: f64,
+    pub bic: f64,
+    /// One step prediction errors of the differenced series
+    pub residuals: Vec<f64>,
+    series: Vec<f64>,
+    differenced: Vec<f64>,
+    /// Kalman state predicted for the step after the series, fitted by exact likelihood only
+    state: Option<Vec<f64>>,
+}
+
+/// Coefficients of `1 - φ_1 B - ... - φ_p B^p` with the partial autocorrelations
+/// `tanh(u_1), ..., tanh(u_p)`, which is stationary for any `u`.
+fn stationary(u: &[f64]) -> Vec<f64> {
+    let mut phi: Vec<f64> = Vec::with_capacity(u.len());
+    for &x in u {
+        let r = x.tanh();
+        let previous = phi.clone();
+        for (j, c) in phi.iter_mut().enumerate() {
+            *c -= r * previous[previous.len() - 1 - j];
+        }
+        phi.push(r);
+    }
+    phi
+}
+
+/// Coefficients `c` of the product of `1 + sign Σ a_i B^i` and `1 + sign Σ b_j B^(j period)`,
+/// written as `1 + sign Σ c_k B^k`.
+fn multiply(a: &[f64], b: &[f64], period: usize, sign: f64) -> Vec<f64> {
+    let mut c = vec![0.0; a.len() + b.len() * period];
+    c[..a.len()].copy_from_slice(a);
+    for (j, y) in b.iter().enumerate() {
+        let lag = (j + 1) * period;
+        c[lag - 1] += y;
+        for (i, x) in a.iter().enumerate() {
+            c[lag + i] += sign * x * y;
+        }
+    }
+    c
+}
+
+/// Coefficients `δ` of `(1 - B)^d (1 - B^s)^D = 1 - Σ δ_i B^i`.
+fn differencing(d: usize, seasonal_d: usize, period: usize) -> Vec<f64> {
+    let mut delta = vec![];
+    for _ in 0..d {
+        delta = multiply(&delta, &[1.0], 1, -1.0);
+    }
+    for _ in 0..seasonal_d {
+        delta = multiply(&delta, &[1.0], period, -1.0);
+    }
+    delta
+}
+
+/// Weights ψ of the moving average representation `ψ(B) = θ(B) / φ(B)`.
+fn psi_weights(phi: &[f64], theta: &[f64], len: usize) -> Vec<f64> {
+    let mut psi = vec![0.0; len];
+    for j in 0..len {
+        psi[j] = if j == 0 { 1.0 } else { theta.get(j - 1).copied().unwrap_or(0.0) };
+        for i in 1..=j.min(phi.len()) {
+            psi[j] += phi[i - 1] * psi[j - i];
+        }
+    }
+    psi
+}
+
+/// Residuals of the ARMA recursion, conditional on the first `phi.len()` observations and
+/// zero earlier errors.
+fn css_residuals(w: &[f64], phi: &[f64], theta: &[f64], mean: f64) -> Vec<f64> {
+    let mut e = vec![0.0; w.len()];
+    for t in phi.len()..w.len() {
+        let mut v = w[t] - mean;
+        for (i, a) in phi.iter().enumerate() {
+            v -= a * (w[t - 1 - i] - mean);
+        }
+        for (j, b) in theta.iter().enumerate().take(t) {
+            v -= b * e[t - 1 - j];
+        }
+        e[t] = v;
+    }
+    e
+}
+
+struct Filtered {
+    /// Innovations, the one step prediction errors
+    residuals: Vec<f64>,
+    sum_squares: f64,
+    sum_log_variance: f64,
+    /// State predicted for the step after `w`, whose first entry is the forecast minus the mean
+    state: Vec<f64>,
+}
+
+/// Kalman filter of the ARMA process with unit innovation variance.
+fn kalman(w: &[f64], phi: &[f64], theta: &[f64], mean: f64) -> Filtered {
+    let r = phi.len().max(theta.len() + 1);
+    let phi = (0..r).map(|i| phi.get(i).copied().unwrap_or(0.0)).collect::<Vec<f64>>();
+    let rr = (0..r)
+        .map(|i| if i == 0 { 1.0 } else { theta.get(i - 1).copied().unwrap_or(0.0) })
+        .collect::<Vec<f64>>();
+    // T P T' with the companion matrix T, which has phi as first column and ones above the diagonal
+    let propagate = |p: &[Vec<f64>]| {
+        let tp = (0..r)
+            .map(|i| {
+                (0..r)
+                    .map(|j| phi[i] * p[0][j] + if i + 1 < r { p[i + 1][j] } else { 0.0 })
+                    .collect::<Vec<f64>>()
+            })
+            .collect::<Vec<Vec<f64>>>();
+        (0..r)
+            .map(|i| {
+                (0..r)
+                    .map(|j| tp[i][0] * phi[j] + if j + 1 < r { tp[i][j + 1] } else { 0.0 })
+                    .collect::<Vec<f64>>()
+            })
+            .collect::<Vec<Vec<f64>>>()
+    };
+
+    // stationary covariance P = T P T' + R R' by doubling: P = Σ T^k R R' T'^k
+    let mut p = (0..r).map(|i| (0..r).map(|j| rr[i] * rr[j]).collect()).collect::<Vec<Vec<f64>>>();
+    let mut power = (0..r)
+        .map(|i| {
+            (0..r)
+                .map(|j| {
+                    if j == 0 {
+                        phi[i]
+                    } else if j == i + 1 {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        })
+        .collect::<Vec<Vec<f64>>>();
+    let product = |a: &[Vec<f64>], b: &[Vec<f64>]| {
+        (0..r)
+            .map(|i| (0..r).map(|j| (0..r).map(|k| a[i][k] * b[k][j]).sum::<f64>()).collect())
+            .collect::<Vec<Vec<f64>>>()
+    };
+    for _ in 0..64 {
+        if power.iter().flatten().all(|x| x.abs() < 1e-15) {
+            break;
+        }
+        let transposed = (0..r).map(|i| (0..r).map(|j| power[j][i]).collect()).collect::<Vec<_>>();
+        let added = product(&product(&power, &p), &transposed);
+        for (row, added) in p.iter_mut().zip(added.iter()) {
+            for (x, y) in row.iter_mut().zip(added.iter()) {
+                *x += y;
+            }
+        }
+        power = product(&power, &power);
+    }
+
+    let mut a = vec![0.0; r];
+    let mut filtered = Filtered {
+        residuals: vec![0.0; w.len()],
+        sum_squares: 0.0,
+        sum_log_variance: 0.0,
+        state: vec![],
+    };
+    // the prediction variance only decreases towards 1, after which P stays fixed
+    let mut steady = false;
+    for (t, y) in w.iter().enumerate() {
+        let v = y - mean - a[0];
+        let f = p[0][0];
+        filtered.residuals[t] = v;
+        filtered.sum_squares += v * v / f;
+        filtered.sum_log_variance += f.ln();
+        let gain = (0..r).map(|i| p[i][0] / f).collect::<Vec<f64>>();
+        for i in 0..r {
+            a[i] += gain[i] * v;
+        }
+        a = transition(&phi, &a);
+        if steady {
+            continue;
+        }
+        steady = f - 1.0 < 1e-12;
+        let first = p[0].clone();
+        for i in 0..r {
+            for j in 0..r {
+                p[i][j] -= gain[i] * first[j];
+            }
+        }
+        p = propagate(&p);
+        for i in 0..r {
+            for j in 0..r {
+                p[i][j] += rr[i] * rr[j];
+            }
+        }
+    }
+    filtered.state = a;
+    filtered
+}
+
+/// State `T a` after one step of the companion matrix `T` with `phi` as first column.
+fn transition(phi: &[f64], a: &[f64]) -> Vec<f64> {
+    let r = a.len();
+    (0..r)
+        .map(|i| phi.get(i).copied().unwrap_or(0.0) * a[0] + a.get(i + 1).unwrap_or(&0.0))
+        .collect()
+}
+
+impl Arima {
+    /// A non-seasonal ARIMA(p, d, q) model.
+    pub fn new(p: usize, d: usize, q: usize) -> Self {
+        Arima {
+            p,
+            d,
+            q,
+            seasonal_p: 0,
+            seasonal_d: 0,
+            seasonal_q: 0,
+            period: 1,
+            constant: None,
+            method: FitMethod::default(),
+        }
+    }
+
+    /// Add the seasonal part (P, D, Q) with `period` samples per season.
+    pub fn seasonal(mut self, p: usize, d: usize, q: usize, period: usize) -> Self {
+        self.seasonal_p = p;
+        self.seasonal_d = d;
+        self.seasonal_q = q;
+        self.period = period;
+        self
+    }
+
+    pub fn with_constant(mut self, constant: bool) -> Self {
+        self.constant = Some(constant);
+        self
+    }
+
+    pub fn with_method(mut self, method: FitMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    fn has_constant(&self) -> bool {
+        self.constant.unwrap_or(self.d + self.seasonal_d == 0)
+    }
+
+    fn n_coefficients(&self) -> usize {
+        self.p + self.q + self.seasonal_p + self.seasonal_q + self.has_constant() as usize
+    }
+
+    /// Split unconstrained parameters into (ar, ma, seasonal ar, seasonal ma, mean).
+    fn coefficients(&self, u: &[f64]) -> (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>, f64) {
+        let (ar, rest) = u.split_at(self.p);
+        let (ma, rest) = rest.split_at(self.q);
+        let (seasonal_ar, rest) = rest.split_at(self.seasonal_p);
+        let (seasonal_ma, rest) = rest.split_at(self.seasonal_q);
+        let negate = |x: Vec<f64>| x.iter().map(|c| -c).collect::<Vec<f64>>();
+        (
+            stationary(ar),
+            negate(stationary(ma)),
+            stationary(seasonal_ar),
+            negate(stationary(seasonal_ma)),
+            rest.first().copied().unwrap_or(0.0),
+        )
+    }
+
+    /// Full autoregressive and moving average coefficients and the mean.
+    fn expand(&self, u: &[f64]) -> (Vec<f64>, Vec<f64>, f64) {
+        let (ar, ma, seasonal_ar, seasonal_ma, mean) = self.coefficients(u);
+        (
+            multiply(&ar, &seasonal_ar, self.period, -1.0),
+            multiply(&ma, &seasonal_ma, self.period, 1.0),
+            mean,
+        )
+    }
+
+    /// Fit the model to `series` by `method`.
+    ///
+    /// Returns an error if the series contains NaN, is too short for the orders or is constant
+    /// after differencing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathbox::app::signal::forecast::arima::Arima;
+    /// let series = [10.0, 12.1, 11.4, 13.2, 14.0, 13.1, 15.3, 16.2, 15.8, 17.5, 18.1, 17.9];
+    /// let model = Arima::new(0, 1, 1).with_constant(true).fit(&series).unwrap();
+    /// assert!(model.mean > 0.5);
+    /// let forecast = model.forecast(2, 0.05).unwrap();
+    /// assert!(forecast.mean[1] > forecast.mean[0] && forecast.mean[0] > 18.0);
+    /// assert!(forecast.lower[0] < forecast.mean[0] && forecast.upper[0] > forecast.mean[0]);
+    /// ```
+    pub fn fit<T: Into<f64> + Copy>(&self, series: &[T]) -> Result<ArimaModel> {
+        check_no_nan(series)?;
+        let seasonal = self.seasonal_p + self.seasonal_d + self.seasonal_q > 0;
+        if seasonal && self.period < 2 {
+            return Err(Error::InvalidParameter(
+                "period must be at least 2 for a seasonal model".to_string(),
+            ));
+        }
+        let period = if seasonal { self.period } else { 1 };
+        let spec = Arima { period, ..*self };
+        let ar_order = spec.p + spec.seasonal_p * period;
+        let diff_order = spec.d + spec.seasonal_d * period;
+        check_len(series.len(), diff_order + ar_order + spec.n_coefficients() + 2)?;
+        let series = series.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+        let mut w = series.clone();
+        for _ in 0..spec.d {
+            w = try_diff(&w)?;
+        }
+        for _ in 0..spec.seasonal_d {
+            w = lagged_diff(&w, period)?;
+        }
+        if is_constant(&w) {
+            return Err(Error::ConstantSeries);
+        }
+
+        let mut x0 = vec![0.0; spec.n_coefficients()];
+        if spec.has_constant() {
+            x0[spec.n_coefficients() - 1] = w.iter().sum::<f64>() / w.len() as f64;
+        }
+        let options = NelderMeadOptions::default();
+        let n_css = (w.len() - ar_order) as f64;
+        let css = |u: &[f64]| {
+            let (phi, theta, mean) = spec.expand(u);
+            let ss = css_residuals(&w, &phi, &theta, mean).iter().map(|e| e * e).sum::<f64>();
+            0.5 * (ss / n_css).ln()
+        };
+        let mut u = nelder_mead(css, &x0, &options).x;
+        let (phi, theta, mean) = spec.expand(&u);
+        let (residuals, sigma2, log_likelihood, n_used, state) = match spec.method {
+            FitMethod::Css => {
+                let residuals = css_residuals(&w, &phi, &theta, mean);
+                let sigma2 = residuals.iter().map(|e| e * e).sum::<f64>() / n_css;
+                let log_likelihood = -0.5 * n_css * ((2.0 * PI * sigma2).ln() + 1.0);
+                (residuals, sigma2, log_likelihood, n_css, None)
+            }
+            FitMethod::ExactLikelihood => {
+                let n = w.len() as f64;
+                let exact = |u: &[f64]| {
+                    let (phi, theta, mean) = spec.expand(u);
+                    let filtered = kalman(&w, &phi, &theta, mean);
+                    0.5 * ((filtered.sum_squares / n).ln() + filtered.sum_log_variance / n)
+                };
+                u = nelder_mead(exact, &u, &options).x;
+                let (phi, theta, mean) = spec.expand(&u);
+                let filtered = kalman(&w, &phi, &theta, mean);
+                let sigma2 = filtered.sum_squares / n;
+                let log_likelihood =
+                    -0.5 * (n * ((2.0 * PI * sigma2).ln() + 1.0) + filtered.sum_log_variance);
+                (filtered.residuals, sigma2, log_likelihood, n, Some(filtered.state))
+            }
+        };
+
+        let (ar, ma, seasonal_ar, seasonal_ma, mean) = spec.coefficients(&u);
+        let k = (spec.n_coefficients() + 1) as f64;
+        Ok(ArimaModel {
+            specification: spec,
+            ar,
+            ma,
+            seasonal_ar,
+            seasonal_ma,
+            mean,
+            sigma2,
+            log_likelihood,
+            aic: -2.0 * log_likelihood + 2.0 * k,
+            bic: -2.0 * log_likelihood + k * n_used.ln(),
+            residuals,
+            series,
+            differenced: w,
+            state,
+        })
+    }
+
+    /// Fit this model with every `p <= max_p` and `q <= max_q` and return the one with the
+    /// lowest `criterion`.
+    ///
+    /// Orders that cannot be fitted are skipped, the error of the first one is returned if none
+    /// can.
+    pub fn select<T: Into<f64> + Copy>(
+        &self,
+        series: &[T],
+        max_p: usize,
+        max_q: usize,
+        criterion: InformationCriterion,
+    ) -> Result<ArimaModel> {
+        let score = |model: &ArimaModel| match criterion {
+            InformationCriterion::Aic => model.aic,
+            InformationCriterion::Bic => model.bic,
+        };
+        let mut best: Option<ArimaModel> = None;
+        let mut error = None;
+        for p in 0..=max_p {
+            for q in 0..=max_q {
+                match (Arima { p, q, ..*self }).fit(series) {
+                    Ok(model) => {
+                        if best.as_ref().is_none_or(|b| score(&model) < score(b)) {
+                            best = Some(model);
+                        }
+                    }
+                    Err(e) => {
+                        error.get_or_insert(e);
+                    }
+                }
+            }
+        }
+        best.ok_or_else(|| error.unwrap_or(Error::InsufficientData { required: 1, actual: 0 }))
+    }
+}
+
+impl ArimaModel {
+    /// Forecast `horizon` steps after the fitted series with `1 - alpha` prediction intervals.
+    ///
+    /// A model fitted by exact likelihood forecasts from the final Kalman state, a model fitted
+    /// by CSS runs the ARMA recursion on its residuals.
+    pub fn forecast(&self, horizon: usize, alpha: f64) -> Result<Forecast> {
+        if !(alpha > 0.0 && alpha < 1.0) {
+            return Err(Error::InvalidParameter("alpha must be in (0, 1)".to_string()));
+        }
+        let spec = &self.specification;
+        let phi = multiply(&self.ar, &self.seasonal_ar, spec.period, -1.0);
+        let theta = multiply(&self.ma, &self.seasonal_ma, spec.period, 1.0);
+        let delta = differencing(spec.d, spec.seasonal_d, spec.period);
+
+        let mut w = self.differenced.clone();
+        if let Some(state) = &self.state {
+            let mut a = state.clone();
+            for _ in 0..horizon {
+                w.push(self.mean + a[0]);
+                a = transition(&phi, &a);
+            }
+        } else {
+            let mut e = self.residuals.clone();
+            for _ in 0..horizon {
+                let t = w.len();
+                let mut x = self.mean;
+                for (i, a) in phi.iter().enumerate().take(t) {
+                    x += a * (w[t - 1 - i] - self.mean);
+                }
+                for (j, b) in theta.iter().enumerate().take(t) {
+                    x += b * e[t - 1 - j];
+                }
+                w.push(x);
+                e.push(0.0);
+            }
+        }
+        let mut y = self.series.clone();
+        for h in 0..horizon {
+            let t = y.len();
+            let x = w[self.differenced.len() + h]
+                + delta.iter().enumerate().map(|(i, c)| c * y[t - 1 - i]).sum::<f64>();
+            y.push(x);
+        }
+
+        let psi = psi_weights(&multiply(&phi, &delta, 1, -1.0), &theta, horizon);
+        let variance = psi
+            .iter()
+            .scan(0.0, |sum, p| {
+                *sum += p * p;
+                Some(*sum * self.sigma2)
+            })
+            .collect::<Vec<f64>>();
+        Ok(Forecast::normal(y.split_off(self.series.len()), &variance, alpha))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// ARMA process with uniform innovations of variance 1 / 12 after a burn-in.
+    fn arma(n: usize, phi: &[f64], theta: &[f64], mean: f64, seed: u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let burn_in = 200;
+        let mut x = vec![0.0; n + burn_in];
+        let mut e = vec![0.0; n + burn_in];
+        for t in 0..n + burn_in {
+            e[t] = rng.gen_range(-0.5..0.5);
+            x[t] = e[t];
+            for (i, a) in phi.iter().enumerate().take(t) {
+                x[t] += a * x[t - 1 - i];
+            }
+            for (j, b) in theta.iter().enumerate().take(t) {
+                x[t] += b * e[t - 1 - j];
+            }
+        }
+        x.split_off(burn_in).iter().map(|v| v + mean).collect()
+    }
+
+    fn integrate(w: &[f64], delta: &[f64]) -> Vec<f64> {
+        let mut y = vec![0.0; delta.len()];
+        for v in w {
+            let t = y.len();
+            let x = v + delta.iter().enumerate().map(|(i, c)| c * y[t - 1 - i]).sum::<f64>();
+            y.push(x);
+        }
+        y
+    }
+
+    #[test]
+    fn test_polynomials() {
+        assert_eq!(stationary(&[]), Vec::<f64>::new());
+        let r = [0.5f64, -0.3];
+        let phi = stationary(&[r[0].atanh(), r[1].atanh()]);
+        assert_relative_eq!(phi[0], 0.5 + 0.3 * 0.5, epsilon = 1e-12);
+        assert_relative_eq!(phi[1], -0.3, epsilon = 1e-12);
+
+        let theta = multiply(&[0.4], &[0.6], 4, 1.0);
+        assert_eq!(theta, vec![0.4, 0.0, 0.0, 0.6, 0.4 * 0.6]);
+        let phi = multiply(&[0.4], &[0.6], 4, -1.0);
+        assert_eq!(phi, vec![0.4, 0.0, 0.0, 0.6, -0.4 * 0.6]);
+        assert_eq!(differencing(2, 0, 1), vec![2.0, -1.0]);
+        assert_eq!(differencing(1, 1, 3), vec![1.0, 0.0, 1.0, -1.0]);
+
+        // ARMA(1, 1): ψ_j = (φ + θ) φ^(j-1)
+        let psi = psi_weights(&[0.5], &[0.3], 4);
+        assert_eq!(psi, vec![1.0, 0.8, 0.4, 0.2]);
+    }
+
+    #[test]
+    fn test_kalman() {
+        // exact AR(1) likelihood: x_1 ~ N(0, σ² / (1 - φ²)), then x_t | x_(t-1) ~ N(φ x_(t-1), σ²)
+        let w = [0.3, -0.2, 0.5, 0.9, 0.4, -0.1];
+        let phi = 0.6;
+        let filtered = kalman(&w, &[phi], &[], 0.0);
+        let ss = (1.0 - phi * phi) * w[0] * w[0]
+            + w.windows(2).map(|x| (x[1] - phi * x[0]).powi(2)).sum::<f64>();
+        assert_relative_eq!(filtered.sum_squares, ss, epsilon = 1e-12);
+        assert_relative_eq!(filtered.sum_log_variance, -(1.0 - phi * phi).ln(), epsilon = 1e-12);
+
+        // MA(1): the first prediction error is the first centered sample, with variance 1 + θ²,
+        // and the next one removes θ / (1 + θ²) of it
+        let filtered = kalman(&w, &[], &[0.5], 0.1);
+        assert_relative_eq!(filtered.residuals[0], 0.2, epsilon = 1e-12);
+        assert_relative_eq!(filtered.residuals[1], -0.3 - 0.2 * 0.5 / 1.25, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_fit_arma() {
+        let series = arma(1000, &[0.6], &[], 10.0, 1);
+        for method in [FitMethod::Css, FitMethod::ExactLikelihood] {
+            let model = Arima::new(1, 0, 0).with_method(method).fit(&series).unwrap();
+            assert_relative_eq!(model.ar[0], 0.6, epsilon = 0.05);
+            assert_relative_eq!(model.mean, 10.0, epsilon = 0.05);
+            assert_relative_eq!(model.sigma2, 1.0 / 12.0, max_relative = 0.1);
+            assert!(model.ma.is_empty() && model.seasonal_ar.is_empty());
+        }
+
+        let series = arma(1000, &[], &[-0.5], 0.0, 2);
+        let css = Arima::new(0, 0, 1).with_method(FitMethod::Css).fit(&series).unwrap();
+        let exact = Arima::new(0, 0, 1).fit(&series).unwrap();
+        assert_relative_eq!(exact.ma[0], -0.5, epsilon = 0.06);
+        assert_relative_eq!(exact.ma[0], css.ma[0], epsilon = 0.02);
+        assert_eq!(exact.residuals.len(), 1000);
+        assert_relative_eq!(exact.aic, -2.0 * exact.log_likelihood + 6.0, epsilon = 1e-9);
+        assert_relative_eq!(
+            exact.bic,
+            -2.0 * exact.log_likelihood + 3.0 * 1000f64.ln(),
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn test_exact_forecast() {
+        // the best linear predictor of a short MA(1) series is γ' Γ⁻¹ (w - μ), with the
+        // tridiagonal autocovariance matrix Γ of 1 + θ² and θ, solved by the Thomas algorithm
+        let series = arma(12, &[], &[0.8], 2.0, 5);
+        let model = Arima::new(0, 0, 1).fit(&series).unwrap();
+        let (theta, mean) = (model.ma[0], model.mean);
+        let n = series.len();
+        let mut diagonal = vec![1.0 + theta * theta; n];
+        let mut x = series.iter().map(|v| v - mean).collect::<Vec<f64>>();
+        for i in 1..n {
+            let factor = theta / diagonal[i - 1];
+            diagonal[i] -= factor * theta;
+            x[i] -= factor * x[i - 1];
+        }
+        x[n - 1] /= diagonal[n - 1];
+        for i in (0..n - 1).rev() {
+            x[i] = (x[i] - theta * x[i + 1]) / diagonal[i];
+        }
+        let forecast = model.forecast(2, 0.05).unwrap();
+        assert_relative_eq!(forecast.mean[0], mean + theta * x[n - 1], epsilon = 1e-9);
+        assert_relative_eq!(forecast.mean[1], mean, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_select() {
+        let series = arma(800, &[0.5, 0.3], &[], 0.0, 3);
+        let model = Arima::new(0, 0, 0).select(&series, 2, 2, InformationCriterion::Bic).unwrap();
+        assert_eq!((model.specification.p, model.specification.q), (2, 0));
+        assert_relative_eq!(model.ar[0], 0.5, epsilon = 0.08);
+        assert_relative_eq!(model.ar[1], 0.3, epsilon = 0.08);
+        let model = Arima::new(0, 0, 0).select(&series, 2, 2, InformationCriterion::Aic).unwrap();
+        assert!(model.specification.p >= 1);
+    }
+
+    #[test]
+    fn test_random_walk_forecast() {
+        let steps = arma(300, &[], &[], 0.0, 4);
+        let series = integrate(&steps, &[1.0]);
+        let model = Arima::new(0, 1, 0).fit(&series).unwrap();
+        let sigma2 = steps.iter().map(|x| x * x).sum::<f64>() / 300.0;
+        assert_relative_eq!(model.sigma2, sigma2, epsilon = 1e-12);
+        let forecast = model.forecast(3, 0.05).unwrap();
+        let last = series[series.len() - 1];
+        for (h, (lower, upper)) in forecast.lower.iter().zip(forecast.upper.iter()).enumerate() {
+            assert_relative_eq!(forecast.mean[h], last, epsilon = 1e-12);
+            let half_width = 1.959_963_984_540_054 * (sigma2 * (h + 1) as f64).sqrt();
+            assert_relative_eq!(upper - last, half_width, epsilon = 1e-9);
+            assert_relative_eq!(last - lower, half_width, epsilon = 1e-9);
+        }
+        assert_eq!(model.forecast(0, 0.05).unwrap().mean, Vec::<f64>::new());
+    }
+
+    #[test]
+    fn test_seasonal() {
+        // airline model (1 - B)(1 - B^12) y = (1 - 0.4 B)(1 - 0.6 B^12) e
+        let theta = multiply(&[-0.4], &[-0.6], 12, 1.0);
+        let w = arma(480, &[], &theta, 0.0, 5);
+        let series = integrate(&w, &differencing(1, 1, 12))
+            .iter()
+            .enumerate()
+            .map(|(i, y)| y + 100.0 + 5.0 * (i % 12) as f64)
+            .collect::<Vec<f64>>();
+        let model = Arima::new(0, 1, 1).seasonal(0, 1, 1, 12).fit(&series).unwrap();
+        assert_relative_eq!(model.ma[0], -0.4, epsilon = 0.08);
+        assert_relative_eq!(model.seasonal_ma[0], -0.6, epsilon = 0.1);
+        assert_eq!(model.mean, 0.0);
+
+        let forecast = model.forecast(24, 0.1).unwrap();
+        let n = series.len();
+        // the seasonal pattern carries over and the intervals widen
+        let pattern = (0..12).map(|h| forecast.mean[h] - series[n - 12 + h]).collect::<Vec<f64>>();
+        assert!(pattern.iter().all(|d| d.abs() < 2.0));
+        let widths = forecast.upper.iter().zip(forecast.lower.iter()).map(|(u, l)| u - l);
+        assert!(widths.collect::<Vec<f64>>().windows(2).all(|w| w[1] > w[0]));
+    }
+
+    #[test]
+    fn test_arima_errors() {
+        let series = arma(50, &[0.5], &[], 0.0, 6);
+        assert_eq!(
+            Arima::new(1, 0, 0).fit(&series[..3]),
+            Err(Error::InsufficientData { required: 5, actual: 3 })
+        );
+        assert_eq!(
+            Arima::new(1, 1, 0).fit(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+            Err(Error::ConstantSeries)
+        );
+        assert!(matches!(
+            Arima::new(0, 0, 1).seasonal(0, 1, 0, 1).fit(&series),
+            Err(Error::InvalidParameter(_))
+        ));
+        let mut with_nan = series.clone();
+        with_nan[7] = f64::NAN;
+        assert_eq!(Arima::new(1, 0, 0).fit(&with_nan), Err(Error::NanValue { index: 7 }));
+        let model = Arima::new(1, 0, 0).fit(&series).unwrap();
+        assert!(matches!(model.forecast(3, 1.0), Err(Error::InvalidParameter(_))));
+    }
+}
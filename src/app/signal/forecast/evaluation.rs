@@ -0,0 +1,415 @@
+//! Forecast accuracy
+//!
+//! Point and interval accuracy measures, and a rolling origin backtest which refits a forecaster
+//! at successive origins of a series and scores its forecasts of the following samples.
+//!
+//! Reference: [Hyndman and Koehler, Another look at measures of forecast accuracy](https://doi.org/10.1016/j.ijforecast.2006.03.001)
+
+use super::Forecast;
+use crate::error::{check_len, check_no_nan, check_same_len, Error, Result};
+
+fn check_pair(actual: &[f64], predicted: &[f64]) -> Result<()> {
+    check_same_len(actual.len(), predicted.len())?;
+    check_len(actual.len(), 1)?;
+    check_no_nan(actual)?;
+    check_no_nan(predicted)
+}
+
+/// Mean absolute error.
+pub fn mae(actual: &[f64], predicted: &[f64]) -> Result<f64> {
+    check_pair(actual, predicted)?;
+    let n = actual.len() as f64;
+    Ok(actual.iter().zip(predicted).map(|(a, p)| (a - p).abs()).sum::<f64>() / n)
+}
+
+/// Root mean squared error.
+pub fn rmse(actual: &[f64], predicted: &[f64]) -> Result<f64> {
+    check_pair(actual, predicted)?;
+    let n = actual.len() as f64;
+    Ok((actual.iter().zip(predicted).map(|(a, p)| (a - p).powi(2)).sum::<f64>() / n).sqrt())
+}
+
+/// Mean absolute percentage error, in percent.
+///
+/// Returns an error if an actual value is 0.
+pub fn mape(actual: &[f64], predicted: &[f64]) -> Result<f64> {
+    check_pair(actual, predicted)?;
+    if actual.contains(&0.0) {
+        return Err(Error::InvalidParameter("actual values must not be 0".to_string()));
+    }
+    let n = actual.len() as f64;
+    Ok(100.0 * actual.iter().zip(predicted).map(|(a, p)| ((a - p) / a).abs()).sum::<f64>() / n)
+}
+
+/// Symmetric mean absolute percentage error `mean(2 |a - p| / (|a| + |p|))`, in percent from 0
+/// to 200. Pairs of zeros count as exact.
+pub fn smape(actual: &[f64], predicted: &[f64]) -> Result<f64> {
+    check_pair(actual, predicted)?;
+    let n = actual.len() as f64;
+    let sum = actual
+        .iter()
+        .zip(predicted)
+        .map(|(a, p)| {
+            let scale = a.abs() + p.abs();
+            if scale == 0.0 {
+                0.0
+            } else {
+                2.0 * (a - p).abs() / scale
+            }
+        })
+        .sum::<f64>();
+    Ok(100.0 * sum / n)
+}
+
+/// Mean absolute scaled error, the mean absolute error relative to the in-sample mean absolute
+/// error of the seasonal naive forecast `training[t - period]`.
+///
+/// Returns an error if `training` has no more than `period` samples or the naive forecast is
+/// exact on it.
+pub fn mase(actual: &[f64], predicted: &[f64], training: &[f64], period: usize) -> Result<f64> {
+    if period == 0 {
+        return Err(Error::InvalidParameter("period must be positive".to_string()));
+    }
+    check_len(training.len(), period + 1)?;
+    check_no_nan(training)?;
+    let scale = training.windows(period + 1).map(|w| (w[period] - w[0]).abs()).sum::<f64>()
+        / (training.len() - period) as f64;
+    if scale == 0.0 {
+        return Err(Error::InvalidParameter(
+            "the naive forecast of the training series must have errors".to_string(),
+        ));
+    }
+    Ok(mae(actual, predicted)? / scale)
+}
+
+/// Pinball loss of the forecasts `predicted` of the `tau` quantile.
+pub fn pinball_loss(actual: &[f64], predicted: &[f64], tau: f64) -> Result<f64> {
+    if !(0.0..=1.0).contains(&tau) {
+        return Err(Error::OutOfRange { value: tau, start: 0.0, end: 1.0 });
+    }
+    check_pair(actual, predicted)?;
+    let n = actual.len() as f64;
+    let sum = actual
+        .iter()
+        .zip(predicted)
+        .map(|(a, p)| if a >= p { tau * (a - p) } else { (1.0 - tau) * (p - a) })
+        .sum::<f64>();
+    Ok(sum / n)
+}
+
+/// Share of `actual` within `[lower, upper]`.
+pub fn interval_coverage(actual: &[f64], lower: &[f64], upper: &[f64]) -> Result<f64> {
+    check_pair(actual, lower)?;
+    check_pair(actual, upper)?;
+    let inside = (0..actual.len()).filter(|&i| lower[i] <= actual[i] && actual[i] <= upper[i]);
+    Ok(inside.count() as f64 / actual.len() as f64)
+}
+
+/// Training window of a backtest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Window {
+    /// All samples before the origin
+    #[default]
+    Expanding,
+    /// The `initial` samples before the origin
+    Rolling,
+}
+
+/// Settings of `backtest`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BacktestOptions {
+    /// Training samples before the first origin
+    pub initial: usize,
+    /// Forecast steps scored at each origin
+    pub horizon: usize,
+    /// Samples between successive origins
+    pub step: usize,
+    pub window: Window,
+    /// The forecaster's intervals are of level `1 - alpha`, scored by the pinball loss of their
+    /// bounds
+    pub alpha: f64,
+    /// Seasonal period of the naive forecast that scales the MASE
+    pub period: usize,
+}
+
+impl BacktestOptions {
+    pub fn new(initial: usize, horizon: usize) -> Self {
+        BacktestOptions {
+            initial,
+            horizon,
+            step: 1,
+            window: Window::Expanding,
+            alpha: 0.05,
+            period: 1,
+        }
+    }
+}
+
+/// Accuracy of forecasts. Measures that are undefined for the data, like the MAPE of zeros,
+/// are NaN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scores {
+    pub mae: f64,
+    pub rmse: f64,
+    pub mape: f64,
+    pub smape: f64,
+    pub mase: f64,
+    /// Mean pinball loss of the lower bounds as `alpha / 2` and the upper bounds as
+    /// `1 - alpha / 2` quantiles
+    pub pinball: f64,
+    pub coverage: f64,
+}
+
+impl Scores {
+    fn new(
+        actual: &[f64],
+        forecast: &Forecast,
+        training: &[f64],
+        options: &BacktestOptions,
+    ) -> Result<Self> {
+        let tau = options.alpha / 2.0;
+        let pinball = (pinball_loss(actual, &forecast.lower, tau)?
+            + pinball_loss(actual, &forecast.upper, 1.0 - tau)?)
+            / 2.0;
+        Ok(Scores {
+            mae: mae(actual, &forecast.mean)?,
+            rmse: rmse(actual, &forecast.mean)?,
+            mape: mape(actual, &forecast.mean).unwrap_or(f64::NAN),
+            smape: smape(actual, &forecast.mean)?,
+            mase: mase(actual, &forecast.mean, training, options.period).unwrap_or(f64::NAN),
+            pinball,
+            coverage: interval_coverage(actual, &forecast.lower, &forecast.upper)?,
+        })
+    }
+
+    fn mean(scores: &[Scores]) -> Self {
+        let n = scores.len() as f64;
+        let mean = |f: fn(&Scores) -> f64| scores.iter().map(f).sum::<f64>() / n;
+        Scores {
+            mae: mean(|s| s.mae),
+            rmse: mean(|s| s.rmse),
+            mape: mean(|s| s.mape),
+            smape: mean(|s| s.smape),
+            mase: mean(|s| s.mase),
+            pinball: mean(|s| s.pinball),
+            coverage: mean(|s| s.coverage),
+        }
+    }
+}
+
+/// Forecast and scores at one origin of a backtest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fold {
+    /// Index of the first forecast sample
+    pub origin: usize,
+    pub forecast: Forecast,
+    pub scores: Scores,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backtest {
+    pub folds: Vec<Fold>,
+    /// Averages of the scores of the folds
+    pub scores: Scores,
+}
+
+/// Rolling origin evaluation of `forecaster`, which is given a training series and a horizon
+/// and forecasts that many samples after it.
+///
+/// The origins start after `initial` samples and move by `step` while `horizon` samples follow
+/// them. Returns an error if the series is shorter than `initial + horizon`, contains NaN, a
+/// setting is 0, or `forecaster` fails or forecasts fewer than `horizon` samples.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::forecast::arima::Arima;
+/// use mathbox::app::signal::forecast::evaluation::{backtest, BacktestOptions};
+/// let series = (0..60).map(|i| (i as f64 * 0.7).sin() + 0.1 * i as f64).collect::<Vec<f64>>();
+/// let options = BacktestOptions { step: 5, ..BacktestOptions::new(40, 3) };
+/// let result = backtest(&series, |train, h| Arima::new(2, 1, 0).fit(train)?.forecast(h, 0.05), &options).unwrap();
+/// assert_eq!(result.folds.len(), 4);
+/// assert!(result.scores.mase < 1.0);
+/// ```
+pub fn backtest<F: FnMut(&[f64], usize) -> Result<Forecast>>(
+    series: &[f64],
+    mut forecaster: F,
+    options: &BacktestOptions,
+) -> Result<Backtest> {
+    if options.initial == 0 || options.horizon == 0 || options.step == 0 {
+        return Err(Error::InvalidParameter(
+            "initial, horizon and step must be positive".to_string(),
+        ));
+    }
+    if !(options.alpha > 0.0 && options.alpha < 1.0) {
+        return Err(Error::InvalidParameter("alpha must be in (0, 1)".to_string()));
+    }
+    check_len(series.len(), options.initial + options.horizon)?;
+    check_no_nan(series)?;
+    let mut folds = vec![];
+    let mut origin = options.initial;
+    while origin + options.horizon <= series.len() {
+        let start = match options.window {
+            Window::Expanding => 0,
+            Window::Rolling => origin - options.initial,
+        };
+        let training = &series[start..origin];
+        let mut forecast = forecaster(training, options.horizon)?;
+        if [&forecast.mean, &forecast.lower, &forecast.upper]
+            .iter()
+            .any(|x| x.len() < options.horizon)
+        {
+            return Err(Error::InvalidParameter(
+                "the forecaster must forecast the whole horizon".to_string(),
+            ));
+        }
+        for x in [&mut forecast.mean, &mut forecast.lower, &mut forecast.upper] {
+            x.truncate(options.horizon);
+        }
+        let actual = &series[origin..origin + options.horizon];
+        let scores = Scores::new(actual, &forecast, training, options)?;
+        folds.push(Fold { origin, forecast, scores });
+        origin += options.step;
+    }
+    let scores = Scores::mean(&folds.iter().map(|f| f.scores).collect::<Vec<Scores>>());
+    Ok(Backtest { folds, scores })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_point_metrics() {
+        let actual = [2.0, 4.0, 5.0, 10.0];
+        let predicted = [3.0, 4.0, 3.0, 10.0];
+        assert_eq!(mae(&actual, &predicted), Ok(0.75));
+        assert_relative_eq!(rmse(&actual, &predicted).unwrap(), 1.25f64.sqrt(), epsilon = 1e-15);
+        assert_relative_eq!(
+            mape(&actual, &predicted).unwrap(),
+            25.0 * (0.5 + 0.4),
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(
+            smape(&actual, &predicted).unwrap(),
+            25.0 * (2.0 / 5.0 + 2.0 * 2.0 / 8.0),
+            epsilon = 1e-12
+        );
+        assert_eq!(smape(&[0.0, 1.0], &[0.0, 1.0]), Ok(0.0));
+        assert_eq!(smape(&[0.0], &[1.0]), Ok(200.0));
+        // naive errors of the training series average 2 at lag 1 and 2.5 at lag 2
+        let training = [1.0, 2.0, 5.0, 3.0];
+        assert_eq!(mase(&actual, &predicted, &training, 1), Ok(0.75 / 2.0));
+        assert_eq!(mase(&actual, &predicted, &training, 2), Ok(0.75 / 2.5));
+
+        assert!(matches!(mape(&[0.0, 1.0], &[1.0, 1.0]), Err(Error::InvalidParameter(_))));
+        assert!(matches!(
+            mase(&actual, &predicted, &[1.0, 1.0], 1),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert_eq!(
+            mase(&actual, &predicted, &training, 4),
+            Err(Error::InsufficientData { required: 5, actual: 4 })
+        );
+        assert_eq!(mae(&actual, &predicted[..3]), Err(Error::LengthMismatch { left: 4, right: 3 }));
+        assert_eq!(rmse(&[], &[]), Err(Error::InsufficientData { required: 1, actual: 0 }));
+        assert_eq!(mae(&[1.0], &[f64::NAN]), Err(Error::NanValue { index: 0 }));
+    }
+
+    #[test]
+    fn test_interval_metrics() {
+        let actual = [1.0, 5.0, 3.0, 8.0];
+        let lower = [0.0, 1.0, 3.0, 9.0];
+        let upper = [2.0, 4.0, 6.0, 12.0];
+        assert_eq!(interval_coverage(&actual, &lower, &upper), Ok(0.5));
+        // (0.1 * 1 + 0.1 * 4 + 0 + 0.9 * 1) / 4
+        assert_relative_eq!(pinball_loss(&actual, &lower, 0.1).unwrap(), 0.35, epsilon = 1e-15);
+        assert_relative_eq!(pinball_loss(&actual, &actual, 0.3).unwrap(), 0.0);
+        assert_eq!(pinball_loss(&actual, &[2.0; 4], 0.5), Ok((1.0 + 3.0 + 1.0 + 6.0) / 8.0));
+        assert_eq!(
+            pinball_loss(&actual, &lower, 1.5),
+            Err(Error::OutOfRange { value: 1.5, start: 0.0, end: 1.0 })
+        );
+    }
+
+    #[test]
+    fn test_backtest() {
+        let series = (0..20).map(|i| i as f64).collect::<Vec<f64>>();
+        let naive = |training: &[f64], horizon: usize| {
+            let last = training[training.len() - 1];
+            Ok(Forecast {
+                mean: vec![last; horizon],
+                lower: vec![last - 1.5; horizon],
+                upper: vec![last + 1.5; horizon],
+            })
+        };
+        let options = BacktestOptions { step: 3, ..BacktestOptions::new(10, 2) };
+        let result = backtest(&series, naive, &options).unwrap();
+        let origins = result.folds.iter().map(|f| f.origin).collect::<Vec<usize>>();
+        assert_eq!(origins, [10, 13, 16]);
+        for fold in result.folds.iter() {
+            assert_eq!(fold.forecast.mean, vec![fold.origin as f64 - 1.0; 2]);
+            assert_eq!(fold.scores.mae, 1.5);
+            assert_eq!(fold.scores.mase, 1.5);
+            assert_eq!(fold.scores.coverage, 0.5);
+        }
+        assert_eq!(result.scores.mae, 1.5);
+        assert_relative_eq!(result.scores.rmse, 2.5f64.sqrt(), epsilon = 1e-15);
+        // lower bounds 2.5 and 3.5 below and upper bounds 0.5 above and below the actual values
+        let pinball = (0.025 * 6.0 + 0.025 * 0.5 + 0.975 * 0.5) / 4.0;
+        assert_relative_eq!(result.scores.pinball, pinball, epsilon = 1e-12);
+        assert!(result.scores.mape > 0.0 && result.scores.smape > 0.0);
+
+        let mut lengths = vec![];
+        let options = BacktestOptions { window: Window::Rolling, ..BacktestOptions::new(5, 1) };
+        let result = backtest(
+            &series,
+            |training, horizon| {
+                lengths.push(training.len());
+                naive(training, horizon)
+            },
+            &options,
+        )
+        .unwrap();
+        assert_eq!(result.folds.len(), 15);
+        assert!(lengths.iter().all(|&l| l == 5));
+
+        // MAPE is undefined for a series with zeros
+        let options = BacktestOptions::new(1, 1);
+        let result = backtest(&[1.0, 0.0, 2.0], naive, &options).unwrap();
+        assert!(result.scores.mape.is_nan() && result.scores.mase.is_nan());
+    }
+
+    #[test]
+    fn test_backtest_errors() {
+        let series = [1.0, 2.0, 3.0, 4.0];
+        let constant = |_: &[f64], horizon: usize| {
+            Ok(Forecast {
+                mean: vec![0.0; horizon],
+                lower: vec![0.0; horizon],
+                upper: vec![0.0; horizon],
+            })
+        };
+        assert_eq!(
+            backtest(&series, constant, &BacktestOptions::new(3, 2)),
+            Err(Error::InsufficientData { required: 5, actual: 4 })
+        );
+        assert!(matches!(
+            backtest(&series, constant, &BacktestOptions::new(0, 2)),
+            Err(Error::InvalidParameter(_))
+        ));
+        let short = |_: &[f64], _: usize| {
+            Ok(Forecast { mean: vec![0.0], lower: vec![0.0], upper: vec![0.0] })
+        };
+        assert!(matches!(
+            backtest(&series, short, &BacktestOptions::new(2, 2)),
+            Err(Error::InvalidParameter(_))
+        ));
+        let failing = |_: &[f64], _: usize| Err(Error::ConstantSeries);
+        assert_eq!(
+            backtest(&series, failing, &BacktestOptions::new(2, 1)),
+            Err(Error::ConstantSeries)
+        );
+    }
+}
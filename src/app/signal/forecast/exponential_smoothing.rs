@@ -0,0 +1,619 @@
+//! Exponential smoothing
+//!
+//! The ETS(error, trend, seasonal) state space models of Hyndman et al., which contain simple
+//! exponential smoothing, Holt's linear and damped trend methods and Holt-Winters. The smoothing
+//! parameters and the initial level and trend maximize the likelihood, the initial seasonal
+//! states come from a classical decomposition.
+//!
+//! Reference: [Hyndman, Koehler, Ord and Snyder, Forecasting with Exponential Smoothing](https://doi.org/10.1007/978-3-540-71918-2)
+
+use super::Forecast;
+use crate::app::signal::decompose::{seasonal_decompose, SeasonalModel};
+use crate::app::signal::seasonality::detect_periods;
+use crate::error::{check_len, check_no_nan, Error, Result};
+use crate::opt::minimize::{nelder_mead, NelderMeadOptions};
+use crate::stats::estimator::is_constant;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorType {
+    Additive,
+    Multiplicative,
+}
+
+/// Form of the trend or the seasonal component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Component {
+    #[default]
+    None,
+    Additive,
+    Multiplicative,
+}
+
+/// An ETS model to fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExponentialSmoothing {
+    pub error: ErrorType,
+    pub trend: Component,
+    pub damped: bool,
+    pub seasonal: Component,
+    /// Samples per season, detected by `seasonality::detect_periods` if `None`
+    pub period: Option<usize>,
+}
+
+/// A fitted ETS model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EtsModel {
+    /// The fitted model, with the period it used
+    pub specification: ExponentialSmoothing,
+    /// Smoothing of the level
+    pub alpha: f64,
+    /// Smoothing of the trend in Holt's form, 0 without trend
+    pub beta: f64,
+    /// Smoothing of the seasonal component, 0 without season
+    pub gamma: f64,
+    /// Damping of the trend, 1 without damping
+    pub phi: f64,
+    /// One step forecasts of the series
+    pub fitted: Vec<f64>,
+    pub residuals: Vec<f64>,
+    /// Variance of the innovations, which are relative for multiplicative errors
+    pub sigma2: f64,
+    pub log_likelihood: f64,
+    pub aic: f64,
+    pub bic: f64,
+    state: State,
+}
+
+/// Number of simulated paths for the prediction intervals of nonlinear models.
+const SIMULATIONS: usize = 5000;
+
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    level: f64,
+    trend: f64,
+    /// Seasonal states of the phases of the period, the one for sample `t` is at `t % period`
+    seasonal: Vec<f64>,
+}
+
+struct Parameters {
+    alpha: f64,
+    beta: f64,
+    gamma: f64,
+    phi: f64,
+}
+
+fn logistic(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn logit(p: f64) -> f64 {
+    (p / (1.0 - p)).ln()
+}
+
+/// Linear least squares fit `(intercept, slope)` of `y` at times 1, 2, ...
+fn line(y: &[f64]) -> (f64, f64) {
+    let n = y.len() as f64;
+    let t_mean = (n + 1.0) / 2.0;
+    let y_mean = y.iter().sum::<f64>() / n;
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for (i, v) in y.iter().enumerate() {
+        sxy += (i as f64 + 1.0 - t_mean) * (v - y_mean);
+        sxx += (i as f64 + 1.0 - t_mean).powi(2);
+    }
+    let slope = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+    (y_mean - slope * t_mean, slope)
+}
+
+/// Linearly interpolated `p` quantile of sorted values.
+fn quantile(sorted: &[f64], p: f64) -> f64 {
+    let position = p * (sorted.len() - 1) as f64;
+    let i = (position.floor() as usize).min(sorted.len() - 2);
+    sorted[i] + (position - i as f64) * (sorted[i + 1] - sorted[i])
+}
+
+impl Default for ExponentialSmoothing {
+    fn default() -> Self {
+        ExponentialSmoothing::simple()
+    }
+}
+
+impl ExponentialSmoothing {
+    /// Simple exponential smoothing, ETS(A, N, N).
+    pub fn simple() -> Self {
+        ExponentialSmoothing {
+            error: ErrorType::Additive,
+            trend: Component::None,
+            damped: false,
+            seasonal: Component::None,
+            period: None,
+        }
+    }
+
+    /// Holt's linear trend method, ETS(A, A, N), or ETS(A, Ad, N) if `damped`.
+    pub fn holt(damped: bool) -> Self {
+        ExponentialSmoothing::simple().with_trend(Component::Additive, damped)
+    }
+
+    /// Holt-Winters method with additive trend, ETS(A, A, A) for an additive season and
+    /// ETS(M, A, M) for a multiplicative one.
+    pub fn holt_winters(seasonal: Component, period: Option<usize>) -> Self {
+        let error = match seasonal {
+            Component::Multiplicative => ErrorType::Multiplicative,
+            _ => ErrorType::Additive,
+        };
+        ExponentialSmoothing::holt(false).with_error(error).with_seasonal(seasonal, period)
+    }
+
+    pub fn with_error(mut self, error: ErrorType) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_trend(mut self, trend: Component, damped: bool) -> Self {
+        self.trend = trend;
+        self.damped = damped && trend != Component::None;
+        self
+    }
+
+    pub fn with_seasonal(mut self, seasonal: Component, period: Option<usize>) -> Self {
+        self.seasonal = seasonal;
+        self.period = period;
+        self
+    }
+
+    fn is_multiplicative(&self) -> bool {
+        self.error == ErrorType::Multiplicative
+            || self.trend == Component::Multiplicative
+            || self.seasonal == Component::Multiplicative
+    }
+
+    /// Number of smoothing parameters.
+    fn n_smoothing(&self) -> usize {
+        1 + (self.trend != Component::None) as usize
+            + (self.seasonal != Component::None) as usize
+            + self.damped as usize
+    }
+
+    /// Smoothing parameters and initial level and trend from unconstrained `u`.
+    fn parameters(&self, u: &[f64]) -> (Parameters, f64, f64) {
+        let mut u = u.iter().copied();
+        let alpha = logistic(u.next().unwrap_or(0.0));
+        let beta = match self.trend {
+            Component::None => 0.0,
+            _ => logistic(u.next().unwrap_or(0.0)),
+        };
+        let gamma = match self.seasonal {
+            Component::None => 0.0,
+            _ => (1.0 - alpha) * logistic(u.next().unwrap_or(0.0)),
+        };
+        let phi = if self.damped { 0.8 + 0.18 * logistic(u.next().unwrap_or(0.0)) } else { 1.0 };
+        let level = u.next().unwrap_or(0.0);
+        let trend = u.next().unwrap_or(0.0);
+        (Parameters { alpha, beta, gamma, phi }, level, trend)
+    }
+
+    /// Trend part of the forecast with the damped sum `k` of trend steps.
+    fn base(&self, state: &State, k: f64) -> f64 {
+        match self.trend {
+            Component::None => state.level,
+            Component::Additive => state.level + k * state.trend,
+            Component::Multiplicative => state.level * state.trend.powf(k),
+        }
+    }
+
+    fn season(&self, base: f64, state: &State, t: usize) -> f64 {
+        match self.seasonal {
+            Component::None => base,
+            Component::Additive => base + state.seasonal[t % state.seasonal.len()],
+            Component::Multiplicative => base * state.seasonal[t % state.seasonal.len()],
+        }
+    }
+
+    /// Update `state` with the observation `y` at sample `t`.
+    fn update(&self, p: &Parameters, state: &mut State, t: usize, y: f64) {
+        let base = self.base(state, p.phi);
+        let m = state.seasonal.len().max(1);
+        let deseasonalized = match self.seasonal {
+            Component::None => y,
+            Component::Additive => y - state.seasonal[t % m],
+            Component::Multiplicative => y / state.seasonal[t % m],
+        };
+        let level = p.alpha * deseasonalized + (1.0 - p.alpha) * base;
+        state.trend = match self.trend {
+            Component::None => 0.0,
+            Component::Additive => {
+                p.beta * (level - state.level) + (1.0 - p.beta) * p.phi * state.trend
+            }
+            Component::Multiplicative => {
+                p.beta * level / state.level + (1.0 - p.beta) * state.trend.powf(p.phi)
+            }
+        };
+        match self.seasonal {
+            Component::None => {}
+            Component::Additive => {
+                state.seasonal[t % m] =
+                    p.gamma * (y - base) + (1.0 - p.gamma) * state.seasonal[t % m]
+            }
+            Component::Multiplicative => {
+                state.seasonal[t % m] = p.gamma * y / base + (1.0 - p.gamma) * state.seasonal[t % m]
+            }
+        }
+        state.level = level;
+    }
+
+    /// Innovation of `y` against the one step forecast `mu`.
+    fn innovation(&self, y: f64, mu: f64) -> f64 {
+        match self.error {
+            ErrorType::Additive => y - mu,
+            ErrorType::Multiplicative => (y - mu) / mu,
+        }
+    }
+
+    /// One step forecasts, innovations and final state, `None` if a multiplicative model
+    /// leaves the positive values.
+    fn filter(
+        &self,
+        y: &[f64],
+        p: &Parameters,
+        mut state: State,
+    ) -> Option<(Vec<f64>, Vec<f64>, State)> {
+        let mut fitted = Vec::with_capacity(y.len());
+        let mut innovations = Vec::with_capacity(y.len());
+        for (t, &value) in y.iter().enumerate() {
+            let mu = self.season(self.base(&state, p.phi), &state, t);
+            if !mu.is_finite() || self.is_multiplicative() && mu <= 0.0 {
+                return None;
+            }
+            fitted.push(mu);
+            innovations.push(self.innovation(value, mu));
+            self.update(p, &mut state, t, value);
+        }
+        Some((fitted, innovations, state))
+    }
+
+    /// Twice the negative log likelihood without its constant terms, infinite for invalid
+    /// parameters.
+    fn objective(&self, y: &[f64], u: &[f64], seasonal: &[f64]) -> f64 {
+        let (p, level, trend) = self.parameters(u);
+        if self.trend == Component::Multiplicative && trend <= 0.0 {
+            return f64::INFINITY;
+        }
+        let state = State { level, trend, seasonal: seasonal.to_vec() };
+        let Some((fitted, innovations, _)) = self.filter(y, &p, state) else {
+            return f64::INFINITY;
+        };
+        let n = y.len() as f64;
+        let sse = innovations.iter().map(|e| e * e).sum::<f64>();
+        let log_mu = match self.error {
+            ErrorType::Additive => 0.0,
+            ErrorType::Multiplicative => fitted.iter().map(|mu| mu.abs().ln()).sum::<f64>(),
+        };
+        n * sse.ln() + 2.0 * log_mu
+    }
+
+    /// Fit the model to `series`.
+    ///
+    /// Returns an error if the series contains NaN, is constant, is too short for the
+    /// parameters and two seasons, has no detectable period for a seasonal model without one,
+    /// or is not positive for a multiplicative model.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathbox::app::signal::forecast::exponential_smoothing::{Component, ExponentialSmoothing};
+    /// let series = (0..48)
+    ///     .map(|i| 10.0 + 0.5 * i as f64 + [3.0, -1.0, -4.0, 2.0][i % 4])
+    ///     .collect::<Vec<f64>>();
+    /// let model = ExponentialSmoothing::holt_winters(Component::Additive, None).fit(&series).unwrap();
+    /// assert_eq!(model.specification.period, Some(4));
+    /// let forecast = model.forecast(4, 0.05).unwrap();
+    /// assert!((forecast.mean[0] - 37.0).abs() < 0.1);
+    /// ```
+    pub fn fit<T: Into<f64> + Copy>(&self, series: &[T]) -> Result<EtsModel> {
+        check_no_nan(series)?;
+        let y = series.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+        if self.is_multiplicative() && y.iter().any(|&x| x <= 0.0) {
+            return Err(Error::InvalidParameter(
+                "series must be positive for a multiplicative model".to_string(),
+            ));
+        }
+        let period = match (self.seasonal, self.period) {
+            (Component::None, _) => 1,
+            (_, Some(period)) if period < 2 => {
+                return Err(Error::InvalidParameter("period must be at least 2".to_string()))
+            }
+            (_, Some(period)) => period,
+            (_, None) => match detect_periods(&y, 1.0)?.first() {
+                Some(candidate) => candidate.period,
+                None => {
+                    return Err(Error::InvalidParameter("no seasonal period found".to_string()))
+                }
+            },
+        };
+        let spec = ExponentialSmoothing { period: Some(period), ..*self };
+        let has_trend = spec.trend != Component::None;
+        let n_initial = 1 + has_trend as usize + (period - 1);
+        let k = spec.n_smoothing() + n_initial + 1;
+        check_len(y.len(), (k + 2).max(if period > 1 { 2 * period } else { 0 }))?;
+        if is_constant(&y) {
+            return Err(Error::ConstantSeries);
+        }
+
+        let seasonal = match spec.seasonal {
+            Component::None => vec![],
+            Component::Additive => {
+                seasonal_decompose(&y, period, SeasonalModel::Additive)?.seasonal
+            }
+            Component::Multiplicative => {
+                seasonal_decompose(&y, period, SeasonalModel::Multiplicative)?.seasonal
+            }
+        };
+        let seasonal = seasonal.into_iter().take(period).collect::<Vec<f64>>();
+        let start = y.len().min(10.max(2 * period));
+        let deseasonalized = (0..start)
+            .map(|t| match spec.seasonal {
+                Component::None => y[t],
+                Component::Additive => y[t] - seasonal[t % period],
+                Component::Multiplicative => y[t] / seasonal[t % period],
+            })
+            .collect::<Vec<f64>>();
+        let (intercept, slope) = line(&deseasonalized);
+
+        let mut x0 = vec![logit(0.3)];
+        if has_trend {
+            x0.push(logit(0.1));
+        }
+        if spec.seasonal != Component::None {
+            x0.push(logit(0.1 / 0.7));
+        }
+        if spec.damped {
+            x0.push(logit(0.5));
+        }
+        match spec.trend {
+            Component::None => x0.push(y[..start].iter().sum::<f64>() / start as f64),
+            Component::Additive => x0.extend([intercept, slope]),
+            Component::Multiplicative => {
+                let level = if intercept > 0.0 { intercept } else { y[0] };
+                x0.extend([level, (1.0 + slope / level).max(0.01)]);
+            }
+        }
+        let minimum = nelder_mead(
+            |u: &[f64]| spec.objective(&y, u, &seasonal),
+            &x0,
+            &NelderMeadOptions::default(),
+        );
+        let (p, level, trend) = spec.parameters(&minimum.x);
+        let state = State { level, trend, seasonal };
+        let (fitted, innovations, state) = spec.filter(&y, &p, state).ok_or_else(|| {
+            Error::InvalidParameter("no valid parameters found for the model".to_string())
+        })?;
+
+        let n = y.len() as f64;
+        let sigma2 = innovations.iter().map(|e| e * e).sum::<f64>() / n;
+        let log_mu = match spec.error {
+            ErrorType::Additive => 0.0,
+            ErrorType::Multiplicative => fitted.iter().map(|mu| mu.abs().ln()).sum::<f64>(),
+        };
+        let log_likelihood = -0.5 * n * ((2.0 * PI * sigma2).ln() + 1.0) - log_mu;
+        Ok(EtsModel {
+            specification: spec,
+            alpha: p.alpha,
+            beta: p.beta,
+            gamma: p.gamma,
+            phi: p.phi,
+            residuals: y.iter().zip(fitted.iter()).map(|(a, b)| a - b).collect(),
+            fitted,
+            sigma2,
+            log_likelihood,
+            aic: -2.0 * log_likelihood + 2.0 * k as f64,
+            bic: -2.0 * log_likelihood + k as f64 * n.ln(),
+            state,
+        })
+    }
+}
+
+impl EtsModel {
+    fn parameters(&self) -> Parameters {
+        Parameters { alpha: self.alpha, beta: self.beta, gamma: self.gamma, phi: self.phi }
+    }
+
+    /// Forecast `horizon` steps after the fitted series with `1 - alpha` prediction intervals.
+    ///
+    /// The intervals are exact for the linear models with additive errors and from simulated
+    /// sample paths with a fixed seed otherwise.
+    pub fn forecast(&self, horizon: usize, alpha: f64) -> Result<Forecast> {
+        if !(alpha > 0.0 && alpha < 1.0) {
+            return Err(Error::InvalidParameter("alpha must be in (0, 1)".to_string()));
+        }
+        let spec = &self.specification;
+        let n = self.fitted.len();
+        let period = self.state.seasonal.len().max(1);
+        // damped sums φ + ... + φ^h
+        let damped = (1..=horizon)
+            .scan(0.0, |sum, h| {
+                *sum += self.phi.powi(h as i32);
+                Some(*sum)
+            })
+            .collect::<Vec<f64>>();
+        let mean = (0..horizon)
+            .map(|h| spec.season(spec.base(&self.state, damped[h]), &self.state, n + h))
+            .collect::<Vec<f64>>();
+
+        if !spec.is_multiplicative() {
+            let mut sum = 0.0;
+            let variance = (0..horizon)
+                .map(|h| {
+                    if h > 0 {
+                        let mut c = self.alpha;
+                        if spec.trend != Component::None {
+                            c += self.alpha * self.beta * damped[h - 1];
+                        }
+                        if spec.seasonal != Component::None && h.is_multiple_of(period) {
+                            c += self.gamma;
+                        }
+                        sum += c * c;
+                    }
+                    self.sigma2 * (1.0 + sum)
+                })
+                .collect::<Vec<f64>>();
+            return Ok(Forecast::normal(mean, &variance, alpha));
+        }
+
+        let p = self.parameters();
+        let sigma = self.sigma2.sqrt();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut paths = vec![Vec::with_capacity(SIMULATIONS); horizon];
+        for _ in 0..SIMULATIONS {
+            let mut state = self.state.clone();
+            for (h, path) in paths.iter_mut().enumerate() {
+                let mu = spec.season(spec.base(&state, self.phi), &state, n + h);
+                // Box-Muller transform of two uniform samples
+                let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+                let e = sigma * (-2.0 * u.ln()).sqrt() * (2.0 * PI * rng.gen::<f64>()).cos();
+                let y = match spec.error {
+                    ErrorType::Additive => mu + e,
+                    ErrorType::Multiplicative => mu * (1.0 + e),
+                };
+                path.push(y);
+                spec.update(&p, &mut state, n + h, y);
+            }
+        }
+        let mut lower = Vec::with_capacity(horizon);
+        let mut upper = Vec::with_capacity(horizon);
+        for mut path in paths {
+            path.sort_by(|a, b| a.total_cmp(b));
+            lower.push(quantile(&path, alpha / 2.0));
+            upper.push(quantile(&path, 1.0 - alpha / 2.0));
+        }
+        Ok(Forecast { mean, lower, upper })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::signal::filter::exponential_moving_average;
+    use approx::assert_relative_eq;
+
+    fn noise(n: usize, scale: f64, seed: u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n).map(|_| scale * rng.gen_range(-0.5..0.5)).collect()
+    }
+
+    #[test]
+    fn test_simple() {
+        let series = noise(200, 1.0, 1).iter().map(|e| 20.0 + e).collect::<Vec<f64>>();
+        let model = ExponentialSmoothing::simple().fit(&series).unwrap();
+        assert!(model.alpha < 0.2);
+        assert_eq!((model.beta, model.gamma, model.phi), (0.0, 0.0, 1.0));
+        assert_relative_eq!(model.sigma2, 1.0 / 12.0, max_relative = 0.15);
+        let forecast = model.forecast(3, 0.05).unwrap();
+        let level = model.state.level;
+        assert!(forecast.mean.iter().all(|&m| m == level));
+        for (h, upper) in forecast.upper.iter().enumerate() {
+            let variance = model.sigma2 * (1.0 + h as f64 * model.alpha.powi(2));
+            assert_relative_eq!(
+                upper - level,
+                1.959_963_984_540_054 * variance.sqrt(),
+                epsilon = 1e-9
+            );
+        }
+
+        // the level is the exponential moving average once started at the first sample
+        let p = model.parameters();
+        let state = State { level: series[0], trend: 0.0, seasonal: vec![] };
+        let spec = ExponentialSmoothing::simple();
+        let (_, _, state) = spec.filter(&series, &p, state).unwrap();
+        let average = exponential_moving_average(&series, model.alpha).unwrap();
+        assert_relative_eq!(state.level, average[series.len() - 1], epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_holt() {
+        let series = noise(100, 0.5, 2)
+            .iter()
+            .enumerate()
+            .map(|(i, e)| 5.0 + 0.5 * i as f64 + e)
+            .collect::<Vec<f64>>();
+        let model = ExponentialSmoothing::holt(false).fit(&series).unwrap();
+        let forecast = model.forecast(10, 0.05).unwrap();
+        for (h, m) in forecast.mean.iter().enumerate() {
+            assert_relative_eq!(*m, 5.0 + 0.5 * (100 + h) as f64, epsilon = 0.5);
+        }
+        let widths = forecast.upper.iter().zip(forecast.lower.iter()).map(|(u, l)| u - l);
+        assert!(widths.collect::<Vec<f64>>().windows(2).all(|w| w[1] >= w[0]));
+
+        let damped = ExponentialSmoothing::holt(true).fit(&series).unwrap();
+        assert!(damped.phi >= 0.8 && damped.phi < 0.98);
+        let forecast = damped.forecast(30, 0.05).unwrap();
+        let steps = forecast.mean.windows(2).map(|w| w[1] - w[0]).collect::<Vec<f64>>();
+        assert!(steps.windows(2).all(|s| s[1] < s[0]));
+
+        let multiplicative = ExponentialSmoothing::simple()
+            .with_trend(Component::Multiplicative, false)
+            .with_error(ErrorType::Multiplicative);
+        let series = (0..60).map(|i| 100.0 * 1.02f64.powi(i)).collect::<Vec<f64>>();
+        let model = multiplicative.fit(&series).unwrap();
+        let forecast = model.forecast(5, 0.1).unwrap();
+        for (h, m) in forecast.mean.iter().enumerate() {
+            assert_relative_eq!(*m, 100.0 * 1.02f64.powi(60 + h as i32), max_relative = 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_holt_winters() {
+        let pattern = [5.0, 8.0, 3.0, -2.0, -6.0, -8.0, -4.0, 0.0, 2.0, 4.0, -1.0, -1.0];
+        let noise = noise(120, 1.0, 3);
+        let series = (0..120)
+            .map(|i| 50.0 + 0.2 * i as f64 + pattern[i % 12] + noise[i])
+            .collect::<Vec<f64>>();
+        let model =
+            ExponentialSmoothing::holt_winters(Component::Additive, Some(12)).fit(&series).unwrap();
+        let forecast = model.forecast(12, 0.05).unwrap();
+        for (h, m) in forecast.mean.iter().enumerate() {
+            assert_relative_eq!(*m, 50.0 + 0.2 * (120 + h) as f64 + pattern[h % 12], epsilon = 1.0);
+        }
+        assert!(model.gamma < 1.0 - model.alpha);
+
+        let series = (0..120)
+            .map(|i| (100.0 + i as f64) * (1.0 + pattern[i % 12] / 20.0) + noise[i])
+            .collect::<Vec<f64>>();
+        let model = ExponentialSmoothing::holt_winters(Component::Multiplicative, None)
+            .fit(&series)
+            .unwrap();
+        assert_eq!(model.specification.period, Some(12));
+        let forecast = model.forecast(24, 0.05).unwrap();
+        for (h, m) in forecast.mean.iter().enumerate() {
+            let expected = (220.0 + h as f64) * (1.0 + pattern[h % 12] / 20.0);
+            assert_relative_eq!(*m, expected, max_relative = 0.03);
+            assert!(forecast.lower[h] < *m && *m < forecast.upper[h]);
+        }
+        assert!(forecast.upper[12] - forecast.lower[12] > forecast.upper[0] - forecast.lower[0]);
+        assert!(model.aic < model.bic);
+    }
+
+    #[test]
+    fn test_exponential_smoothing_errors() {
+        let series = noise(30, 1.0, 4);
+        let multiplicative = ExponentialSmoothing::simple().with_error(ErrorType::Multiplicative);
+        assert!(matches!(multiplicative.fit(&series), Err(Error::InvalidParameter(_))));
+        let seasonal = ExponentialSmoothing::holt_winters(Component::Additive, Some(20));
+        assert_eq!(
+            seasonal.fit(&series),
+            Err(Error::InsufficientData { required: 40, actual: 30 })
+        );
+        let line = (0..30).map(|i| i as f64).collect::<Vec<f64>>();
+        let seasonal = ExponentialSmoothing::holt_winters(Component::Additive, None);
+        assert!(matches!(seasonal.fit(&line), Err(Error::InvalidParameter(_))));
+        assert_eq!(ExponentialSmoothing::simple().fit(&[2.0; 10]), Err(Error::ConstantSeries));
+        assert_eq!(
+            ExponentialSmoothing::simple().fit(&[1.0, f64::NAN, 2.0]),
+            Err(Error::NanValue { index: 1 })
+        );
+        let model = ExponentialSmoothing::simple().fit(&series).unwrap();
+        assert!(matches!(model.forecast(3, 0.0), Err(Error::InvalidParameter(_))));
+    }
+}
@@ -0,0 +1,26 @@
+//! Time series forecasting
+
+pub mod arima;
+pub mod evaluation;
+pub mod exponential_smoothing;
+
+use crate::stats::distribution::normal_quantile;
+
+/// Point forecasts with a prediction interval, one value per step ahead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forecast {
+    pub mean: Vec<f64>,
+    pub lower: Vec<f64>,
+    pub upper: Vec<f64>,
+}
+
+impl Forecast {
+    /// Normal prediction intervals of level `1 - alpha` around `mean` with the forecast error
+    /// variances `variance`.
+    pub(crate) fn normal(mean: Vec<f64>, variance: &[f64], alpha: f64) -> Self {
+        let z = normal_quantile(1.0 - alpha / 2.0);
+        let lower = mean.iter().zip(variance).map(|(m, v)| m - z * v.sqrt()).collect();
+        let upper = mean.iter().zip(variance).map(|(m, v)| m + z * v.sqrt()).collect();
+        Forecast { mean, lower, upper }
+    }
+}
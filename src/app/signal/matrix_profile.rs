@@ -0,0 +1,243 @@
+//! Matrix profile
+//!
+//! The matrix profile of a series gives, for every subsequence of a window length, the
+//! z-normalized Euclidean distance to its most similar other subsequence. Its smallest values
+//! are motifs, patterns that repeat, and its largest values are discords, subsequences unlike
+//! anything else in the series.
+//!
+//! Reference: [STOMP](https://doi.org/10.1109/ICDM.2016.0085)
+
+use crate::app::signal::transform::sliding_dot_product;
+use crate::error::{check_len, check_no_nan, Error, Result};
+
+/// Matrix profile of a series, see `matrix_profile`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatrixProfile {
+    pub window: usize,
+    /// Distance from every subsequence to its nearest neighbor
+    pub profile: Vec<f64>,
+    /// Start of the nearest neighbor of every subsequence
+    pub index: Vec<usize>,
+}
+
+/// A pair of similar subsequences.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Motif {
+    pub index: usize,
+    pub neighbor: usize,
+    pub distance: f64,
+}
+
+/// A subsequence far from all others.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Discord {
+    pub index: usize,
+    /// Distance to its nearest neighbor
+    pub distance: f64,
+}
+
+/// Z-normalized Euclidean distance between subsequences of `window` samples with the dot
+/// product `qt`, means `mu` and standard deviations `sigma`. A constant subsequence is at 0 from
+/// another constant one and at `sqrt(window)` from the others.
+fn distance(qt: f64, window: usize, mu: (f64, f64), sigma: (Option<f64>, Option<f64>)) -> f64 {
+    let m = window as f64;
+    match sigma {
+        (None, None) => 0.0,
+        (Some(si), Some(sj)) => {
+            let correlation = (qt - m * mu.0 * mu.1) / (m * si * sj);
+            (2.0 * m * (1.0 - correlation)).max(0.0).sqrt()
+        }
+        _ => m.sqrt(),
+    }
+}
+
+/// Matrix profile of `series` for subsequences of `window` samples, by STOMP.
+///
+/// The dot products of the first subsequence with all others come from
+/// `transform::sliding_dot_product`, and each next row of dot products is updated from the
+/// previous one in constant time per entry, so the profile takes O(n^2) time and O(n) memory.
+/// Neighbors that overlap a subsequence by more than three quarters of the window are trivial
+/// matches and excluded.
+///
+/// Returns an error for a NaN, a `window` below 3 or a series shorter than two windows.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::matrix_profile::matrix_profile;
+/// let mut series = (0..200).map(|i| (i as f64 * 0.3).sin()).collect::<Vec<f64>>();
+/// series[120] += 2.0;
+/// let mp = matrix_profile(&series, 20).unwrap();
+/// let discord = mp.discords(1)[0];
+/// assert!(discord.index > 100 && discord.index <= 120);
+/// ```
+pub fn matrix_profile<T: Into<f64> + Copy>(series: &[T], window: usize) -> Result<MatrixProfile> {
+    check_no_nan(series)?;
+    if window < 3 {
+        return Err(Error::InvalidParameter("window must be at least 3".to_string()));
+    }
+    check_len(series.len(), 2 * window)?;
+    // z-normalized distances do not depend on the offset, which centering removes from the sums
+    let offset = series.iter().map(|&x| x.into()).sum::<f64>() / series.len() as f64;
+    let x = series.iter().map(|&x| x.into() - offset).collect::<Vec<f64>>();
+    let (n, m) = (x.len(), window);
+    let l = n - m + 1;
+    let exclusion = m.div_ceil(4);
+
+    let mut sum = vec![0.0; n + 1];
+    let mut sum_squares = vec![0.0; n + 1];
+    for (i, v) in x.iter().enumerate() {
+        sum[i + 1] = sum[i] + v;
+        sum_squares[i + 1] = sum_squares[i] + v * v;
+    }
+    let scale = x.iter().fold(0.0f64, |a, v| a.max(v.abs()));
+    let mut mu = Vec::with_capacity(l);
+    let mut sigma = Vec::with_capacity(l);
+    for i in 0..l {
+        let mean = (sum[i + m] - sum[i]) / m as f64;
+        let variance = (sum_squares[i + m] - sum_squares[i]) / m as f64 - mean * mean;
+        let std = variance.max(0.0).sqrt();
+        mu.push(mean);
+        sigma.push(if std > 1e-8 * scale.max(1.0) { Some(std) } else { None });
+    }
+
+    let mut profile = vec![f64::INFINITY; l];
+    let mut index = vec![0; l];
+    // dot products along the row i, only those at j >= i + exclusion are kept up to date
+    let mut qt = sliding_dot_product(&x[..m], &x)?;
+    for i in 0..l {
+        if i > 0 {
+            for j in (i + exclusion..l).rev() {
+                qt[j] = qt[j - 1] - x[i - 1] * x[j - 1] + x[i + m - 1] * x[j + m - 1];
+            }
+        }
+        for j in i + exclusion..l {
+            let d = distance(qt[j], m, (mu[i], mu[j]), (sigma[i], sigma[j]));
+            if d < profile[i] {
+                profile[i] = d;
+                index[i] = j;
+            }
+            if d < profile[j] {
+                profile[j] = d;
+                index[j] = i;
+            }
+        }
+    }
+    Ok(MatrixProfile { window, profile, index })
+}
+
+impl MatrixProfile {
+    /// Start indices sorted by the profile, skipping those that overlap an already taken one.
+    fn non_overlapping(&self, k: usize, descending: bool) -> Vec<usize> {
+        let mut order = (0..self.profile.len()).collect::<Vec<usize>>();
+        order.sort_by(|&a, &b| self.profile[a].total_cmp(&self.profile[b]));
+        if descending {
+            order.reverse();
+        }
+        let mut chosen = vec![];
+        let mut taken: Vec<usize> = vec![];
+        for i in order {
+            if chosen.len() == k {
+                break;
+            }
+            // a motif takes its neighbor as well
+            let members = if descending { vec![i] } else { vec![i, self.index[i]] };
+            if members.iter().all(|m| taken.iter().all(|t| t.abs_diff(*m) >= self.window)) {
+                chosen.push(i);
+                taken.extend(members);
+            }
+        }
+        chosen
+    }
+
+    /// The `k` closest pairs of subsequences, closest first, that do not overlap each other.
+    pub fn motifs(&self, k: usize) -> Vec<Motif> {
+        self.non_overlapping(k, false)
+            .into_iter()
+            .map(|i| Motif {
+                index: i.min(self.index[i]),
+                neighbor: i.max(self.index[i]),
+                distance: self.profile[i],
+            })
+            .collect()
+    }
+
+    /// The `k` subsequences farthest from their nearest neighbors, farthest first, that do not
+    /// overlap each other.
+    pub fn discords(&self, k: usize) -> Vec<Discord> {
+        self.non_overlapping(k, true)
+            .into_iter()
+            .map(|i| Discord { index: i, distance: self.profile[i] })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn z_normalized_distance(a: &[f64], b: &[f64]) -> f64 {
+        let normalize = |x: &[f64]| {
+            let mean = x.iter().sum::<f64>() / x.len() as f64;
+            let std = (x.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / x.len() as f64).sqrt();
+            x.iter().map(|v| (v - mean) / std).collect::<Vec<f64>>()
+        };
+        let (a, b) = (normalize(a), normalize(b));
+        a.iter().zip(&b).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt()
+    }
+
+    #[test]
+    fn test_matrix_profile() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let series = (0..80).map(|_| 100.0 + rng.gen::<f64>()).collect::<Vec<f64>>();
+        let window = 8;
+        let mp = matrix_profile(&series, window).unwrap();
+        for i in 0..series.len() - window + 1 {
+            let (best, j) = (0..series.len() - window + 1)
+                .filter(|j| j.abs_diff(i) >= 2)
+                .map(|j| (z_normalized_distance(&series[i..i + 8], &series[j..j + 8]), j))
+                .fold((f64::INFINITY, 0), |a, b| if b.0 < a.0 { b } else { a });
+            assert_relative_eq!(mp.profile[i], best, epsilon = 1e-8);
+            assert_eq!(mp.index[i], j);
+        }
+
+        assert_eq!(
+            matrix_profile(&[1.0, 1.0, 1.0, 2.0, 2.0, 2.0], 3).unwrap().profile,
+            vec![0.0, 3f64.sqrt(), 3f64.sqrt(), 0.0]
+        );
+        assert!(matches!(matrix_profile(&series, 2), Err(Error::InvalidParameter(_))));
+        assert_eq!(
+            matrix_profile(&series, 41),
+            Err(Error::InsufficientData { required: 82, actual: 80 })
+        );
+    }
+
+    #[test]
+    fn test_motifs_and_discords() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut series = (0..300).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>();
+        let pattern = (0..20).map(|i| 3.0 * (i as f64 * 0.6).sin()).collect::<Vec<f64>>();
+        for start in [40, 190] {
+            for (k, p) in pattern.iter().enumerate() {
+                series[start + k] += p;
+            }
+        }
+        let mp = matrix_profile(&series, 20).unwrap();
+        let motifs = mp.motifs(2);
+        // shifts of the planted pair match about as well
+        assert_eq!(motifs[0].neighbor - motifs[0].index, 150);
+        assert!(motifs[0].index.abs_diff(40) < 5);
+        assert!(motifs[0].distance < motifs[1].distance);
+        for i in [motifs[1].index, motifs[1].neighbor] {
+            assert!(i.abs_diff(motifs[0].index) >= 20 && i.abs_diff(motifs[0].neighbor) >= 20);
+        }
+
+        let discords = mp.discords(3);
+        assert_eq!(discords.len(), 3);
+        assert!(discords.windows(2).all(|d| d[0].distance >= d[1].distance));
+        assert!(discords[0].index.abs_diff(discords[1].index) >= 20);
+    }
+}
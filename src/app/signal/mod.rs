@@ -1,6 +1,12 @@
 //! Time series signals
 
 pub mod change_points;
+pub mod control_chart;
+pub mod decompose;
+pub mod dtw;
 pub mod filter;
+pub mod forecast;
+pub mod matrix_profile;
 pub mod outlier;
+pub mod seasonality;
 pub mod transform;
@@ -0,0 +1,274 @@
+//! Isolation Forest
+//!
+//! Anomalies are few and different, so random axis-parallel splits isolate them after fewer
+//! splits than normal points. An Isolation Forest grows random trees on subsamples of the rows
+//! and scores a row by its average depth in the trees, which makes it work on several features
+//! at once.
+//!
+//! Reference: [Liu, Ting & Zhou (2008)](https://doi.org/10.1109/ICDM.2008.17)
+
+use crate::error::{check_len, check_no_nan, check_same_len, Error, Result};
+use rand::rngs::StdRng;
+use rand::seq::index::sample;
+use rand::{Rng, SeedableRng};
+
+/// Settings of an Isolation Forest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsolationForest {
+    /// Number of trees
+    pub trees: usize,
+    /// Rows drawn without replacement for every tree, all rows if there are fewer
+    pub sample_size: usize,
+    /// Seed of the random number generator, from the OS if `None`
+    pub seed: Option<u64>,
+}
+
+impl Default for IsolationForest {
+    fn default() -> Self {
+        IsolationForest { trees: 100, sample_size: 256, seed: None }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf { size: usize },
+    Split { feature: usize, threshold: f64, left: usize, right: usize },
+}
+
+/// Nodes of a tree, the root first.
+#[derive(Debug, Clone)]
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+/// A fitted Isolation Forest.
+#[derive(Debug, Clone)]
+pub struct IsolationForestModel {
+    trees: Vec<Tree>,
+    features: usize,
+    sample_size: usize,
+}
+
+/// Average path length of an unsuccessful search in a binary search tree of `n` points, which
+/// normalizes the depths.
+fn average_path_length(n: usize) -> f64 {
+    match n {
+        0 | 1 => 0.0,
+        2 => 1.0,
+        _ => {
+            let n = n as f64;
+            2.0 * ((n - 1.0).ln() + 0.577_215_664_901_532_9) - 2.0 * (n - 1.0) / n
+        }
+    }
+}
+
+/// Returns `Error::NanValue` with the position of a NaN in `row`, or an error for an infinity.
+fn check_features(row: &[f64]) -> Result<()> {
+    check_no_nan(row)?;
+    if row.iter().any(|x| x.is_infinite()) {
+        return Err(Error::InvalidParameter("features must be finite".to_string()));
+    }
+    Ok(())
+}
+
+impl IsolationForest {
+    pub fn new(trees: usize, sample_size: usize) -> Self {
+        IsolationForest { trees, sample_size, seed: None }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Grow the trees on `rows` of features.
+    ///
+    /// Returns an error for no rows, rows of different or zero lengths, a NaN or an infinity, or
+    /// a `trees` or `sample_size` of 0. The index of a NaN is its position in its row, as in
+    /// `IsolationForestModel::score`.
+    pub fn fit<R: AsRef<[f64]>>(&self, rows: &[R]) -> Result<IsolationForestModel> {
+        check_len(rows.len(), 1)?;
+        if self.trees == 0 || self.sample_size == 0 {
+            return Err(Error::InvalidParameter(
+                "trees and sample_size must be positive".to_string(),
+            ));
+        }
+        let features = rows[0].as_ref().len();
+        if features == 0 {
+            return Err(Error::InvalidParameter("rows must not be empty".to_string()));
+        }
+        for row in rows {
+            check_same_len(features, row.as_ref().len())?;
+            check_features(row.as_ref())?;
+        }
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let sample_size = self.sample_size.min(rows.len());
+        let max_depth = (sample_size as f64).log2().ceil() as usize;
+        let trees = (0..self.trees)
+            .map(|_| {
+                let subsample = sample(&mut rng, rows.len(), sample_size).into_vec();
+                let mut tree = Tree { nodes: vec![] };
+                tree.grow(rows, subsample, 0, max_depth, &mut rng);
+                tree
+            })
+            .collect();
+        Ok(IsolationForestModel { trees, features, sample_size })
+    }
+}
+
+impl Tree {
+    /// Add the subtree isolating the rows `indices` and return the index of its root.
+    fn grow<R: AsRef<[f64]>>(
+        &mut self,
+        rows: &[R],
+        indices: Vec<usize>,
+        depth: usize,
+        max_depth: usize,
+        rng: &mut StdRng,
+    ) -> usize {
+        let node = self.nodes.len();
+        self.nodes.push(Node::Leaf { size: indices.len() });
+        if indices.len() <= 1 || depth >= max_depth {
+            return node;
+        }
+        // pick a feature among those that are not constant in the node
+        let features = rows[0].as_ref().len();
+        let ranges = (0..features)
+            .map(|f| {
+                indices
+                    .iter()
+                    .map(|&i| rows[i].as_ref()[f])
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| (lo.min(x), hi.max(x)))
+            })
+            .enumerate()
+            .filter(|(_, (lo, hi))| lo < hi)
+            .collect::<Vec<_>>();
+        if ranges.is_empty() {
+            return node;
+        }
+        let (feature, (lo, hi)) = ranges[rng.gen_range(0..ranges.len())];
+        // a convex combination, as hi - lo may overflow
+        let u = rng.gen::<f64>();
+        let threshold = lo * (1.0 - u) + hi * u;
+        let (left, right): (Vec<usize>, Vec<usize>) =
+            indices.into_iter().partition(|&i| rows[i].as_ref()[feature] < threshold);
+        let left = self.grow(rows, left, depth + 1, max_depth, rng);
+        let right = self.grow(rows, right, depth + 1, max_depth, rng);
+        self.nodes[node] = Node::Split { feature, threshold, left, right };
+        node
+    }
+
+    /// Depth of the leaf of `row`, plus the expected depth of the rest of the unfinished subtree.
+    fn path_length(&self, row: &[f64]) -> f64 {
+        let mut node = 0;
+        let mut depth = 0.0;
+        loop {
+            match self.nodes[node] {
+                Node::Leaf { size } => return depth + average_path_length(size),
+                Node::Split { feature, threshold, left, right } => {
+                    node = if row[feature] < threshold { left } else { right };
+                    depth += 1.0;
+                }
+            }
+        }
+    }
+}
+
+impl IsolationForestModel {
+    /// Anomaly score of `row` in (0, 1]. Scores close to 1 mark anomalies, while scores well
+    /// below 0.5 are normal.
+    ///
+    /// Returns an error for a row of a different length than the fitted rows or with a NaN or
+    /// an infinity.
+    pub fn score(&self, row: &[f64]) -> Result<f64> {
+        check_same_len(self.features, row.len())?;
+        check_features(row)?;
+        let depth = self.trees.iter().map(|tree| tree.path_length(row)).sum::<f64>()
+            / self.trees.len() as f64;
+        let normalizer = average_path_length(self.sample_size);
+        if normalizer == 0.0 {
+            return Ok(0.5);
+        }
+        Ok(2f64.powf(-depth / normalizer))
+    }
+
+    /// Anomaly scores of `rows`, see `score`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mathbox::app::signal::outlier::isolation_forest::IsolationForest;
+    /// let mut rows = (0..100).map(|i| [(i % 10) as f64, (i / 10) as f64]).collect::<Vec<_>>();
+    /// rows.push([30.0, -20.0]);
+    /// let model = IsolationForest::default().with_seed(1).fit(&rows).unwrap();
+    /// let scores = model.scores(&rows).unwrap();
+    /// assert!(scores[100] > 0.7);
+    /// assert!(scores[..100].iter().all(|&s| s < scores[100]));
+    /// ```
+    pub fn scores<R: AsRef<[f64]>>(&self, rows: &[R]) -> Result<Vec<f64>> {
+        rows.iter().map(|row| self.score(row.as_ref())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_average_path_length() {
+        assert_eq!(average_path_length(1), 0.0);
+        assert_eq!(average_path_length(2), 1.0);
+        // 2 H(255) - 2 * 255 / 256
+        let harmonic = (1..256).map(|i| 1.0 / i as f64).sum::<f64>();
+        assert_relative_eq!(
+            average_path_length(256),
+            2.0 * harmonic - 510.0 / 256.0,
+            epsilon = 1e-2
+        );
+    }
+
+    #[test]
+    fn test_isolation_forest() {
+        // two correlated features
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut rows = (0..500)
+            .map(|_| {
+                let x = (0..4).map(|_| rng.gen::<f64>()).sum::<f64>() * 2.5;
+                vec![x, 2.0 * x + rng.gen::<f64>()]
+            })
+            .collect::<Vec<_>>();
+        rows.push(vec![12.0, 30.0]);
+        let forest = IsolationForest::new(200, 128).with_seed(7);
+        let model = forest.fit(&rows).unwrap();
+        let scores = model.scores(&rows).unwrap();
+        let mean = scores[..500].iter().sum::<f64>() / 500.0;
+        assert!(scores[500] > 0.7 && mean < 0.5);
+        assert!(scores[..500].iter().all(|&s| s < scores[500]));
+        // the same seed grows the same trees
+        assert_eq!(forest.fit(&rows).unwrap().scores(&rows).unwrap(), scores);
+
+        assert_relative_eq!(
+            IsolationForest::default().fit(&[[1.0]]).unwrap().score(&[1.0]).unwrap(),
+            0.5
+        );
+        assert_eq!(model.score(&[1.0]), Err(Error::LengthMismatch { left: 2, right: 1 }));
+        assert_eq!(
+            forest.fit(&[vec![1.0, 2.0], vec![1.0, 2.0], vec![f64::NAN, 2.0]]).unwrap_err(),
+            Error::NanValue { index: 0 }
+        );
+        assert_eq!(model.score(&[1.0, f64::NAN]), Err(Error::NanValue { index: 1 }));
+        assert!(matches!(IsolationForest::new(0, 10).fit(&rows), Err(Error::InvalidParameter(_))));
+        assert!(matches!(
+            forest.fit(&[[1.0], [f64::INFINITY], [2.0]]),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(model.score(&[f64::NEG_INFINITY, 1.0]), Err(Error::InvalidParameter(_))));
+        // a range wider than f64::MAX still splits
+        let model = forest.fit(&[[-1e308], [1e308], [2.0]]).unwrap();
+        assert!(model.score(&[1e308]).unwrap() > 0.0);
+    }
+}
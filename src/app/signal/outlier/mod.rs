@@ -0,0 +1,628 @@
+//! Outlier detection
+//!
+//! The detectors of this module flag samples of a single signal, while `isolation_forest`
+//! scores rows of several features together.
+
+pub mod isolation_forest;
+
+use crate::app::signal::decompose::{stl, StlOptions};
+use crate::error::{check_len, check_no_nan, Error, Result};
+use crate::stats::distribution::student_t_quantile;
+use crate::stats::estimator::{try_mean, try_median, try_std};
+use crate::stats::missing::{valid_samples, NanPolicy};
+
+/// The simplist method to detect the outliers from a signal.
+pub fn iqr_outlier<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    signal: &[X],
+    iqr_factor: Y,
+) -> (Vec<usize>, Vec<usize>) {
+    try_iqr_outlier(signal, iqr_factor).unwrap_or_else(|e| panic!("Cannot detect outliers: {}", e))
+}
+
+/// Same as `iqr_outlier`, but returns an error for an empty signal or a NaN.
+pub fn try_iqr_outlier<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    signal: &[X],
+    iqr_factor: Y,
+) -> Result<(Vec<usize>, Vec<usize>)> {
+    check_len(signal.len(), 1)?;
+    check_no_nan(signal)?;
+    let mut outlier_lo = vec![];
+    let mut outlier_hi = vec![];
+    let mut sorted = signal.iter().map(|&x| x.into()).collect::<Vec<f64>>(); //signal.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let q1 = sorted[(sorted.len() as f64 * 0.25) as usize];
+    let q3 = sorted[(sorted.len() as f64 * 0.75) as usize];
+    let iqr = q3 - q1;
+    for (i, &item) in signal.iter().enumerate() {
+        if item.into() < q1 - iqr_factor.into() * iqr {
+            outlier_lo.push(i);
+        }
+        if item.into() > q3 + iqr_factor.into() * iqr {
+            outlier_hi.push(i);
+        }
+    }
+    Ok((outlier_lo, outlier_hi))
+}
+
+/// `iqr_outlier` under a `NanPolicy`.
+///
+/// With `Propagate` a NaN makes the quartiles NaN so no sample is flagged, with `Skip` the
+/// quartiles come from the non-NaN samples and NaN samples are never flagged. Indices always
+/// refer to `signal`.
+pub fn iqr_outlier_with_nan_policy<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    signal: &[X],
+    iqr_factor: Y,
+    policy: NanPolicy,
+) -> Result<(Vec<usize>, Vec<usize>)> {
+    with_nan_policy(signal, policy, |values| try_iqr_outlier(values, iqr_factor))
+}
+
+fn with_nan_policy<T, F>(
+    signal: &[T],
+    policy: NanPolicy,
+    detect: F,
+) -> Result<(Vec<usize>, Vec<usize>)>
+where
+    T: Into<f64> + Copy,
+    F: Fn(&[f64]) -> Result<(Vec<usize>, Vec<usize>)>,
+{
+    check_len(signal.len(), 1)?;
+    let (indices, values) = valid_samples(signal);
+    if indices.len() == signal.len() {
+        return detect(&values);
+    }
+    match policy {
+        NanPolicy::Propagate => Ok((vec![], vec![])),
+        NanPolicy::Skip => {
+            let (lower, upper) = detect(&values)?;
+            Ok((
+                lower.into_iter().map(|i| indices[i]).collect(),
+                upper.into_iter().map(|i| indices[i]).collect(),
+            ))
+        }
+        NanPolicy::Error => check_no_nan(signal).map(|_| (vec![], vec![])),
+    }
+}
+
+/// Detect the outliers from a signal. The signal should be normal distributed. So it's better to decompose the signal first.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::outlier::normal_outlier;
+/// let signal: Vec<i32> = vec![-100000000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 100000000];
+/// let (lower, upper) = normal_outlier(&signal, 3);
+/// assert_eq!(lower, [0]);
+/// assert_eq!(upper, [21]);
+/// ```
+pub fn normal_outlier<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    normal: &[X],
+    std_factor: Y,
+) -> (Vec<usize>, Vec<usize>) {
+    try_normal_outlier(normal, std_factor)
+        .unwrap_or_else(|e| panic!("Cannot detect outliers: {}", e))
+}
+
+/// Same as `normal_outlier`, but returns an error if there are less than 2 samples.
+pub fn try_normal_outlier<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    normal: &[X],
+    std_factor: Y,
+) -> Result<(Vec<usize>, Vec<usize>)> {
+    let mut outlier_lo = vec![];
+    let mut outlier_hi = vec![];
+    let mean = try_mean(normal)?;
+    let std = try_std(normal, false)?;
+    for (i, &item) in normal.iter().enumerate() {
+        if item.into() > mean + std_factor.into() * std {
+            outlier_hi.push(i);
+        } else if item.into() < mean - std_factor.into() * std {
+            outlier_lo.push(i);
+        }
+    }
+    Ok((outlier_lo, outlier_hi))
+}
+
+/// `normal_outlier` under a `NanPolicy`, see `iqr_outlier_with_nan_policy`.
+pub fn normal_outlier_with_nan_policy<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    normal: &[X],
+    std_factor: Y,
+    policy: NanPolicy,
+) -> Result<(Vec<usize>, Vec<usize>)> {
+    with_nan_policy(normal, policy, |values| try_normal_outlier(values, std_factor))
+}
+
+/// Outliers with the score of every sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outliers {
+    /// Indices of the outliers below the bulk of the samples
+    pub lower: Vec<usize>,
+    /// Indices of the outliers above the bulk of the samples
+    pub upper: Vec<usize>,
+    /// Deviation of every sample from the center in units of the scale, negative below it
+    pub scores: Vec<f64>,
+}
+
+impl Outliers {
+    fn from_scores(scores: Vec<f64>, is_outlier: impl Fn(usize, f64) -> bool) -> Self {
+        let mut lower = vec![];
+        let mut upper = vec![];
+        for (i, &score) in scores.iter().enumerate() {
+            if is_outlier(i, score) {
+                if score < 0.0 {
+                    lower.push(i);
+                } else {
+                    upper.push(i);
+                }
+            }
+        }
+        Outliers { lower, upper, scores }
+    }
+}
+
+/// `(x - center) / scale`, infinite for a zero scale unless `x` is the center.
+fn score(x: f64, center: f64, scale: f64) -> f64 {
+    if x == center {
+        0.0
+    } else {
+        (x - center) / scale
+    }
+}
+
+fn check_threshold(threshold: f64) -> Result<()> {
+    if threshold.is_nan() || threshold < 0.0 {
+        return Err(Error::InvalidParameter("threshold must not be negative".to_string()));
+    }
+    Ok(())
+}
+
+/// Median and median absolute deviation, for a non-empty slice without NaN.
+fn median_and_mad(values: &[f64]) -> (f64, f64) {
+    let median = try_median(values).unwrap();
+    let deviations = values.iter().map(|x| (x - median).abs()).collect::<Vec<f64>>();
+    (median, try_median(&deviations).unwrap())
+}
+
+/// Detect the outliers by the modified z-score `0.6745 (x - median) / MAD` of Iglewicz and
+/// Hoaglin. Unlike the standard deviation of `normal_outlier`, the median absolute deviation
+/// (MAD) is not inflated by the outliers themselves. A `threshold` of 3.5 is usual.
+///
+/// If more than half of the samples are equal the MAD is 0, and the mean absolute deviation
+/// scaled by 1.2533 replaces it.
+///
+/// Returns an error for an empty signal, a NaN or a negative `threshold`.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::outlier::mad_outlier;
+/// let signal = [10.0, 10.2, 9.9, 10.1, 9.8, 10.0, 35.0, 10.3, 9.7, -8.0];
+/// let outliers = mad_outlier(&signal, 3.5).unwrap();
+/// assert_eq!(outliers.lower, [9]);
+/// assert_eq!(outliers.upper, [6]);
+/// ```
+pub fn mad_outlier<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    signal: &[X],
+    threshold: Y,
+) -> Result<Outliers> {
+    check_len(signal.len(), 1)?;
+    check_no_nan(signal)?;
+    let threshold = threshold.into();
+    check_threshold(threshold)?;
+    let values = signal.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    let (median, mad) = median_and_mad(&values);
+    let scale = if mad > 0.0 {
+        mad / 0.6745
+    } else {
+        values.iter().map(|x| (x - median).abs()).sum::<f64>() / values.len() as f64 * 1.2533
+    };
+    let scores = values.iter().map(|&x| score(x, median, scale)).collect();
+    Ok(Outliers::from_scores(scores, |_, score| score.abs() > threshold))
+}
+
+/// Hampel filter: a sample is an outlier if it is more than `threshold` scaled MADs from the
+/// median of the window of `half_window` samples on both sides, which are fewer at the ends.
+/// The scaled MAD `1.4826 MAD` estimates the standard deviation of normal samples, so a
+/// `threshold` of 3 is usual.
+///
+/// Returns the outliers, scored against their windows, and the signal with the outliers
+/// replaced by the medians of their windows. Returns an error for an empty signal, a NaN, a
+/// `half_window` of 0 or a negative `threshold`.
+pub fn hampel_filter<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    signal: &[X],
+    half_window: usize,
+    threshold: Y,
+) -> Result<(Outliers, Vec<f64>)> {
+    check_len(signal.len(), 1)?;
+    check_no_nan(signal)?;
+    let threshold = threshold.into();
+    check_threshold(threshold)?;
+    if half_window == 0 {
+        return Err(Error::InvalidParameter("half_window must be positive".to_string()));
+    }
+    let values = signal.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    let mut medians = Vec::with_capacity(values.len());
+    let mut scores = Vec::with_capacity(values.len());
+    for (i, &x) in values.iter().enumerate() {
+        let window =
+            &values[i.saturating_sub(half_window)..(i + half_window + 1).min(values.len())];
+        let (median, mad) = median_and_mad(window);
+        medians.push(median);
+        scores.push(score(x, median, 1.4826 * mad));
+    }
+    let outliers = Outliers::from_scores(scores, |_, score| score.abs() > threshold);
+    let mut cleaned = values;
+    for &i in outliers.lower.iter().chain(&outliers.upper) {
+        cleaned[i] = medians[i];
+    }
+    Ok((outliers, cleaned))
+}
+
+/// Which deviations from the bulk of the samples are anomalies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Negative,
+    Positive,
+    Both,
+}
+
+/// Indices of the outliers found by the generalized ESD test, with the median and the scaled
+/// MAD in place of the mean and the standard deviation if `robust`.
+fn esd(
+    values: &[f64],
+    max_outliers: usize,
+    alpha: f64,
+    direction: Direction,
+    robust: bool,
+) -> Vec<usize> {
+    let mut remaining = (0..values.len()).collect::<Vec<usize>>();
+    let mut removed = vec![];
+    let mut count = 0;
+    for i in 1..=max_outliers.min(values.len().saturating_sub(2)) {
+        let samples = remaining.iter().map(|&j| values[j]).collect::<Vec<f64>>();
+        let (center, scale) = if robust {
+            let (median, mad) = median_and_mad(&samples);
+            (median, 1.4826 * mad)
+        } else {
+            (try_mean(&samples).unwrap(), try_std(&samples, false).unwrap())
+        };
+        if scale == 0.0 {
+            break;
+        }
+        let (position, deviation) = samples
+            .iter()
+            .map(|x| match direction {
+                Direction::Negative => center - x,
+                Direction::Positive => x - center,
+                Direction::Both => (x - center).abs(),
+            })
+            .enumerate()
+            .fold((0, f64::NEG_INFINITY), |best, (k, d)| if d > best.1 { (k, d) } else { best });
+        removed.push(remaining.remove(position));
+        let m = samples.len() as f64;
+        let tails = if direction == Direction::Both { 2.0 } else { 1.0 };
+        let t = student_t_quantile(1.0 - alpha / (tails * m), m - 2.0);
+        let critical = (m - 1.0) * t / ((m - 2.0 + t * t) * m).sqrt();
+        if deviation / scale > critical {
+            count = i;
+        }
+    }
+    removed.truncate(count);
+    removed
+}
+
+/// Rosner's generalized extreme studentized deviate (ESD) test for up to `max_outliers`
+/// outliers of normal samples at the significance level `alpha`.
+///
+/// The sample farthest from the mean is removed `max_outliers` times, and the outliers are the
+/// samples removed up to the last one whose studentized deviation exceeds its critical value.
+/// The scores are the z-scores against the mean and standard deviation of the other samples.
+///
+/// Returns an error for a NaN, `alpha` outside (0, 1) or less than `max_outliers + 2` samples.
+///
+/// Reference: [Rosner (1983)](https://doi.org/10.1080/00401706.1983.10487848)
+pub fn generalized_esd<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    signal: &[X],
+    max_outliers: usize,
+    alpha: Y,
+) -> Result<Outliers> {
+    check_len(signal.len(), max_outliers + 2)?;
+    check_no_nan(signal)?;
+    let alpha = alpha.into();
+    if !(alpha > 0.0 && alpha < 1.0) {
+        return Err(Error::InvalidParameter("alpha must be in (0, 1)".to_string()));
+    }
+    let values = signal.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    let removed = esd(&values, max_outliers, alpha, Direction::Both, false);
+    let inliers = (0..values.len()).filter(|i| !removed.contains(i)).map(|i| values[i]);
+    let inliers = inliers.collect::<Vec<f64>>();
+    let mean = try_mean(&inliers)?;
+    let std = try_std(&inliers, false)?;
+    let scores = values.iter().map(|&x| score(x, mean, std)).collect();
+    Ok(Outliers::from_scores(scores, |i, _| removed.contains(&i)))
+}
+
+/// Settings of `seasonal_hybrid_esd`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeasonalEsdOptions {
+    /// Largest share of anomalies in a piece, in (0, 0.5]
+    pub max_anomalies: f64,
+    /// Significance level of the ESD test
+    pub alpha: f64,
+    pub direction: Direction,
+    /// Length of the pieces a long signal is split into, at least two periods, or `None` to
+    /// take the whole signal at once
+    pub piece_length: Option<usize>,
+}
+
+impl Default for SeasonalEsdOptions {
+    fn default() -> Self {
+        SeasonalEsdOptions {
+            max_anomalies: 0.1,
+            alpha: 0.05,
+            direction: Direction::Both,
+            piece_length: None,
+        }
+    }
+}
+
+/// Seasonal hybrid ESD (S-H-ESD) anomaly detection of Twitter.
+///
+/// The seasonal component of a robust STL decomposition with `period` and the median of the
+/// signal are removed, and the generalized ESD test with the median and the MAD finds the
+/// anomalies among the residuals. The median follows the level of the signal less than the
+/// trend, so that anomalies are not absorbed by it. With `piece_length` every piece of a long
+/// signal is decomposed and tested on its own, so that a slowly moving level does not hide
+/// anomalies; the last piece is the last `piece_length` samples. The scores are the residuals
+/// in scaled MADs.
+///
+/// Returns an error for a NaN, a `period` below 2, a piece shorter than two periods or invalid
+/// options.
+///
+/// Reference: [Vallis et al. (2014)](https://www.usenix.org/system/files/conference/hotcloud14/hotcloud14-vallis.pdf)
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::outlier::{seasonal_hybrid_esd, SeasonalEsdOptions};
+/// let mut signal = (0..96).map(|i| [0.0, 5.0, 10.0, 5.0][i % 4] + (i % 7) as f64 * 0.1).collect::<Vec<f64>>();
+/// signal[50] += 6.0;
+/// let anomalies = seasonal_hybrid_esd(&signal, 4, &SeasonalEsdOptions::default()).unwrap();
+/// assert_eq!(anomalies.upper, [50]);
+/// ```
+pub fn seasonal_hybrid_esd<T: Into<f64> + Copy>(
+    signal: &[T],
+    period: usize,
+    options: &SeasonalEsdOptions,
+) -> Result<Outliers> {
+    check_no_nan(signal)?;
+    if !(options.max_anomalies > 0.0 && options.max_anomalies <= 0.5) {
+        return Err(Error::InvalidParameter("max_anomalies must be in (0, 0.5]".to_string()));
+    }
+    if !(options.alpha > 0.0 && options.alpha < 1.0) {
+        return Err(Error::InvalidParameter("alpha must be in (0, 1)".to_string()));
+    }
+    if period < 2 {
+        return Err(Error::InvalidParameter("period must be at least 2".to_string()));
+    }
+    if options.piece_length == Some(0) {
+        return Err(Error::InvalidParameter("piece_length must be positive".to_string()));
+    }
+    let values = signal.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    let piece_length = options.piece_length.unwrap_or(values.len()).min(values.len());
+    if piece_length < 2 * period {
+        return Err(Error::InvalidParameter(
+            "pieces must be at least two periods long".to_string(),
+        ));
+    }
+    let mut starts = (0..values.len() - piece_length).step_by(piece_length).collect::<Vec<_>>();
+    starts.push(values.len() - piece_length);
+
+    let mut scores = vec![0.0; values.len()];
+    let mut anomalies = vec![false; values.len()];
+    for start in starts {
+        let piece = &values[start..start + piece_length];
+        // a periodic seasonal component, as STL with a seasonal window of 10 n + 1 and degree 0
+        let stl_options = StlOptions {
+            seasonal_window: 10 * piece_length + 1,
+            seasonal_degree: 0,
+            ..StlOptions::robust()
+        };
+        let seasonal = stl(piece, period, &stl_options)?.seasonal;
+        let median = try_median(piece)?;
+        let residuals = piece.iter().zip(&seasonal).map(|(x, s)| x - s - median);
+        let residuals = residuals.collect::<Vec<f64>>();
+        let max_outliers = (piece_length as f64 * options.max_anomalies) as usize;
+        for i in esd(&residuals, max_outliers, options.alpha, options.direction, true) {
+            anomalies[start + i] = true;
+        }
+        let (center, mad) = median_and_mad(&residuals);
+        for (i, &r) in residuals.iter().enumerate() {
+            scores[start + i] = score(r, center, 1.4826 * mad);
+        }
+    }
+    Ok(Outliers::from_scores(scores, |i, _| anomalies[i]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_iqr_outlier() {
+        let signal = vec![
+            1.0, 20000.0, 20000.0, 20000.0, 20000.0, 20000.0, 20000.0, 20000.0, 20000.0, 20000.0,
+            20000.0, 1000000.,
+        ];
+        let (lower, upper) = iqr_outlier(&signal, 1.5);
+        assert_eq!(lower, [0]);
+        assert_eq!(upper, [11]);
+        let signal =
+            vec![1, 20000, 20000, 20000, 20000, 20000, 20000, 20000, 20000, 20000, 20000, 1000000];
+        let (lower, upper) = iqr_outlier(&signal, 3);
+        assert_eq!(lower, [0]);
+        assert_eq!(upper, [11]);
+    }
+
+    #[test]
+    fn test_try_outlier() {
+        let empty: Vec<f64> = vec![];
+        assert_eq!(
+            try_iqr_outlier(&empty, 1.5),
+            Err(Error::InsufficientData { required: 1, actual: 0 })
+        );
+        assert_eq!(try_iqr_outlier(&[1.0, f64::NAN], 1.5), Err(Error::NanValue { index: 1 }));
+        assert_eq!(
+            try_normal_outlier(&[1.0], 3.0),
+            Err(Error::InsufficientData { required: 2, actual: 1 })
+        );
+    }
+
+    #[test]
+    fn test_outlier_with_nan_policy() {
+        let mut signal = vec![0.0; 40];
+        signal[3] = f64::NAN;
+        signal[5] = -1e6;
+        signal[37] = 1e6;
+        for policy in [NanPolicy::Propagate, NanPolicy::Skip, NanPolicy::Error] {
+            let expected = match policy {
+                NanPolicy::Propagate => Ok((vec![], vec![])),
+                NanPolicy::Skip => Ok((vec![5], vec![37])),
+                NanPolicy::Error => Err(Error::NanValue { index: 3 }),
+            };
+            assert_eq!(iqr_outlier_with_nan_policy(&signal, 1.5, policy), expected);
+            assert_eq!(normal_outlier_with_nan_policy(&signal, 3.0, policy), expected);
+        }
+        assert_eq!(
+            normal_outlier_with_nan_policy(&[f64::NAN, 1.0], 3.0, NanPolicy::Skip),
+            Err(Error::InsufficientData { required: 2, actual: 1 })
+        );
+    }
+
+    #[test]
+    fn test_mad_outlier() {
+        let signal = [1.0, 2.0, 3.0, 4.0, 100.0];
+        let outliers = mad_outlier(&signal, 3.5).unwrap();
+        // median 3, MAD 1
+        assert_relative_eq!(outliers.scores[0], -2.0 * 0.6745);
+        assert_eq!((outliers.lower, outliers.upper), (vec![], vec![4]));
+        // the MAD is 0, the mean absolute deviation is 1.2
+        let outliers = mad_outlier(&[5, 5, 5, 5, 11], 3.5).unwrap();
+        assert_relative_eq!(outliers.scores[4], 6.0 / (1.2 * 1.2533), epsilon = 1e-12);
+        assert_eq!(outliers.upper, [4]);
+        assert_eq!(mad_outlier(&[2.0; 4], 3.5).unwrap().scores, vec![0.0; 4]);
+        assert!(matches!(mad_outlier(&signal, -1.0), Err(Error::InvalidParameter(_))));
+        assert_eq!(mad_outlier(&[1.0, f64::NAN], 3.5), Err(Error::NanValue { index: 1 }));
+    }
+
+    #[test]
+    fn test_hampel_filter() {
+        let signal = [1.0, 1.1, 0.9, 1.0, 9.0, 1.2, 0.8, 1.0, 1.1, -5.0];
+        let (outliers, cleaned) = hampel_filter(&signal, 3, 3.0).unwrap();
+        assert_eq!((outliers.lower.clone(), outliers.upper.clone()), (vec![9], vec![4]));
+        assert_eq!(cleaned[4], 1.0);
+        assert_relative_eq!(cleaned[9], 0.9); // the window is cut at the end
+        assert_eq!(&cleaned[..4], &signal[..4]);
+        // window 0.9, 1.0, 9.0, 1.2, 0.8 at index 4: median 1, MAD 0.2
+        let (outliers, _) = hampel_filter(&signal, 2, 3.0).unwrap();
+        assert_relative_eq!(outliers.scores[4], 8.0 / (1.4826 * 0.2), epsilon = 1e-9);
+        assert!(matches!(hampel_filter(&signal, 0, 3.0), Err(Error::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_generalized_esd() {
+        // Rosner's example: 54 samples, of which the three largest are outliers at 5%
+        let signal = [
+            -0.25, 0.68, 0.94, 1.15, 1.20, 1.26, 1.26, 1.34, 1.38, 1.43, 1.49, 1.49, 1.55, 1.56,
+            1.58, 1.65, 1.69, 1.70, 1.76, 1.77, 1.81, 1.91, 1.94, 1.96, 1.99, 2.06, 2.09, 2.10,
+            2.14, 2.15, 2.23, 2.24, 2.26, 2.35, 2.37, 2.40, 2.47, 2.54, 2.62, 2.64, 2.90, 2.92,
+            2.92, 2.93, 3.21, 3.26, 3.30, 3.59, 3.68, 4.30, 4.64, 5.34, 5.42, 6.01,
+        ];
+        let outliers = generalized_esd(&signal, 10, 0.05).unwrap();
+        assert_eq!((outliers.lower.clone(), outliers.upper.clone()), (vec![], vec![51, 52, 53]));
+        assert!(outliers.scores[53] > outliers.scores[50]);
+        assert!(generalized_esd(&signal, 10, 1e-9).unwrap().upper.is_empty());
+        assert_eq!(
+            generalized_esd(&[1.0, 2.0, 3.0], 2, 0.05),
+            Err(Error::InsufficientData { required: 4, actual: 3 })
+        );
+        assert!(matches!(generalized_esd(&signal, 1, 1.0), Err(Error::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_seasonal_hybrid_esd() {
+        let pattern = [0.0, 4.0, 10.0, 6.0, 2.0, 1.0];
+        let mut signal = (0..240)
+            .map(|i| pattern[i % 6] + 0.005 * i as f64 + ((i * 37) % 11) as f64 * 0.05)
+            .collect::<Vec<f64>>();
+        signal[40] += 5.0;
+        signal[125] -= 5.0;
+        signal[200] += 4.0;
+        let options = SeasonalEsdOptions::default();
+        let anomalies = seasonal_hybrid_esd(&signal, 6, &options).unwrap();
+        assert_eq!((anomalies.lower.clone(), anomalies.upper.clone()), (vec![125], vec![40, 200]));
+        assert!(anomalies.scores[40] > 10.0 && anomalies.scores[125] < -10.0);
+
+        let positive = SeasonalEsdOptions { direction: Direction::Positive, ..options };
+        let anomalies = seasonal_hybrid_esd(&signal, 6, &positive).unwrap();
+        assert_eq!((anomalies.lower, anomalies.upper), (vec![], vec![40, 200]));
+        let pieces = SeasonalEsdOptions { piece_length: Some(100), ..options };
+        let anomalies = seasonal_hybrid_esd(&signal, 6, &pieces).unwrap();
+        assert_eq!((anomalies.lower, anomalies.upper), (vec![125], vec![40, 200]));
+
+        // a steep trend spreads the residuals from the median unless the pieces are short
+        let steep = signal.iter().enumerate().map(|(i, x)| x + 0.05 * i as f64).collect::<Vec<_>>();
+        assert!(seasonal_hybrid_esd(&steep, 6, &options).unwrap().upper.is_empty());
+        let pieces = SeasonalEsdOptions { piece_length: Some(36), ..options };
+        let anomalies = seasonal_hybrid_esd(&steep, 6, &pieces).unwrap();
+        assert_eq!((anomalies.lower, anomalies.upper), (vec![125], vec![40, 200]));
+
+        let short = SeasonalEsdOptions { piece_length: Some(11), ..options };
+        assert!(matches!(seasonal_hybrid_esd(&signal, 6, &short), Err(Error::InvalidParameter(_))));
+        let many = SeasonalEsdOptions { max_anomalies: 0.6, ..options };
+        assert!(matches!(seasonal_hybrid_esd(&signal, 6, &many), Err(Error::InvalidParameter(_))));
+        let empty: [f64; 0] = [];
+        assert!(matches!(
+            seasonal_hybrid_esd(&empty, 0, &options),
+            Err(Error::InvalidParameter(_))
+        ));
+        let zero = SeasonalEsdOptions { piece_length: Some(0), ..options };
+        assert!(matches!(seasonal_hybrid_esd(&signal, 0, &zero), Err(Error::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_normal_outlier() {
+        let signal = vec![
+            -100000000000.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            100000000000.,
+        ];
+        let (lower, upper) = normal_outlier(&signal, 3.);
+        assert_eq!(lower, [0]);
+        assert_eq!(upper, [21]);
+        let signal: Vec<i32> =
+            vec![-100000000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 100000000];
+        let (lower, upper) = normal_outlier(&signal, 3);
+        assert_eq!(lower, [0]);
+        assert_eq!(upper, [21]);
+    }
+}
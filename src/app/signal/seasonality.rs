@@ -0,0 +1,180 @@
+//! Seasonality period detection
+//!
+//! Peaks of the periodogram propose candidate periods, which are kept only if the
+//! autocorrelation has a peak near them, following AUTOPERIOD by Vlachos et al.
+//!
+//! Reference: [AUTOPERIOD](https://doi.org/10.1137/1.9781611972757.40)
+
+use crate::app::signal::transform::rdft;
+use crate::error::{check_len, check_no_nan, Error, Result};
+use crate::opt::utils::local_max;
+use crate::stats::autocorrelation::acf;
+
+/// A seasonal period found by `detect_periods`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeriodCandidate {
+    /// Period in samples, at the autocorrelation peak
+    pub period: usize,
+    /// Frequency of the periodogram peak, in cycles per unit of `dt`
+    pub frequency: f64,
+    /// Share of the spectral power in the periodogram peak and its two neighbouring bins
+    pub power: f64,
+    /// Autocorrelation at `period`
+    pub acf: f64,
+    /// `power * acf`, in [0, 1]
+    pub confidence: f64,
+}
+
+/// Minimum share of the spectral power of a periodogram peak to be a candidate.
+const MIN_POWER: f64 = 0.01;
+
+/// Signal minus its least squares line.
+fn detrend(signal: &[f64]) -> Vec<f64> {
+    let n = signal.len() as f64;
+    let t_mean = (n - 1.0) / 2.0;
+    let y_mean = signal.iter().sum::<f64>() / n;
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for (i, y) in signal.iter().enumerate() {
+        sxy += (i as f64 - t_mean) * (y - y_mean);
+        sxx += (i as f64 - t_mean).powi(2);
+    }
+    let slope = sxy / sxx;
+    signal.iter().enumerate().map(|(i, y)| y - y_mean - slope * (i as f64 - t_mean)).collect()
+}
+
+/// Detect the seasonal periods of `signal` sampled every `dt`.
+///
+/// Returns the candidates ranked by confidence, empty if the signal has no variation. Periods
+/// are between 2 samples and half the signal length.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::seasonality::detect_periods;
+/// let signal = (0..200).map(|i| (i as f64 * std::f64::consts::PI / 5.0).sin()).collect::<Vec<_>>();
+/// let candidates = detect_periods(&signal, 1.0).unwrap();
+/// assert_eq!(candidates[0].period, 10);
+/// assert!(candidates[0].confidence > 0.9);
+/// ```
+pub fn detect_periods<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    signal: &[X],
+    dt: Y,
+) -> Result<Vec<PeriodCandidate>> {
+    check_len(signal.len(), 4)?;
+    check_no_nan(signal)?;
+    let dt = dt.into();
+    if dt.is_nan() || dt <= 0.0 {
+        return Err(Error::InvalidParameter("dt must be positive".to_string()));
+    }
+    let n = signal.len();
+    let signal = detrend(&signal.iter().map(|&x| x.into()).collect::<Vec<f64>>());
+    let periodogram = rdft(&signal).iter().map(|x| x.norm_sqr() / n as f64).collect::<Vec<f64>>();
+    let total = periodogram[1..].iter().sum::<f64>();
+    if total <= f64::EPSILON * signal.iter().map(|x| x * x).sum::<f64>().max(f64::MIN_POSITIVE) {
+        return Ok(vec![]);
+    }
+    let acf = acf(&signal, n / 2, true)?;
+    let acf_peaks = local_max(&acf, 0.0);
+
+    let mut candidates: Vec<PeriodCandidate> = vec![];
+    for k in local_max(&periodogram, 0.0) {
+        if k < 2 {
+            continue;
+        }
+        let power = periodogram[k - 1..(k + 2).min(periodogram.len())].iter().sum::<f64>() / total;
+        if power < MIN_POWER {
+            continue;
+        }
+        // the periods that fall into bin k of the periodogram
+        let shortest = n / (k + 1);
+        let longest = n.div_ceil(k - 1).min(n / 2);
+        let best = acf_peaks
+            .iter()
+            .copied()
+            .filter(|&lag| lag >= shortest.max(2) && lag <= longest)
+            .max_by(|&a, &b| acf[a].total_cmp(&acf[b]));
+        let Some(period) = best else {
+            continue;
+        };
+        if acf[period] <= 0.0 {
+            continue;
+        }
+        let candidate = PeriodCandidate {
+            period,
+            frequency: k as f64 / (n as f64 * dt),
+            power,
+            acf: acf[period],
+            confidence: (power * acf[period]).min(1.0),
+        };
+        match candidates.iter_mut().find(|c| c.period == period) {
+            Some(c) if c.confidence < candidate.confidence => *c = candidate,
+            Some(_) => {}
+            None => candidates.push(candidate),
+        }
+    }
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::signal::decompose::{stl, StlOptions};
+    use approx::assert_relative_eq;
+    use std::f64::consts::PI;
+
+    fn noise(i: usize) -> f64 {
+        ((i * 7919 + 13) % 101) as f64 / 101.0 - 0.5
+    }
+
+    #[test]
+    fn test_detect_periods() {
+        let signal = (0..365)
+            .map(|i| 50.0 + 0.1 * i as f64 + 5.0 * (2.0 * PI * i as f64 / 7.0).sin() + noise(i))
+            .collect::<Vec<f64>>();
+        let candidates = detect_periods(&signal, 86400.0).unwrap();
+        assert_eq!(candidates[0].period, 7);
+        assert_relative_eq!(candidates[0].frequency, 1.0 / (7.0 * 86400.0), epsilon = 1e-8);
+        assert!(candidates[0].confidence > 0.8);
+        let result = stl(&signal, candidates[0].period, &StlOptions::default()).unwrap();
+        assert!(result.residual.iter().all(|r| r.abs() < 1.0));
+
+        // hourly samples with a daily and a weekly season
+        let signal = (0..1008)
+            .map(|i| {
+                let t = i as f64;
+                3.0 * (2.0 * PI * t / 24.0).sin() + 2.0 * (2.0 * PI * t / 168.0).cos() + noise(i)
+            })
+            .collect::<Vec<f64>>();
+        let candidates = detect_periods(&signal, 1).unwrap();
+        let mut periods = candidates.iter().take(2).map(|c| c.period).collect::<Vec<usize>>();
+        periods.sort();
+        assert_eq!(periods, [24, 168]);
+        assert!(candidates.windows(2).all(|w| w[0].confidence >= w[1].confidence));
+    }
+
+    #[test]
+    fn test_detect_periods_nyquist() {
+        // frequencies near the Nyquist bin n / 2 stay positive, unlike the upper half of `dftfreq`
+        let signal =
+            (0..100).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 } + noise(i)).collect::<Vec<f64>>();
+        let candidates = detect_periods(&signal, 0.5).unwrap();
+        assert_eq!(candidates[0].period, 2);
+        assert!(candidates.iter().all(|c| c.frequency > 0.0 && c.frequency <= 1.0));
+    }
+
+    #[test]
+    fn test_detect_periods_without_season() {
+        assert_eq!(detect_periods(&[3.0; 50], 1.0), Ok(vec![]));
+        let line = (0..50).map(|i| i as f64).collect::<Vec<f64>>();
+        assert_eq!(detect_periods(&line, 1.0), Ok(vec![]));
+        let signal = (0..500).map(noise).collect::<Vec<f64>>();
+        let candidates = detect_periods(&signal, 1.0).unwrap();
+        assert!(candidates.iter().all(|c| c.confidence < 0.2));
+        assert!(matches!(detect_periods(&line, 0.0), Err(Error::InvalidParameter(_))));
+        assert_eq!(
+            detect_periods(&[1.0, 2.0, f64::NAN, 1.0], 1.0),
+            Err(Error::NanValue { index: 2 })
+        );
+    }
+}
@@ -1,85 +1,130 @@
+use crate::error::{check_len, Error, Result};
+use crate::na::fft::{FftPlan, RealFftPlan};
 use num::Complex;
 
-/// Discrete Fourier transform 
+/// Discrete Fourier transform
+///
+/// Computed by an O(n log n) FFT, see `crate::na::fft::FftPlan` to reuse a plan across calls.
 pub fn dft<T: Into<f64> + Copy>(signal: &[T]) -> Vec<Complex<f64>> {
-    let n = signal.len();
-    let mut dft: Vec<Complex<f64>> = Vec::with_capacity(n);
-    for k in 0..n {
-        let mut sum = Complex::new(0.0, 0.0);
-        //for t in 0..n {
-        //    let theta = -(k as f64) * (2.0 * std::f64::consts::PI) * (t as f64) / (n as f64);
-        //    let c = Complex::new(theta.cos(), theta.sin());
-        //    sum += signal[t] * c;
-        //}
-        for (t, &item) in signal.iter().enumerate().take(n) {
-            let theta = -(k as f64) * (2.0 * std::f64::consts::PI) * (t as f64) / (n as f64);
-            let c = Complex::new(theta.cos(), theta.sin());
-            sum += item.into() * c;
-        }
-        dft.push(sum);
-    }
+    let mut dft = signal.iter().map(|&x| Complex::new(x.into(), 0.0)).collect::<Vec<_>>();
+    FftPlan::new(signal.len()).forward(&mut dft);
     dft
 }
 
-/// Real discrete Fourier transform 
+/// Real discrete Fourier transform
+///
+/// Returns the `n / 2 + 1` non-negative frequency bins, computed by `crate::na::fft::RealFftPlan`.
 pub fn rdft<T: Into<f64> + Copy>(signal: &[T]) -> Vec<Complex<f64>> {
-    dft(signal)[..(signal.len() / 2) + 1].to_vec()
+    let signal = signal.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    RealFftPlan::new(signal.len()).forward(&signal)
 }
 
-/// Inverse discrete Fourier transform 
+/// Inverse discrete Fourier transform
+///
+/// Returns the real part of the normalized inverse FFT.
 pub fn idft<T: Into<f64> + Copy>(signal: &[Complex<T>]) -> Vec<f64> {
     let n = signal.len();
-    let mut idft: Vec<f64> = Vec::with_capacity(n);
-    for k in 0..n {
-        let mut sum = 0.0;
-        //for t in 0..n {
-        //    let theta = (k as f64) * (2.0 * std::f64::consts::PI) * (t as f64) / (n as f64);
-        //    let c = Complex::new(theta.cos(), theta.sin());
-        //    sum += signal[t].re * c.re - signal[t].im * c.im;
-        //}
-        for (t, &item) in signal.iter().enumerate().take(n) {
-            let theta = (k as f64) * (2.0 * std::f64::consts::PI) * (t as f64) / (n as f64);
-            let c = Complex::new(theta.cos(), theta.sin());
-            sum += item.re.into() * c.re - item.im.into() * c.im;
-        }
-        idft.push(sum / (n as f64));
-    }
-    idft
+    let mut idft =
+        signal.iter().map(|&x| Complex::new(x.re.into(), x.im.into())).collect::<Vec<_>>();
+    FftPlan::new(n).inverse(&mut idft);
+    idft.iter().map(|x| x.re / n as f64).collect()
 }
 
-/// Inverse real discrete Fourier transform 
+/// Inverse real discrete Fourier transform
+///
+/// The length of the original signal is even if the imaginary part of the last bin is zero,
+/// otherwise it is odd.
 pub fn irdft<T: Into<f64> + Copy>(rfourier: &[Complex<T>]) -> Vec<f64> {
+    try_irdft(rfourier).unwrap_or_else(|e| panic!("Cannot calculate irdft: {}", e))
+}
+
+/// Same as `irdft`, but returns an error for an empty spectrum.
+pub fn try_irdft<T: Into<f64> + Copy>(rfourier: &[Complex<T>]) -> Result<Vec<f64>> {
     let n = rfourier.len();
-    let mut fourier =
-        rfourier.iter().map(|&x| Complex::new(x.re.into(), x.im.into())).collect::<Vec<_>>(); //rfourier.to_vec();
-    let mut conjugate: Vec<Complex<f64>> =
-        rfourier[1..].iter().map(|&x| Complex::new(x.re.into(), x.im.into())).collect::<Vec<_>>(); //.to_vec();
-    if rfourier[n - 1].im.into().abs() < 1e-10 {
-        conjugate = rfourier[1..n - 1]
-            .iter()
-            .map(|&x| Complex::new(x.re.into(), x.im.into()))
-            .collect::<Vec<_>>(); //.to_vec();
-                                  //fourier.pop();
-    }
-    fourier.extend(conjugate.iter().map(|x| x.conj()).rev().collect::<Vec<_>>());
-    idft(&fourier)
+    check_len(n, 1)?;
+    let fourier =
+        rfourier.iter().map(|&x| Complex::new(x.re.into(), x.im.into())).collect::<Vec<_>>();
+    let len = if n > 1 && fourier[n - 1].im.abs() < 1e-10 { 2 * (n - 1) } else { 2 * n - 1 };
+    let irdft = RealFftPlan::new(len).inverse(&fourier);
+    Ok(irdft.iter().map(|x| x / len as f64).collect())
+}
+
+/// Dot products of `query` with every window of `series` of the same length, by a real FFT of
+/// the zero-padded inputs in O(n log n) instead of O(n m).
+///
+/// Returns an error for an empty `query` or a `query` longer than `series`.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::app::signal::transform::sliding_dot_product;
+/// let products = sliding_dot_product(&[1.0, 2.0], &[1.0, 0.0, 3.0, 1.0]).unwrap();
+/// assert!((products[1] - 6.0).abs() < 1e-12);
+/// assert_eq!(products.len(), 3);
+/// ```
+pub fn sliding_dot_product<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    query: &[X],
+    series: &[Y],
+) -> Result<Vec<f64>> {
+    check_len(query.len(), 1)?;
+    check_len(series.len(), query.len())?;
+    let (m, n) = (query.len(), series.len());
+    let len = (n + m - 1).next_power_of_two();
+    let plan = RealFftPlan::new(len);
+    let mut padded = vec![0.0; len];
+    padded.iter_mut().zip(query.iter().rev()).for_each(|(p, &q)| *p = q.into());
+    let query = plan.forward(&padded);
+    let mut padded = vec![0.0; len];
+    padded.iter_mut().zip(series).for_each(|(p, &x)| *p = x.into());
+    let series = plan.forward(&padded);
+    let product = query.iter().zip(&series).map(|(q, x)| q * x).collect::<Vec<_>>();
+    // the convolution with the reversed query at m - 1 + i is the dot product at i
+    let convolution = plan.inverse(&product);
+    Ok(convolution[m - 1..n].iter().map(|x| x / len as f64).collect())
 }
 
 /// Same as fftfreq from Python Numpy.
 pub fn dftfreq<T: Into<f64> + Copy>(n: usize, dt: T) -> Vec<f64> {
-    let val = 1.0 / (n as f64 * dt.into());
+    try_dftfreq(n, dt).unwrap_or_else(|e| panic!("Cannot calculate dftfreq: {}", e))
+}
+
+/// Same as `dftfreq`, but returns an error for `n == 0` or a NaN or non-positive `dt`.
+pub fn try_dftfreq<T: Into<f64> + Copy>(n: usize, dt: T) -> Result<Vec<f64>> {
+    check_len(n, 1)?;
+    let dt = dt.into();
+    if dt.is_nan() || dt <= 0.0 {
+        return Err(Error::InvalidParameter("dt must be positive".to_string()));
+    }
+    let val = 1.0 / (n as f64 * dt);
     let mut result: Vec<f64> = vec![];
     let mid = (n - 1) / 2 + 1;
     result.append(&mut (0..mid).map(|x| val * x as f64).collect::<Vec<f64>>());
     result.append(&mut (-(n as isize / 2)..0).map(|x| val * x as f64).collect::<Vec<f64>>());
-    result
+    Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Error;
     use approx::assert_relative_eq;
 
+    #[test]
+    fn test_sliding_dot_product() {
+        let query = [1.0, -2.0, 0.5];
+        let series = [3.0, 1.0, 4.0, 1.0, 5.0, 9.0, 2.0, 6.0];
+        let products = sliding_dot_product(&query, &series).unwrap();
+        assert_eq!(products.len(), 6);
+        for (i, p) in products.iter().enumerate() {
+            let expected = query.iter().zip(&series[i..]).map(|(q, x)| q * x).sum::<f64>();
+            assert_relative_eq!(*p, expected, epsilon = 1e-12);
+        }
+        assert_eq!(
+            sliding_dot_product(&query, &[1.0, 2.0]),
+            Err(Error::InsufficientData { required: 3, actual: 2 })
+        );
+    }
+
     #[test]
     fn test_dft() {
         let signal = vec![1.0, 2.0, 1.0, -1.0, 1.5];
@@ -178,6 +223,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_try_irdft() {
+        let empty: Vec<Complex<f64>> = vec![];
+        assert_eq!(try_irdft(&empty), Err(Error::InsufficientData { required: 1, actual: 0 }));
+        assert_eq!(try_irdft(&[Complex::new(2.0, 0.0)]), Ok(vec![2.0]));
+        assert_eq!(try_dftfreq(0, 1.0), Err(Error::InsufficientData { required: 1, actual: 0 }));
+        assert!(matches!(try_dftfreq(4, 0.0), Err(Error::InvalidParameter(_))));
+        assert!(matches!(try_dftfreq(4, f64::NAN), Err(Error::InvalidParameter(_))));
+    }
+
     #[test]
     fn test_dftfreq() {
         let signal_len = 8;
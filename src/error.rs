@@ -0,0 +1,97 @@
+//! Error type of the crate
+//!
+//! Functions that predate `Error` keep their panicking signature and have a `try_` counterpart
+//! that returns `Result` instead, e.g. `mean` and `try_mean`.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The input has fewer samples than the operation needs
+    InsufficientData { required: usize, actual: usize },
+    /// Inputs that are paired element by element have different lengths
+    LengthMismatch { left: usize, right: usize },
+    /// The input is constant where variation is required, e.g. for a correlation
+    ConstantSeries,
+    /// A NaN was found where values must be ordered or finite
+    NanValue { index: usize },
+    /// A window does not fit in the series
+    WindowTooLarge { window: usize, len: usize },
+    /// A parameter is out of its valid range
+    InvalidParameter(String),
+    /// A value lies outside of the range `[start, end]` it must be in
+    OutOfRange { value: f64, start: f64, end: f64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InsufficientData { required, actual } => {
+                write!(f, "At least {} samples are required, got {}", required, actual)
+            }
+            Error::LengthMismatch { left, right } => {
+                write!(f, "Series must have the same length, got {} and {}", left, right)
+            }
+            Error::ConstantSeries => write!(f, "Series must not be constant"),
+            Error::NanValue { index } => write!(f, "Unexpected NaN at index {}", index),
+            Error::WindowTooLarge { window, len } => {
+                write!(f, "Window size {} must not be larger than signal length {}", window, len)
+            }
+            Error::InvalidParameter(message) => write!(f, "Invalid parameter: {}", message),
+            Error::OutOfRange { value, start, end } => {
+                write!(f, "{} is out of the range [{}, {}]", value, start, end)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub(crate) fn check_len(actual: usize, required: usize) -> Result<()> {
+    if actual < required {
+        return Err(Error::InsufficientData { required, actual });
+    }
+    Ok(())
+}
+
+pub(crate) fn check_same_len(left: usize, right: usize) -> Result<()> {
+    if left != right {
+        return Err(Error::LengthMismatch { left, right });
+    }
+    Ok(())
+}
+
+pub(crate) fn check_no_nan<T: Into<f64> + Copy>(series: &[T]) -> Result<()> {
+    match series.iter().position(|&x| x.into().is_nan()) {
+        Some(index) => Err(Error::NanValue { index }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checks() {
+        assert_eq!(check_len(3, 2), Ok(()));
+        assert_eq!(check_len(1, 2), Err(Error::InsufficientData { required: 2, actual: 1 }));
+        assert_eq!(check_same_len(2, 2), Ok(()));
+        assert_eq!(check_same_len(2, 3), Err(Error::LengthMismatch { left: 2, right: 3 }));
+        assert_eq!(check_no_nan(&[1.0, 2.0]), Ok(()));
+        assert_eq!(check_no_nan(&[1.0, f64::NAN]), Err(Error::NanValue { index: 1 }));
+        assert_eq!(check_no_nan(&[1, 2]), Ok(()));
+    }
+
+    #[test]
+    fn test_display() {
+        let e = Error::WindowTooLarge { window: 5, len: 3 };
+        assert_eq!(e.to_string(), "Window size 5 must not be larger than signal length 3");
+        let e = Error::InvalidParameter("dt must be positive".to_string());
+        assert_eq!(e.to_string(), "Invalid parameter: dt must be positive");
+        let e = Error::OutOfRange { value: 3.0, start: 0.0, end: 2.5 };
+        assert_eq!(e.to_string(), "3 is out of the range [0, 2.5]");
+    }
+}
@@ -1,10 +1,12 @@
-//! 
+//!
 //! `Mathbox` is a collection of math tools for Rust. `na` stands for Numric Analisys; `opt` is Optimization; `stats` is Statistics. There will be more modules in the future.
 //!
 //! `app` is a collection of tools for different filed such as time series signal processing, based on `na`, `opt`, `stats` and other basic modules.
 
-
 pub mod app;
+mod error;
 pub mod na;
 pub mod opt;
 pub mod stats;
+
+pub use error::{Error, Result};
@@ -0,0 +1,1014 @@
+//! Initial value problems of ordinary differential equations
+//!
+//! Solve `y' = f(t, y)` for a vector state `y` with `y(t_span.0) = y0` up to `t_span.1`.
+//! `euler` and `rk4` use a fixed step, `rk45` is the adaptive Dormand-Prince pair and
+//! `rosenbrock23` is an adaptive linearly implicit method for stiff systems. `t_span.1` may be
+//! smaller than `t_span.0` to integrate backwards.
+//!
+//! Every solver accepts `Event`s: functions `g(t, y)` whose zero crossings are located on the
+//! interpolant of each accepted step, optionally stopping the integration.
+
+use crate::error::{Error, Result};
+use std::cell::Cell;
+
+// Dormand-Prince 5(4) tableau
+const DP_C: [f64; 6] = [0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0];
+const DP_A: [[f64; 5]; 6] = [
+    [0.0, 0.0, 0.0, 0.0, 0.0],
+    [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0],
+    [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0],
+    [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0],
+    [19372.0 / 6561.0, -25360.0 / 2187.0, 64448.0 / 6561.0, -212.0 / 729.0, 0.0],
+    [9017.0 / 3168.0, -355.0 / 33.0, 46732.0 / 5247.0, 49.0 / 176.0, -5103.0 / 18656.0],
+];
+const DP_B: [f64; 6] =
+    [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0];
+// difference between the 5th and the embedded 4th order weights, the last stage is f(t + h, y_new)
+const DP_E: [f64; 7] = [
+    -71.0 / 57600.0,
+    0.0,
+    71.0 / 16695.0,
+    -71.0 / 1920.0,
+    17253.0 / 339200.0,
+    -22.0 / 525.0,
+    1.0 / 40.0,
+];
+// coefficients of the 4th order continuous extension, y(t0 + xh) = y0 + h * sum_i k_i * P_i(x)
+const DP_P: [[f64; 4]; 7] = [
+    [
+        1.0,
+        -8048581381.0 / 2820520608.0,
+        8663915743.0 / 2820520608.0,
+        -12715105075.0 / 11282082432.0,
+    ],
+    [0.0, 0.0, 0.0, 0.0],
+    [
+        0.0,
+        131558114200.0 / 32700410799.0,
+        -68118460800.0 / 10900136933.0,
+        87487479700.0 / 32700410799.0,
+    ],
+    [0.0, -1754552775.0 / 470086768.0, 14199869525.0 / 1410260304.0, -10690763975.0 / 1880347072.0],
+    [
+        0.0,
+        127303824393.0 / 49829197408.0,
+        -318862633887.0 / 49829197408.0,
+        701980252875.0 / 199316789632.0,
+    ],
+    [0.0, -282668133.0 / 205662961.0, 2019193451.0 / 616988883.0, -1453857185.0 / 822651844.0],
+    [0.0, 40617522.0 / 29380423.0, -110615467.0 / 29380423.0, 69997945.0 / 29380423.0],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventDirection {
+    /// `g` goes from negative to positive
+    Rising,
+    /// `g` goes from positive to negative
+    Falling,
+    Both,
+}
+
+type EventFunction = Box<dyn Fn(f64, &[f64]) -> f64>;
+
+/// A zero crossing of `g(t, y)` to be located during the integration.
+pub struct Event {
+    function: EventFunction,
+    direction: EventDirection,
+    terminal: bool,
+}
+
+impl Event {
+    /// Non-terminal event triggered by crossings of `function` in both directions.
+    pub fn new<G: Fn(f64, &[f64]) -> f64 + 'static>(function: G) -> Self {
+        Event { function: Box::new(function), direction: EventDirection::Both, terminal: false }
+    }
+
+    pub fn with_direction(mut self, direction: EventDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// A terminal event stops the integration at the first crossing.
+    pub fn with_terminal(mut self, terminal: bool) -> Self {
+        self.terminal = terminal;
+        self
+    }
+}
+
+/// Location of an event crossing, `event` is the index in the events slice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventHit {
+    pub event: usize,
+    pub t: f64,
+    pub y: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OdeStatus {
+    /// Reached the end of `t_span`
+    Success,
+    /// Stopped by a terminal event
+    Terminated,
+    /// The step size needed for the tolerances fell below the floating point resolution of `t`
+    StepSizeTooSmall,
+    MaxStepsReached,
+}
+
+/// Settings of the adaptive solvers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OdeOptions {
+    pub rtol: f64,
+    pub atol: f64,
+    /// Initial step size, estimated from `f` when `None`
+    pub first_step: Option<f64>,
+    pub max_step: f64,
+    pub max_steps: usize,
+    /// Keep the interpolant of every step so the solution can be evaluated at any `t`
+    pub dense_output: bool,
+}
+
+impl Default for OdeOptions {
+    fn default() -> Self {
+        OdeOptions {
+            rtol: 1e-6,
+            atol: 1e-9,
+            first_step: None,
+            max_step: f64::INFINITY,
+            max_steps: 100_000,
+            dense_output: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Interpolant {
+    /// Cubic Hermite from the derivatives at both ends
+    Hermite { f0: Vec<f64>, f1: Vec<f64> },
+    /// Dormand-Prince continuous extension, one row of polynomial coefficients per component
+    DormandPrince { q: Vec<[f64; 4]> },
+}
+
+#[derive(Debug, Clone)]
+struct DenseSegment {
+    t0: f64,
+    h: f64,
+    y0: Vec<f64>,
+    y1: Vec<f64>,
+    interpolant: Interpolant,
+}
+
+impl DenseSegment {
+    fn evaluate(&self, t: f64) -> Vec<f64> {
+        let x = (t - self.t0) / self.h;
+        match &self.interpolant {
+            Interpolant::Hermite { f0, f1 } => {
+                let h00 = 2.0 * x.powi(3) - 3.0 * x * x + 1.0;
+                let h10 = x.powi(3) - 2.0 * x * x + x;
+                let h01 = -2.0 * x.powi(3) + 3.0 * x * x;
+                let h11 = x.powi(3) - x * x;
+                (0..self.y0.len())
+                    .map(|i| {
+                        h00 * self.y0[i]
+                            + h10 * self.h * f0[i]
+                            + h01 * self.y1[i]
+                            + h11 * self.h * f1[i]
+                    })
+                    .collect()
+            }
+            Interpolant::DormandPrince { q } => self
+                .y0
+                .iter()
+                .zip(q.iter())
+                .map(|(y, c)| y + self.h * x * (c[0] + x * (c[1] + x * (c[2] + x * c[3]))))
+                .collect(),
+        }
+    }
+}
+
+/// Output of the solvers: the state at every accepted step plus located events.
+#[derive(Debug, Clone)]
+pub struct OdeSolution {
+    pub t: Vec<f64>,
+    pub y: Vec<Vec<f64>>,
+    pub events: Vec<EventHit>,
+    pub status: OdeStatus,
+    /// Number of evaluations of `f`
+    pub evaluations: usize,
+    dense: Vec<DenseSegment>,
+}
+
+impl OdeSolution {
+    /// Evaluate the dense output at `t`.
+    ///
+    /// Returns `None` if `t` is outside the integrated interval or the solver did not keep the
+    /// dense output.
+    pub fn evaluate(&self, t: f64) -> Option<Vec<f64>> {
+        let first = self.dense.first()?;
+        let direction = first.h.signum();
+        let t_end = self.t[self.t.len() - 1];
+        if (t - self.t[0]) * direction < 0.0 || (t - t_end) * direction > 0.0 {
+            return None;
+        }
+        let idx = self.dense.partition_point(|s| (s.t0 - t) * direction <= 0.0);
+        Some(self.dense[idx.max(1) - 1].evaluate(t))
+    }
+}
+
+/// Collects accepted steps and checks them for events.
+struct Recorder<'a> {
+    events: &'a [Event],
+    g_old: Vec<f64>,
+    keep_dense: bool,
+    solution: OdeSolution,
+}
+
+impl<'a> Recorder<'a> {
+    fn new(events: &'a [Event], t0: f64, y0: &[f64], keep_dense: bool) -> Self {
+        Recorder {
+            events,
+            g_old: events.iter().map(|e| (e.function)(t0, y0)).collect(),
+            keep_dense,
+            solution: OdeSolution {
+                t: vec![t0],
+                y: vec![y0.to_vec()],
+                events: vec![],
+                status: OdeStatus::Success,
+                evaluations: 0,
+                dense: vec![],
+            },
+        }
+    }
+
+    /// Record an accepted step, returns true if a terminal event stopped the integration.
+    fn push(&mut self, segment: DenseSegment) -> bool {
+        let t1 = segment.t0 + segment.h;
+        let mut hits = vec![];
+        for (i, event) in self.events.iter().enumerate() {
+            let g1 = (event.function)(t1, &segment.y1);
+            let g0 = self.g_old[i];
+            let crossed = match event.direction {
+                EventDirection::Rising => g0 < 0.0 && g1 >= 0.0,
+                EventDirection::Falling => g0 > 0.0 && g1 <= 0.0,
+                EventDirection::Both => (g0 < 0.0 && g1 >= 0.0) || (g0 > 0.0 && g1 <= 0.0),
+            };
+            if crossed {
+                let t = locate_root(&*event.function, &segment, g0);
+                hits.push(EventHit { event: i, t, y: segment.evaluate(t) });
+            }
+            self.g_old[i] = g1;
+        }
+        let direction = segment.h.signum();
+        hits.sort_by(|a, b| (a.t * direction).total_cmp(&(b.t * direction)));
+        let stop = hits.iter().position(|hit| self.events[hit.event].terminal);
+        if let Some(stop) = stop {
+            hits.truncate(stop + 1);
+            let hit = hits[stop].clone();
+            self.solution.t.push(hit.t);
+            self.solution.y.push(hit.y);
+            self.solution.status = OdeStatus::Terminated;
+        } else {
+            self.solution.t.push(t1);
+            self.solution.y.push(segment.y1.clone());
+        }
+        self.solution.events.extend(hits);
+        if self.keep_dense {
+            self.solution.dense.push(segment);
+        }
+        stop.is_some()
+    }
+
+    fn finish(mut self, status: Option<OdeStatus>, evaluations: usize) -> OdeSolution {
+        if let Some(status) = status {
+            self.solution.status = status;
+        }
+        self.solution.evaluations = evaluations;
+        self.solution
+    }
+}
+
+/// Bisection of `g` along the interpolant of `segment`, `g0` is the value at the step start.
+fn locate_root(g: &dyn Fn(f64, &[f64]) -> f64, segment: &DenseSegment, g0: f64) -> f64 {
+    let mut lo = segment.t0;
+    let mut hi = segment.t0 + segment.h;
+    let mut g_lo = g0;
+    for _ in 0..100 {
+        if (hi - lo).abs() <= 4.0 * f64::EPSILON * hi.abs().max(1.0) {
+            break;
+        }
+        let mid = (lo + hi) / 2.0;
+        let g_mid = g(mid, &segment.evaluate(mid));
+        if (g_mid > 0.0) == (g_lo > 0.0) && g_mid != 0.0 {
+            lo = mid;
+            g_lo = g_mid;
+        } else {
+            hi = mid;
+        }
+    }
+    hi
+}
+
+fn rms_norm(x: &[f64], scale: &[f64]) -> f64 {
+    if x.is_empty() {
+        return 0.0;
+    }
+    (x.iter().zip(scale.iter()).map(|(v, s)| (v / s).powi(2)).sum::<f64>() / x.len() as f64).sqrt()
+}
+
+fn axpy(y: &[f64], a: f64, x: &[f64]) -> Vec<f64> {
+    y.iter().zip(x.iter()).map(|(yi, xi)| yi + a * xi).collect()
+}
+
+fn check_span(t_span: (f64, f64)) -> Result<()> {
+    if !t_span.0.is_finite() || !t_span.1.is_finite() {
+        return Err(Error::InvalidParameter("t_span must be finite".to_string()));
+    }
+    Ok(())
+}
+
+fn check_step(dt: f64) -> Result<()> {
+    if !(dt > 0.0 && dt.is_finite()) {
+        return Err(Error::InvalidParameter("dt must be positive".to_string()));
+    }
+    Ok(())
+}
+
+fn check_options(options: &OdeOptions) -> Result<()> {
+    let tolerances = options.rtol > 0.0 && options.atol >= 0.0;
+    if !tolerances {
+        return Err(Error::InvalidParameter(
+            "rtol must be positive and atol non-negative".to_string(),
+        ));
+    }
+    let steps = options.max_step > 0.0 && options.first_step.is_none_or(|h| h > 0.0);
+    if !steps {
+        return Err(Error::InvalidParameter("step sizes must be positive".to_string()));
+    }
+    Ok(())
+}
+
+/// Shared driver of the fixed step solvers, `step` advances `(t, y, f(t, y))` by `h`.
+fn fixed_step<F, S>(
+    f: &F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    dt: f64,
+    events: &[Event],
+    step: S,
+) -> OdeSolution
+where
+    F: Fn(f64, &[f64]) -> Vec<f64>,
+    S: Fn(&dyn Fn(f64, &[f64]) -> Vec<f64>, f64, &[f64], &[f64], f64) -> Vec<f64>,
+{
+    let counter = Cell::new(0);
+    let f = |t: f64, y: &[f64]| {
+        counter.set(counter.get() + 1);
+        f(t, y)
+    };
+    let (t0, t_end) = t_span;
+    let direction = if t_end >= t0 { 1.0 } else { -1.0 };
+    let n_steps = ((t_end - t0).abs() / dt - 1e-9).ceil().max(0.0) as usize;
+    let mut recorder = Recorder::new(events, t0, y0, false);
+    let mut t = t0;
+    let mut y = y0.to_vec();
+    let mut fy = f(t, &y);
+    for i in 0..n_steps {
+        let t_new = if i + 1 == n_steps { t_end } else { t0 + direction * dt * (i + 1) as f64 };
+        let h = t_new - t;
+        let y_new = step(&f, t, &y, &fy, h);
+        let f_new = f(t_new, &y_new);
+        let segment = DenseSegment {
+            t0: t,
+            h,
+            y0: y,
+            y1: y_new.clone(),
+            interpolant: Interpolant::Hermite { f0: fy, f1: f_new.clone() },
+        };
+        if recorder.push(segment) {
+            break;
+        }
+        t = t_new;
+        y = y_new;
+        fy = f_new;
+    }
+    recorder.finish(None, counter.get())
+}
+
+/// Explicit Euler method with fixed step `dt`.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::na::differential_eq::euler;
+/// let solution = euler(|_t: f64, _y: &[f64]| vec![1.0], (0.0, 1.0), &[0.0], 0.1, &[]);
+/// assert_eq!(solution.t.len(), 11);
+/// assert!((solution.y[10][0] - 1.0).abs() < 1e-12);
+/// ```
+pub fn euler<F: Fn(f64, &[f64]) -> Vec<f64>>(
+    f: F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    dt: f64,
+    events: &[Event],
+) -> OdeSolution {
+    try_euler(f, t_span, y0, dt, events).unwrap_or_else(|e| panic!("Cannot solve: {}", e))
+}
+
+/// Same as `euler`, but returns an error for a non-positive `dt` or an infinite `t_span`.
+pub fn try_euler<F: Fn(f64, &[f64]) -> Vec<f64>>(
+    f: F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    dt: f64,
+    events: &[Event],
+) -> Result<OdeSolution> {
+    check_span(t_span)?;
+    check_step(dt)?;
+    Ok(fixed_step(&f, t_span, y0, dt, events, |_f, _t, y, fy, h| axpy(y, h, fy)))
+}
+
+/// Classical 4th order Runge-Kutta method with fixed step `dt`.
+pub fn rk4<F: Fn(f64, &[f64]) -> Vec<f64>>(
+    f: F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    dt: f64,
+    events: &[Event],
+) -> OdeSolution {
+    try_rk4(f, t_span, y0, dt, events).unwrap_or_else(|e| panic!("Cannot solve: {}", e))
+}
+
+/// Same as `rk4`, but returns an error for a non-positive `dt` or an infinite `t_span`.
+pub fn try_rk4<F: Fn(f64, &[f64]) -> Vec<f64>>(
+    f: F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    dt: f64,
+    events: &[Event],
+) -> Result<OdeSolution> {
+    check_span(t_span)?;
+    check_step(dt)?;
+    Ok(fixed_step(&f, t_span, y0, dt, events, |f, t, y, k1, h| {
+        let k2 = f(t + h / 2.0, &axpy(y, h / 2.0, k1));
+        let k3 = f(t + h / 2.0, &axpy(y, h / 2.0, &k2));
+        let k4 = f(t + h, &axpy(y, h, &k3));
+        (0..y.len()).map(|i| y[i] + h / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i])).collect()
+    }))
+}
+
+/// Initial step size estimate from Hairer, Norsett & Wanner, for a method of `order`.
+#[allow(clippy::too_many_arguments)]
+fn initial_step(
+    f: &dyn Fn(f64, &[f64]) -> Vec<f64>,
+    t0: f64,
+    y0: &[f64],
+    f0: &[f64],
+    direction: f64,
+    order: i32,
+    span: f64,
+    options: &OdeOptions,
+) -> f64 {
+    if let Some(h) = options.first_step {
+        return h.abs().min(span);
+    }
+    let scale = y0.iter().map(|y| options.atol + y.abs() * options.rtol).collect::<Vec<_>>();
+    let d0 = rms_norm(y0, &scale);
+    let d1 = rms_norm(f0, &scale);
+    let h0 = if d0 < 1e-5 || d1 < 1e-5 { 1e-6 } else { 0.01 * d0 / d1 };
+    let y1 = axpy(y0, h0 * direction, f0);
+    let f1 = f(t0 + h0 * direction, &y1);
+    let diff = f1.iter().zip(f0.iter()).map(|(a, b)| a - b).collect::<Vec<_>>();
+    let d2 = rms_norm(&diff, &scale) / h0;
+    let h1 = if d1 <= 1e-15 && d2 <= 1e-15 {
+        (h0 * 1e-3).max(1e-6)
+    } else {
+        (0.01 / d1.max(d2)).powf(1.0 / (order + 1) as f64)
+    };
+    (100.0 * h0).min(h1).min(span).min(options.max_step)
+}
+
+fn error_scale(y: &[f64], y_new: &[f64], options: &OdeOptions) -> Vec<f64> {
+    y.iter()
+        .zip(y_new.iter())
+        .map(|(a, b)| options.atol + a.abs().max(b.abs()) * options.rtol)
+        .collect()
+}
+
+/// Smallest step that still changes `t`.
+fn min_step(t: f64) -> f64 {
+    10.0 * f64::EPSILON * t.abs().max(f64::MIN_POSITIVE)
+}
+
+/// Adaptive Dormand-Prince 5(4) method.
+///
+/// The local error of each step is kept below `atol + rtol * |y|` in the RMS norm. With
+/// `options.dense_output` the 4th order continuous extension of each step is kept and the
+/// solution can be evaluated anywhere with `OdeSolution::evaluate`.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::na::differential_eq::{rk45, OdeOptions, OdeStatus};
+/// let solution = rk45(|_t, y: &[f64]| vec![-y[0]], (0.0, 2.0), &[1.0], &OdeOptions::default(), &[]);
+/// assert_eq!(solution.status, OdeStatus::Success);
+/// assert!((solution.y[solution.y.len() - 1][0] - (-2f64).exp()).abs() < 1e-6);
+/// ```
+pub fn rk45<F: Fn(f64, &[f64]) -> Vec<f64>>(
+    f: F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    options: &OdeOptions,
+    events: &[Event],
+) -> OdeSolution {
+    try_rk45(f, t_span, y0, options, events).unwrap_or_else(|e| panic!("Cannot solve: {}", e))
+}
+
+/// Same as `rk45`, but returns an error for invalid tolerances or step sizes in `options` or
+/// an infinite `t_span`.
+pub fn try_rk45<F: Fn(f64, &[f64]) -> Vec<f64>>(
+    f: F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    options: &OdeOptions,
+    events: &[Event],
+) -> Result<OdeSolution> {
+    check_span(t_span)?;
+    check_options(options)?;
+    Ok(dormand_prince(f, t_span, y0, options, events))
+}
+
+fn dormand_prince<F: Fn(f64, &[f64]) -> Vec<f64>>(
+    f: F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    options: &OdeOptions,
+    events: &[Event],
+) -> OdeSolution {
+    let counter = Cell::new(0);
+    let f = |t: f64, y: &[f64]| {
+        counter.set(counter.get() + 1);
+        f(t, y)
+    };
+    let (t0, t_end) = t_span;
+    let direction = if t_end >= t0 { 1.0 } else { -1.0 };
+    let n = y0.len();
+    let mut recorder = Recorder::new(events, t0, y0, options.dense_output);
+    let mut t = t0;
+    let mut y = y0.to_vec();
+    let mut fy = f(t, &y);
+    let mut h_abs = initial_step(&f, t0, y0, &fy, direction, 4, (t_end - t0).abs(), options);
+    let mut steps = 0;
+    while (t_end - t) * direction > 0.0 {
+        if steps >= options.max_steps {
+            return recorder.finish(Some(OdeStatus::MaxStepsReached), counter.get());
+        }
+        let mut rejected = false;
+        loop {
+            h_abs = h_abs.min(options.max_step);
+            if h_abs < min_step(t) {
+                return recorder.finish(Some(OdeStatus::StepSizeTooSmall), counter.get());
+            }
+            let mut t_new = t + direction * h_abs;
+            if (t_new - t_end) * direction > 0.0 {
+                t_new = t_end;
+            }
+            let h = t_new - t;
+            let mut k = vec![fy.clone()];
+            for s in 1..6 {
+                let mut y_stage = y.clone();
+                for (j, kj) in k.iter().enumerate() {
+                    for i in 0..n {
+                        y_stage[i] += h * DP_A[s][j] * kj[i];
+                    }
+                }
+                k.push(f(t + DP_C[s] * h, &y_stage));
+            }
+            let y_new = (0..n)
+                .map(|i| y[i] + h * (0..6).map(|s| DP_B[s] * k[s][i]).sum::<f64>())
+                .collect::<Vec<_>>();
+            let f_new = f(t_new, &y_new);
+            k.push(f_new.clone());
+            let err = (0..n)
+                .map(|i| h * (0..7).map(|s| DP_E[s] * k[s][i]).sum::<f64>())
+                .collect::<Vec<_>>();
+            let err_norm = rms_norm(&err, &error_scale(&y, &y_new, options));
+            if err_norm < 1.0 {
+                let mut factor =
+                    if err_norm == 0.0 { 10.0 } else { (0.9 * err_norm.powf(-0.2)).min(10.0) };
+                if rejected {
+                    factor = factor.min(1.0);
+                }
+                h_abs = h.abs() * factor;
+                let q = (0..n)
+                    .map(|i| {
+                        let mut row = [0.0; 4];
+                        for (j, item) in row.iter_mut().enumerate() {
+                            *item = (0..7).map(|s| k[s][i] * DP_P[s][j]).sum::<f64>();
+                        }
+                        row
+                    })
+                    .collect::<Vec<_>>();
+                let segment = DenseSegment {
+                    t0: t,
+                    h,
+                    y0: y,
+                    y1: y_new.clone(),
+                    interpolant: Interpolant::DormandPrince { q },
+                };
+                if recorder.push(segment) {
+                    return recorder.finish(None, counter.get());
+                }
+                t = t_new;
+                y = y_new;
+                fy = f_new;
+                break;
+            }
+            h_abs = h.abs() * (0.9 * err_norm.powf(-0.2)).max(0.2);
+            rejected = true;
+        }
+        steps += 1;
+    }
+    recorder.finish(None, counter.get())
+}
+
+/// LU decomposition with partial pivoting in place, returns the row permutation or `None` if
+/// the matrix is singular or has a non-finite pivot.
+fn lu_decompose(a: &mut [Vec<f64>]) -> Option<Vec<usize>> {
+    let n = a.len();
+    let mut perm = (0..n).collect::<Vec<_>>();
+    for k in 0..n {
+        let pivot = (k..n).max_by(|&i, &j| a[i][k].abs().total_cmp(&a[j][k].abs()))?;
+        if a[pivot][k].abs() < f64::MIN_POSITIVE || !a[pivot][k].is_finite() {
+            return None;
+        }
+        a.swap(k, pivot);
+        perm.swap(k, pivot);
+        let (upper, lower) = a.split_at_mut(k + 1);
+        let pivot_row = &upper[k];
+        for row in lower.iter_mut() {
+            let factor = row[k] / pivot_row[k];
+            row[k] = factor;
+            for (x, p) in row[k + 1..].iter_mut().zip(pivot_row[k + 1..].iter()) {
+                *x -= factor * p;
+            }
+        }
+    }
+    Some(perm)
+}
+
+fn lu_solve(lu: &[Vec<f64>], perm: &[usize], b: &[f64]) -> Vec<f64> {
+    let n = lu.len();
+    let mut x = perm.iter().map(|&p| b[p]).collect::<Vec<_>>();
+    for i in 0..n {
+        for j in 0..i {
+            x[i] -= lu[i][j] * x[j];
+        }
+    }
+    for i in (0..n).rev() {
+        for j in i + 1..n {
+            x[i] -= lu[i][j] * x[j];
+        }
+        x[i] /= lu[i][i];
+    }
+    x
+}
+
+/// Forward difference approximation of `df/dy` and `df/dt` at `(t, y)`.
+fn jacobian(
+    f: &dyn Fn(f64, &[f64]) -> Vec<f64>,
+    t: f64,
+    y: &[f64],
+    fy: &[f64],
+) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let n = y.len();
+    let sqrt_eps = f64::EPSILON.sqrt();
+    let mut jac = vec![vec![0.0; n]; n];
+    let mut y_shift = y.to_vec();
+    for j in 0..n {
+        let delta = sqrt_eps * y[j].abs().max(1e-5);
+        y_shift[j] = y[j] + delta;
+        let f_shift = f(t, &y_shift);
+        for i in 0..n {
+            jac[i][j] = (f_shift[i] - fy[i]) / delta;
+        }
+        y_shift[j] = y[j];
+    }
+    let delta_t = sqrt_eps * t.abs().max(1.0);
+    let dfdt = f(t + delta_t, y).iter().zip(fy.iter()).map(|(a, b)| (a - b) / delta_t).collect();
+    (jac, dfdt)
+}
+
+/// Adaptive Rosenbrock method of order 2(3) for stiff systems, as in Matlab's `ode23s`.
+///
+/// Each step solves linear systems with `I - h·d·J` instead of iterating on `f`, where the
+/// Jacobian `J` is approximated by finite differences, so stiff components do not restrict
+/// the step size.
+///
+/// Reference: Shampine & Reichelt, [The MATLAB ODE Suite](https://doi.org/10.1137/S1064827594276424)
+pub fn rosenbrock23<F: Fn(f64, &[f64]) -> Vec<f64>>(
+    f: F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    options: &OdeOptions,
+    events: &[Event],
+) -> OdeSolution {
+    try_rosenbrock23(f, t_span, y0, options, events)
+        .unwrap_or_else(|e| panic!("Cannot solve: {}", e))
+}
+
+/// Same as `rosenbrock23`, but returns an error for invalid tolerances or step sizes in
+/// `options` or an infinite `t_span`.
+pub fn try_rosenbrock23<F: Fn(f64, &[f64]) -> Vec<f64>>(
+    f: F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    options: &OdeOptions,
+    events: &[Event],
+) -> Result<OdeSolution> {
+    check_span(t_span)?;
+    check_options(options)?;
+    Ok(rosenbrock(f, t_span, y0, options, events))
+}
+
+fn rosenbrock<F: Fn(f64, &[f64]) -> Vec<f64>>(
+    f: F,
+    t_span: (f64, f64),
+    y0: &[f64],
+    options: &OdeOptions,
+    events: &[Event],
+) -> OdeSolution {
+    let counter = Cell::new(0);
+    let f = |t: f64, y: &[f64]| {
+        counter.set(counter.get() + 1);
+        f(t, y)
+    };
+    let d = 1.0 / (2.0 + 2f64.sqrt());
+    let e32 = 6.0 + 2f64.sqrt();
+    let (t0, t_end) = t_span;
+    let direction = if t_end >= t0 { 1.0 } else { -1.0 };
+    let n = y0.len();
+    let mut recorder = Recorder::new(events, t0, y0, options.dense_output);
+    let mut t = t0;
+    let mut y = y0.to_vec();
+    let mut fy = f(t, &y);
+    let mut h_abs = initial_step(&f, t0, y0, &fy, direction, 2, (t_end - t0).abs(), options);
+    let mut steps = 0;
+    while (t_end - t) * direction > 0.0 {
+        if steps >= options.max_steps {
+            return recorder.finish(Some(OdeStatus::MaxStepsReached), counter.get());
+        }
+        let (jac, dfdt) = jacobian(&f, t, &y, &fy);
+        let mut rejected = false;
+        loop {
+            h_abs = h_abs.min(options.max_step);
+            if h_abs < min_step(t) {
+                return recorder.finish(Some(OdeStatus::StepSizeTooSmall), counter.get());
+            }
+            let mut t_new = t + direction * h_abs;
+            if (t_new - t_end) * direction > 0.0 {
+                t_new = t_end;
+            }
+            let h = t_new - t;
+            let mut w = (0..n)
+                .map(|i| {
+                    (0..n)
+                        .map(|j| if i == j { 1.0 } else { 0.0 } - h * d * jac[i][j])
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let perm = match lu_decompose(&mut w) {
+                Some(perm) => perm,
+                None => {
+                    h_abs = h.abs() / 2.0;
+                    rejected = true;
+                    continue;
+                }
+            };
+            let rhs = (0..n).map(|i| fy[i] + h * d * dfdt[i]).collect::<Vec<_>>();
+            let k1 = lu_solve(&w, &perm, &rhs);
+            let f1 = f(t + h / 2.0, &axpy(&y, h / 2.0, &k1));
+            let rhs = (0..n).map(|i| f1[i] - k1[i]).collect::<Vec<_>>();
+            let k2 = axpy(&lu_solve(&w, &perm, &rhs), 1.0, &k1);
+            let y_new = axpy(&y, h, &k2);
+            let f_new = f(t_new, &y_new);
+            let rhs = (0..n)
+                .map(|i| f_new[i] - e32 * (k2[i] - f1[i]) - 2.0 * (k1[i] - fy[i]) + h * d * dfdt[i])
+                .collect::<Vec<_>>();
+            let k3 = lu_solve(&w, &perm, &rhs);
+            let err = (0..n).map(|i| h / 6.0 * (k1[i] - 2.0 * k2[i] + k3[i])).collect::<Vec<_>>();
+            let err_norm = rms_norm(&err, &error_scale(&y, &y_new, options));
+            if err_norm.is_finite() && err_norm < 1.0 {
+                let mut factor =
+                    if err_norm == 0.0 { 5.0 } else { (0.8 * err_norm.powf(-1.0 / 3.0)).min(5.0) };
+                if rejected {
+                    factor = factor.min(1.0);
+                }
+                h_abs = h.abs() * factor;
+                let segment = DenseSegment {
+                    t0: t,
+                    h,
+                    y0: y,
+                    y1: y_new.clone(),
+                    interpolant: Interpolant::Hermite { f0: fy, f1: f_new.clone() },
+                };
+                if recorder.push(segment) {
+                    return recorder.finish(None, counter.get());
+                }
+                t = t_new;
+                y = y_new;
+                fy = f_new;
+                break;
+            }
+            let factor =
+                if err_norm.is_finite() { (0.8 * err_norm.powf(-1.0 / 3.0)).max(0.2) } else { 0.2 };
+            h_abs = h.abs() * factor;
+            rejected = true;
+        }
+        steps += 1;
+    }
+    recorder.finish(None, counter.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn decay(_t: f64, y: &[f64]) -> Vec<f64> {
+        vec![-y[0]]
+    }
+
+    fn oscillator(_t: f64, y: &[f64]) -> Vec<f64> {
+        vec![y[1], -y[0]]
+    }
+
+    #[test]
+    fn test_euler() {
+        let solution = euler(decay, (0.0, 1.0), &[1.0], 0.001, &[]);
+        assert_eq!(solution.status, OdeStatus::Success);
+        assert_eq!(solution.t.len(), 1001);
+        assert_relative_eq!(solution.t[1000], 1.0);
+        assert_relative_eq!(solution.y[1000][0], (-1f64).exp(), epsilon = 1e-3);
+        // the last step is shortened to land on the end of the span
+        let solution = euler(decay, (0.0, 1.05), &[1.0], 0.1, &[]);
+        assert_eq!(solution.t.len(), 12);
+        assert_relative_eq!(solution.t[11], 1.05);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_euler_panic() {
+        euler(decay, (0.0, 1.0), &[1.0], 0.0, &[]);
+    }
+
+    #[test]
+    fn test_try_solvers() {
+        assert!(matches!(
+            try_euler(decay, (0.0, 1.0), &[1.0], -0.1, &[]),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            try_rk4(decay, (0.0, f64::INFINITY), &[1.0], 0.1, &[]),
+            Err(Error::InvalidParameter(_))
+        ));
+        let options = OdeOptions { rtol: 0.0, ..OdeOptions::default() };
+        assert!(matches!(
+            try_rk45(decay, (0.0, 1.0), &[1.0], &options, &[]),
+            Err(Error::InvalidParameter(_))
+        ));
+        let options = OdeOptions { first_step: Some(-1.0), ..OdeOptions::default() };
+        assert!(matches!(
+            try_rosenbrock23(decay, (0.0, 1.0), &[1.0], &options, &[]),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(try_rk45(decay, (0.0, 1.0), &[1.0], &OdeOptions::default(), &[]).is_ok());
+    }
+
+    #[test]
+    fn test_rk4() {
+        let solution = rk4(oscillator, (0.0, 10.0), &[1.0, 0.0], 0.01, &[]);
+        let last = &solution.y[solution.y.len() - 1];
+        assert_relative_eq!(last[0], 10f64.cos(), epsilon = 1e-8);
+        assert_relative_eq!(last[1], -(10f64.sin()), epsilon = 1e-8);
+        assert_eq!(solution.evaluations, 1 + 1000 * 4);
+        // backwards in time
+        let solution = rk4(decay, (1.0, 0.0), &[(-1f64).exp()], 0.01, &[]);
+        assert_relative_eq!(solution.y[solution.y.len() - 1][0], 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_rk45() {
+        let options = OdeOptions { rtol: 1e-9, atol: 1e-12, ..OdeOptions::default() };
+        let solution = rk45(oscillator, (0.0, 20.0), &[1.0, 0.0], &options, &[]);
+        assert_eq!(solution.status, OdeStatus::Success);
+        let last = &solution.y[solution.y.len() - 1];
+        assert_relative_eq!(last[0], 20f64.cos(), epsilon = 1e-7);
+        assert_relative_eq!(last[1], -(20f64.sin()), epsilon = 1e-7);
+        assert!(solution.t.len() < 1000);
+        assert!(solution.evaluate(1.0).is_none());
+    }
+
+    #[test]
+    fn test_rk45_dense_output() {
+        let options =
+            OdeOptions { rtol: 1e-8, atol: 1e-10, dense_output: true, ..OdeOptions::default() };
+        let solution = rk45(oscillator, (0.0, 10.0), &[1.0, 0.0], &options, &[]);
+        for i in 0..=100 {
+            let t = i as f64 * 0.1;
+            let y = solution.evaluate(t).unwrap();
+            assert_relative_eq!(y[0], t.cos(), epsilon = 1e-6);
+            assert_relative_eq!(y[1], -t.sin(), epsilon = 1e-6);
+        }
+        assert!(solution.evaluate(10.5).is_none());
+        assert!(solution.evaluate(-0.5).is_none());
+    }
+
+    #[test]
+    fn test_rk45_max_steps() {
+        let options = OdeOptions { max_steps: 5, ..OdeOptions::default() };
+        let solution = rk45(oscillator, (0.0, 100.0), &[1.0, 0.0], &options, &[]);
+        assert_eq!(solution.status, OdeStatus::MaxStepsReached);
+        assert_eq!(solution.t.len(), 6);
+    }
+
+    #[test]
+    fn test_rosenbrock23_stiff() {
+        // y' = -1000 (y - cos t) quickly relaxes onto the slow solution ~ cos t
+        let f = |t: f64, y: &[f64]| vec![-1000.0 * (y[0] - t.cos())];
+        let options = OdeOptions { rtol: 1e-3, atol: 1e-6, ..OdeOptions::default() };
+        let solution = rosenbrock23(f, (0.0, 10.0), &[0.0], &options, &[]);
+        assert_eq!(solution.status, OdeStatus::Success);
+        let exact = |t: f64| {
+            let a = 1000.0;
+            (a * a * t.cos() + a * t.sin()) / (a * a + 1.0) - a * a / (a * a + 1.0) * (-a * t).exp()
+        };
+        for (t, y) in solution.t.iter().zip(solution.y.iter()) {
+            assert_relative_eq!(y[0], exact(*t), epsilon = 1e-2);
+        }
+        // an explicit method needs far more steps for the same problem
+        let explicit = rk45(f, (0.0, 10.0), &[0.0], &options, &[]);
+        assert!(solution.t.len() * 5 < explicit.t.len());
+        // a NaN Jacobian makes every step fail instead of panicking
+        let f = |t: f64, y: &[f64]| vec![f64::NAN + t + y[0], -y[1]];
+        let solution = try_rosenbrock23(f, (0.0, 1.0), &[1.0, 1.0], &options, &[]).unwrap();
+        assert_eq!(solution.status, OdeStatus::StepSizeTooSmall);
+    }
+
+    #[test]
+    fn test_rosenbrock23_system() {
+        let options =
+            OdeOptions { rtol: 1e-8, atol: 1e-10, dense_output: true, ..OdeOptions::default() };
+        let solution = rosenbrock23(oscillator, (0.0, 5.0), &[1.0, 0.0], &options, &[]);
+        let last = &solution.y[solution.y.len() - 1];
+        assert_relative_eq!(last[0], 5f64.cos(), epsilon = 1e-5);
+        assert_relative_eq!(last[1], -(5f64.sin()), epsilon = 1e-5);
+        assert_relative_eq!(solution.evaluate(2.5).unwrap()[0], 2.5f64.cos(), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_terminal_event() {
+        // falling ball from 10 m
+        let f = |_t: f64, y: &[f64]| vec![y[1], -9.81];
+        let ground = || {
+            Event::new(|_t, y: &[f64]| y[0])
+                .with_direction(EventDirection::Falling)
+                .with_terminal(true)
+        };
+        let expected = (2.0 * 10.0 / 9.81f64).sqrt();
+        let solution = rk45(f, (0.0, 10.0), &[10.0, 0.0], &OdeOptions::default(), &[ground()]);
+        assert_eq!(solution.status, OdeStatus::Terminated);
+        assert_eq!(solution.events.len(), 1);
+        assert_relative_eq!(solution.events[0].t, expected, epsilon = 1e-9);
+        assert_relative_eq!(solution.t[solution.t.len() - 1], expected, epsilon = 1e-9);
+        assert_relative_eq!(solution.y[solution.y.len() - 1][0], 0.0, epsilon = 1e-9);
+        let solution = rk4(f, (0.0, 10.0), &[10.0, 0.0], 0.1, &[ground()]);
+        assert_eq!(solution.status, OdeStatus::Terminated);
+        assert_relative_eq!(solution.events[0].t, expected, epsilon = 1e-9);
+        let solution =
+            rosenbrock23(f, (0.0, 10.0), &[10.0, 0.0], &OdeOptions::default(), &[ground()]);
+        assert_relative_eq!(solution.events[0].t, expected, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_non_terminal_events() {
+        let events = [
+            Event::new(|_t, y: &[f64]| y[0]),
+            Event::new(|_t, y: &[f64]| y[0]).with_direction(EventDirection::Rising),
+        ];
+        let solution = rk45(oscillator, (0.0, 10.0), &[1.0, 0.0], &OdeOptions::default(), &events);
+        assert_eq!(solution.status, OdeStatus::Success);
+        // cos t crosses zero at pi/2, 3pi/2, 5pi/2 and rises at 3pi/2
+        let both = solution.events.iter().filter(|e| e.event == 0).map(|e| e.t).collect::<Vec<_>>();
+        let rising =
+            solution.events.iter().filter(|e| e.event == 1).map(|e| e.t).collect::<Vec<_>>();
+        assert_eq!(both.len(), 3);
+        assert_eq!(rising.len(), 1);
+        for (i, t) in both.iter().enumerate() {
+            assert_relative_eq!(
+                *t,
+                (2 * i + 1) as f64 * std::f64::consts::FRAC_PI_2,
+                epsilon = 1e-6
+            );
+        }
+        assert_relative_eq!(rising[0], 3.0 * std::f64::consts::FRAC_PI_2, epsilon = 1e-6);
+    }
+}
@@ -1,14 +1,34 @@
+use crate::error::{check_len, Error, Result};
+
 pub fn diff<T: Into<f64> + Copy>(signal: &[T]) -> Vec<f64> {
+    try_diff(signal).unwrap_or_else(|e| panic!("Cannot calculate diff: {}", e))
+}
+
+/// Same as `diff`, but returns an error for an empty signal.
+pub fn try_diff<T: Into<f64> + Copy>(signal: &[T]) -> Result<Vec<f64>> {
+    check_len(signal.len(), 1)?;
     let mut result = vec![0.0; signal.len() - 1];
     for i in 0..signal.len() - 1 {
         result[i] = signal[i + 1].into() - signal[i].into();
     }
-    result
+    Ok(result)
+}
+
+/// Differences `signal[i] - signal[i - lag]`, e.g. seasonal differences with `lag` the period.
+///
+/// Returns an error if `lag` is 0 or the signal has no more than `lag` samples.
+pub fn lagged_diff<T: Into<f64> + Copy>(signal: &[T], lag: usize) -> Result<Vec<f64>> {
+    if lag == 0 {
+        return Err(Error::InvalidParameter("lag must be positive".to_string()));
+    }
+    check_len(signal.len(), lag + 1)?;
+    Ok((lag..signal.len()).map(|i| signal[i].into() - signal[i - lag].into()).collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Error;
     use approx::assert_relative_eq;
 
     #[test]
@@ -26,4 +46,22 @@ mod tests {
             assert_relative_eq!(result[i], expected[i]);
         }
     }
+
+    #[test]
+    fn test_try_diff() {
+        let signal: Vec<f64> = vec![];
+        assert_eq!(try_diff(&signal), Err(Error::InsufficientData { required: 1, actual: 0 }));
+        assert_eq!(try_diff(&[1.0]), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_lagged_diff() {
+        assert_eq!(lagged_diff(&[1, 2, 4, 7, 11], 1), Ok(vec![1.0, 2.0, 3.0, 4.0]));
+        assert_eq!(lagged_diff(&[1.0, 2.0, 4.0, 7.0, 11.0], 3), Ok(vec![6.0, 9.0]));
+        assert_eq!(
+            lagged_diff(&[1.0, 2.0], 2),
+            Err(Error::InsufficientData { required: 3, actual: 2 })
+        );
+        assert!(matches!(lagged_diff(&[1.0, 2.0], 0), Err(Error::InvalidParameter(_))));
+    }
 }
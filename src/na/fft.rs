@@ -0,0 +1,442 @@
+//! Fast Fourier transform
+//!
+//! An `FftPlan` precomputes everything that only depends on the transform length, so it can be
+//! built once and reused for many signals of that length. Power-of-two lengths use an iterative
+//! radix-2 kernel, lengths whose prime factors are all small use a mixed-radix Cooley-Tukey
+//! kernel and every other length falls back to Bluestein's chirp-z algorithm, so all lengths
+//! run in O(n log n).
+//!
+//! `RealFftPlan` transforms real input and only returns the non-negative frequencies, like
+//! `rfft` from Python Numpy.
+
+use num::Complex;
+use std::f64::consts::PI;
+
+/// Largest prime factor handled by the mixed-radix kernel, longer primes go through Bluestein.
+const MAX_MIXED_RADIX_FACTOR: usize = 31;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FftDirection {
+    Forward,
+    Inverse,
+}
+
+#[derive(Debug, Clone)]
+enum Algorithm {
+    Identity,
+    Radix2 { twiddles: Vec<Complex<f64>>, bit_reverse: Vec<usize> },
+    MixedRadix { factors: Vec<usize>, twiddles: Vec<Complex<f64>> },
+    Bluestein { chirp: Vec<Complex<f64>>, kernel: Vec<Complex<f64>>, inner: Box<FftPlan> },
+}
+
+/// Precomputed complex FFT of a fixed length.
+///
+/// Both directions are unnormalized: `inverse(forward(x))` equals `x * len`.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::na::fft::FftPlan;
+/// use num::Complex;
+/// let plan = FftPlan::new(4);
+/// let mut buffer = vec![Complex::new(1.0, 0.0); 4];
+/// plan.forward(&mut buffer);
+/// assert_eq!(buffer[0], Complex::new(4.0, 0.0));
+/// assert_eq!(buffer[1], Complex::new(0.0, 0.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FftPlan {
+    len: usize,
+    algorithm: Algorithm,
+}
+
+fn twiddle(k: usize, n: usize) -> Complex<f64> {
+    Complex::from_polar(1.0, -2.0 * PI * k as f64 / n as f64)
+}
+
+fn prime_factors(mut n: usize) -> Vec<usize> {
+    let mut factors = vec![];
+    let mut p = 2;
+    while p * p <= n {
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+        p += 1;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+impl FftPlan {
+    pub fn new(len: usize) -> Self {
+        let algorithm = if len <= 1 {
+            Algorithm::Identity
+        } else if len.is_power_of_two() {
+            let bits = len.trailing_zeros();
+            let bit_reverse =
+                (0..len).map(|i| i.reverse_bits() >> (usize::BITS - bits)).collect::<Vec<_>>();
+            let twiddles = (0..len / 2).map(|k| twiddle(k, len)).collect::<Vec<_>>();
+            Algorithm::Radix2 { twiddles, bit_reverse }
+        } else {
+            let factors = prime_factors(len);
+            if factors.iter().all(|&p| p <= MAX_MIXED_RADIX_FACTOR) {
+                let twiddles = (0..len).map(|k| twiddle(k, len)).collect::<Vec<_>>();
+                Algorithm::MixedRadix { factors, twiddles }
+            } else {
+                Self::bluestein(len)
+            }
+        };
+        FftPlan { len, algorithm }
+    }
+
+    fn bluestein(len: usize) -> Algorithm {
+        let inner_len = (2 * len - 1).next_power_of_two();
+        let inner = FftPlan::new(inner_len);
+        // k^2 is reduced modulo 2 * len before scaling so the phase stays accurate for long inputs
+        let chirp = (0..len)
+            .map(|k| {
+                let k2 = (k as u128 * k as u128) % (2 * len as u128);
+                Complex::from_polar(1.0, -PI * k2 as f64 / len as f64)
+            })
+            .collect::<Vec<_>>();
+        let mut kernel = vec![Complex::new(0.0, 0.0); inner_len];
+        kernel[0] = chirp[0].conj();
+        for k in 1..len {
+            kernel[k] = chirp[k].conj();
+            kernel[inner_len - k] = chirp[k].conj();
+        }
+        inner.forward(&mut kernel);
+        Algorithm::Bluestein { chirp, kernel, inner: Box::new(inner) }
+    }
+
+    /// Length of the transform.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Transform `buffer` in place.
+    pub fn process(&self, buffer: &mut [Complex<f64>], direction: FftDirection) {
+        match direction {
+            FftDirection::Forward => self.forward(buffer),
+            FftDirection::Inverse => self.inverse(buffer),
+        }
+    }
+
+    /// Forward transform in place, with the `exp(-2πi·jk/n)` kernel.
+    ///
+    /// # Panics
+    ///
+    /// If the buffer length is not the plan length.
+    pub fn forward(&self, buffer: &mut [Complex<f64>]) {
+        if buffer.len() != self.len {
+            panic!("Buffer length must be equal to the plan length");
+        }
+        match &self.algorithm {
+            Algorithm::Identity => {}
+            Algorithm::Radix2 { twiddles, bit_reverse } => radix2(buffer, twiddles, bit_reverse),
+            Algorithm::MixedRadix { factors, twiddles } => {
+                let input = buffer.to_vec();
+                let mut scratch = vec![Complex::new(0.0, 0.0); factors[factors.len() - 1]];
+                mixed_radix(buffer, &input, 0, 1, factors, twiddles, &mut scratch);
+            }
+            Algorithm::Bluestein { chirp, kernel, inner } => {
+                let inner_len = kernel.len();
+                let mut work = vec![Complex::new(0.0, 0.0); inner_len];
+                for (w, (x, c)) in work.iter_mut().zip(buffer.iter().zip(chirp.iter())) {
+                    *w = x * c;
+                }
+                inner.forward(&mut work);
+                for (w, k) in work.iter_mut().zip(kernel.iter()) {
+                    *w *= k;
+                }
+                inner.inverse(&mut work);
+                let scale = 1.0 / inner_len as f64;
+                for (x, (w, c)) in buffer.iter_mut().zip(work.iter().zip(chirp.iter())) {
+                    *x = w * c * scale;
+                }
+            }
+        }
+    }
+
+    /// Unnormalized inverse transform in place, with the `exp(2πi·jk/n)` kernel.
+    pub fn inverse(&self, buffer: &mut [Complex<f64>]) {
+        buffer.iter_mut().for_each(|x| *x = x.conj());
+        self.forward(buffer);
+        buffer.iter_mut().for_each(|x| *x = x.conj());
+    }
+}
+
+fn radix2(buffer: &mut [Complex<f64>], twiddles: &[Complex<f64>], bit_reverse: &[usize]) {
+    let n = buffer.len();
+    for (i, &j) in bit_reverse.iter().enumerate() {
+        if i < j {
+            buffer.swap(i, j);
+        }
+    }
+    let mut size = 2;
+    while size <= n {
+        let half = size / 2;
+        let step = n / size;
+        for start in (0..n).step_by(size) {
+            for k in 0..half {
+                let t = twiddles[k * step] * buffer[start + k + half];
+                let u = buffer[start + k];
+                buffer[start + k] = u + t;
+                buffer[start + k + half] = u - t;
+            }
+        }
+        size *= 2;
+    }
+}
+
+/// Recursive decimation-in-time step: `out` receives the DFT of
+/// `input[offset], input[offset + stride], ...` of length `out.len()`.
+fn mixed_radix(
+    out: &mut [Complex<f64>],
+    input: &[Complex<f64>],
+    offset: usize,
+    stride: usize,
+    factors: &[usize],
+    twiddles: &[Complex<f64>],
+    scratch: &mut [Complex<f64>],
+) {
+    let p = factors[0];
+    let m = out.len() / p;
+    if m == 1 {
+        for (q, item) in out.iter_mut().enumerate() {
+            *item = input[offset + q * stride];
+        }
+    } else {
+        for q in 0..p {
+            mixed_radix(
+                &mut out[q * m..(q + 1) * m],
+                input,
+                offset + q * stride,
+                stride * p,
+                &factors[1..],
+                twiddles,
+                scratch,
+            );
+        }
+    }
+    // combine the p sub-transforms of length m, twiddles of this stage are W_N^(stride·q·k)
+    let n_total = twiddles.len();
+    for u in 0..m {
+        for q in 0..p {
+            scratch[q] = out[u + q * m];
+        }
+        for q1 in 0..p {
+            let k = u + q1 * m;
+            let mut acc = scratch[0];
+            for (q, item) in scratch.iter().enumerate().take(p).skip(1) {
+                acc += item * twiddles[(stride * q * k) % n_total];
+            }
+            out[k] = acc;
+        }
+    }
+}
+
+/// Precomputed FFT of real input of a fixed length.
+///
+/// `forward` returns the `len / 2 + 1` non-negative frequency bins, `inverse` takes them back to
+/// `len` real samples. As with `FftPlan`, `inverse(forward(x))` equals `x * len`.
+#[derive(Debug, Clone)]
+pub struct RealFftPlan {
+    len: usize,
+    inner: FftPlan,
+    twiddles: Vec<Complex<f64>>,
+}
+
+impl RealFftPlan {
+    pub fn new(len: usize) -> Self {
+        if len >= 2 && len.is_multiple_of(2) {
+            // even lengths pack pairs of samples into one complex value of a half length FFT
+            let half = len / 2;
+            let twiddles = (0..=half).map(|k| twiddle(k, len)).collect::<Vec<_>>();
+            RealFftPlan { len, inner: FftPlan::new(half), twiddles }
+        } else {
+            RealFftPlan { len, inner: FftPlan::new(len), twiddles: vec![] }
+        }
+    }
+
+    /// Length of the real signal.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of frequency bins, `len / 2 + 1`.
+    pub fn spectrum_len(&self) -> usize {
+        if self.len == 0 {
+            0
+        } else {
+            self.len / 2 + 1
+        }
+    }
+
+    pub fn forward(&self, signal: &[f64]) -> Vec<Complex<f64>> {
+        if signal.len() != self.len {
+            panic!("Signal length must be equal to the plan length");
+        }
+        if self.twiddles.is_empty() {
+            let mut buffer = signal.iter().map(|&x| Complex::new(x, 0.0)).collect::<Vec<_>>();
+            self.inner.forward(&mut buffer);
+            buffer.truncate(self.spectrum_len());
+            return buffer;
+        }
+        let half = self.len / 2;
+        let mut packed = signal.chunks(2).map(|x| Complex::new(x[0], x[1])).collect::<Vec<_>>();
+        self.inner.forward(&mut packed);
+        let i = Complex::new(0.0, 1.0);
+        (0..=half)
+            .map(|k| {
+                let z = packed[k % half];
+                let z_conj = packed[(half - k) % half].conj();
+                let even = (z + z_conj) * 0.5;
+                let odd = (z - z_conj) * (-0.5 * i);
+                even + self.twiddles[k] * odd
+            })
+            .collect()
+    }
+
+    pub fn inverse(&self, spectrum: &[Complex<f64>]) -> Vec<f64> {
+        if spectrum.len() != self.spectrum_len() {
+            panic!("Spectrum length must be equal to len / 2 + 1");
+        }
+        if self.len == 0 {
+            return vec![];
+        }
+        if self.twiddles.is_empty() {
+            let mut buffer = spectrum.to_vec();
+            buffer.extend(
+                spectrum[1..].iter().rev().take(self.len - spectrum.len()).map(|x| x.conj()),
+            );
+            self.inner.inverse(&mut buffer);
+            return buffer.iter().map(|x| x.re).collect();
+        }
+        let half = self.len / 2;
+        let i = Complex::new(0.0, 1.0);
+        let mut packed = (0..half)
+            .map(|k| {
+                let x = spectrum[k];
+                let x_conj = spectrum[half - k].conj();
+                let even = (x + x_conj) * 0.5;
+                let odd = (x - x_conj) * self.twiddles[k].conj() * 0.5;
+                even + i * odd
+            })
+            .collect::<Vec<_>>();
+        self.inner.inverse(&mut packed);
+        packed.iter().flat_map(|z| [2.0 * z.re, 2.0 * z.im]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn naive_dft(signal: &[Complex<f64>]) -> Vec<Complex<f64>> {
+        let n = signal.len();
+        (0..n)
+            .map(|k| {
+                signal
+                    .iter()
+                    .enumerate()
+                    .map(|(t, &x)| x * twiddle((k * t) % n, n))
+                    .fold(Complex::new(0.0, 0.0), |acc, x| acc + x)
+            })
+            .collect()
+    }
+
+    fn test_signal(n: usize) -> Vec<Complex<f64>> {
+        (0..n)
+            .map(|i| Complex::new((i as f64 * 0.7).sin() + 0.1 * i as f64, (i as f64 * 1.3).cos()))
+            .collect()
+    }
+
+    #[test]
+    fn test_prime_factors() {
+        assert_eq!(prime_factors(1), Vec::<usize>::new());
+        assert_eq!(prime_factors(12), vec![2, 2, 3]);
+        assert_eq!(prime_factors(97), vec![97]);
+        assert_eq!(prime_factors(2 * 3 * 5 * 37), vec![2, 3, 5, 37]);
+    }
+
+    #[test]
+    fn test_fft_against_naive() {
+        // radix-2, mixed radix and bluestein lengths
+        let lens =
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 12, 15, 16, 30, 31, 37, 64, 74, 97, 100, 128, 210];
+        for &n in lens.iter() {
+            let signal = test_signal(n);
+            let expected = naive_dft(&signal);
+            let mut buffer = signal.clone();
+            let plan = FftPlan::new(n);
+            assert_eq!(plan.len(), n);
+            plan.forward(&mut buffer);
+            for i in 0..n {
+                assert_relative_eq!(buffer[i].re, expected[i].re, epsilon = 1e-9);
+                assert_relative_eq!(buffer[i].im, expected[i].im, epsilon = 1e-9);
+            }
+            plan.inverse(&mut buffer);
+            for i in 0..n {
+                assert_relative_eq!(buffer[i].re / n as f64, signal[i].re, epsilon = 1e-9);
+                assert_relative_eq!(buffer[i].im / n as f64, signal[i].im, epsilon = 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fft_plan_reuse() {
+        let plan = FftPlan::new(12);
+        for shift in 0..3 {
+            let signal = test_signal(12 + shift)[shift..].to_vec();
+            let expected = naive_dft(&signal);
+            let mut buffer = signal.clone();
+            plan.process(&mut buffer, FftDirection::Forward);
+            for i in 0..12 {
+                assert_relative_eq!(buffer[i].re, expected[i].re, epsilon = 1e-9);
+                assert_relative_eq!(buffer[i].im, expected[i].im, epsilon = 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_fft_wrong_length_panic() {
+        let plan = FftPlan::new(8);
+        let mut buffer = vec![Complex::new(0.0, 0.0); 7];
+        plan.forward(&mut buffer);
+    }
+
+    #[test]
+    fn test_real_fft() {
+        for n in [1, 2, 3, 4, 5, 6, 10, 13, 16, 74, 97, 100] {
+            let signal = test_signal(n).iter().map(|x| x.re).collect::<Vec<_>>();
+            let complex = signal.iter().map(|&x| Complex::new(x, 0.0)).collect::<Vec<_>>();
+            let expected = naive_dft(&complex);
+            let plan = RealFftPlan::new(n);
+            let spectrum = plan.forward(&signal);
+            assert_eq!(spectrum.len(), n / 2 + 1);
+            for i in 0..spectrum.len() {
+                assert_relative_eq!(spectrum[i].re, expected[i].re, epsilon = 1e-9);
+                assert_relative_eq!(spectrum[i].im, expected[i].im, epsilon = 1e-9);
+            }
+            let restored = plan.inverse(&spectrum);
+            assert_eq!(restored.len(), n);
+            for i in 0..n {
+                assert_relative_eq!(restored[i] / n as f64, signal[i], epsilon = 1e-9);
+            }
+        }
+    }
+}
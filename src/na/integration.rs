@@ -0,0 +1,478 @@
+//! Numerical integration
+//!
+//! Quadrature of closures (`adaptive_simpson`, `gauss_legendre`, `gauss_kronrod`, `romberg`)
+//! and of sampled data (`trapz`, `simpson`, `cumulative_trapz`). The sampled variants accept
+//! non-uniform time steps like `series_linear_interp` does.
+
+use crate::error::{check_same_len, Result};
+
+/// Result of a quadrature of a closure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quadrature {
+    /// Estimated value of the integral
+    pub value: f64,
+    /// Estimated absolute error of `value`
+    pub error: f64,
+    /// Number of times the integrand was evaluated
+    pub evaluations: usize,
+}
+
+// Gauss-Kronrod 7-15 nodes and weights on [-1, 1], from QUADPACK
+const KRONROD_NODES: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.0,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+const GAUSS_WEIGHTS: [f64; 4] =
+    [0.1294849661688697, 0.27970539148927664, 0.3818300505051189, 0.4179591836734694];
+
+/// Nodes and weights of the `n` point Gauss-Legendre rule on [-1, 1].
+pub fn gauss_legendre_nodes(n: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut nodes = vec![0.0; n];
+    let mut weights = vec![0.0; n];
+    for i in 0..n.div_ceil(2) {
+        // Newton iteration on P_n from the Chebyshev-like initial guess
+        let mut x = (std::f64::consts::PI * (i as f64 + 0.75) / (n as f64 + 0.5)).cos();
+        let mut dp = 0.0;
+        for _ in 0..100 {
+            let mut p0 = 1.0;
+            let mut p1 = 0.0;
+            for j in 0..n {
+                let p2 = p1;
+                p1 = p0;
+                p0 = ((2 * j + 1) as f64 * x * p1 - j as f64 * p2) / (j + 1) as f64;
+            }
+            dp = n as f64 * (x * p0 - p1) / (x * x - 1.0);
+            let dx = p0 / dp;
+            x -= dx;
+            if dx.abs() < 1e-15 {
+                break;
+            }
+        }
+        nodes[i] = -x;
+        nodes[n - 1 - i] = x;
+        weights[i] = 2.0 / ((1.0 - x * x) * dp * dp);
+        weights[n - 1 - i] = weights[i];
+    }
+    (nodes, weights)
+}
+
+/// Integrate `f` over [a, b] with the fixed `n` point Gauss-Legendre rule.
+///
+/// Exact for polynomials of degree up to `2n - 1`.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::na::integration::gauss_legendre;
+/// let result = gauss_legendre(|x| x * x, 0.0, 3.0, 2);
+/// assert!((result - 9.0).abs() < 1e-12);
+/// ```
+pub fn gauss_legendre<F: Fn(f64) -> f64>(f: F, a: f64, b: f64, n: usize) -> f64 {
+    let (nodes, weights) = gauss_legendre_nodes(n);
+    let half = (b - a) / 2.0;
+    let center = (a + b) / 2.0;
+    nodes.iter().zip(weights.iter()).map(|(x, w)| w * f(center + half * x)).sum::<f64>() * half
+}
+
+/// One Gauss-Kronrod 7-15 panel, returns the Kronrod value and the Gauss-Kronrod difference.
+fn kronrod_panel<F: Fn(f64) -> f64>(f: &F, a: f64, b: f64) -> (f64, f64) {
+    let half = (b - a) / 2.0;
+    let center = (a + b) / 2.0;
+    let f_center = f(center);
+    let mut kronrod = f_center * KRONROD_WEIGHTS[7];
+    let mut gauss = f_center * GAUSS_WEIGHTS[3];
+    for j in 0..7 {
+        let dx = half * KRONROD_NODES[j];
+        let pair = f(center - dx) + f(center + dx);
+        kronrod += KRONROD_WEIGHTS[j] * pair;
+        if j % 2 == 1 {
+            gauss += GAUSS_WEIGHTS[j / 2] * pair;
+        }
+    }
+    (kronrod * half, ((kronrod - gauss) * half).abs())
+}
+
+/// Adaptive Gauss-Kronrod 7-15 quadrature of `f` over [a, b].
+///
+/// The panel with the largest error estimate is bisected until the total error is below
+/// `max(abs_tol, rel_tol * |value|)` or `max_intervals` panels have been used. A NaN error
+/// estimate stops the bisection and is returned as is.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::na::integration::gauss_kronrod;
+/// let result = gauss_kronrod(|x: f64| x.sin(), 0.0, std::f64::consts::PI, 1e-12, 1e-12, 50);
+/// assert!((result.value - 2.0).abs() < 1e-12);
+/// ```
+pub fn gauss_kronrod<F: Fn(f64) -> f64>(
+    f: F,
+    a: f64,
+    b: f64,
+    abs_tol: f64,
+    rel_tol: f64,
+    max_intervals: usize,
+) -> Quadrature {
+    let (value, error) = kronrod_panel(&f, a, b);
+    let mut panels = vec![(a, b, value, error)];
+    let mut evaluations = 15;
+    loop {
+        let value = panels.iter().map(|p| p.2).sum::<f64>();
+        let error = panels.iter().map(|p| p.3).sum::<f64>();
+        if error.is_nan()
+            || error <= abs_tol.max(rel_tol * value.abs())
+            || panels.len() >= max_intervals.max(1)
+        {
+            return Quadrature { value, error, evaluations };
+        }
+        let worst = (0..panels.len()).max_by(|&i, &j| panels[i].3.total_cmp(&panels[j].3)).unwrap();
+        let (lo, hi, _, _) = panels.swap_remove(worst);
+        let mid = (lo + hi) / 2.0;
+        let (left, left_err) = kronrod_panel(&f, lo, mid);
+        let (right, right_err) = kronrod_panel(&f, mid, hi);
+        evaluations += 30;
+        panels.push((lo, mid, left, left_err));
+        panels.push((mid, hi, right, right_err));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn simpson_step<F: Fn(f64) -> f64>(
+    f: &F,
+    a: f64,
+    b: f64,
+    fa: f64,
+    fm: f64,
+    fb: f64,
+    whole: f64,
+    tol: f64,
+    depth: usize,
+    evaluations: &mut usize,
+) -> (f64, f64) {
+    let m = (a + b) / 2.0;
+    let lm = (a + m) / 2.0;
+    let rm = (m + b) / 2.0;
+    let flm = f(lm);
+    let frm = f(rm);
+    *evaluations += 2;
+    let left = (m - a) / 6.0 * (fa + 4.0 * flm + fm);
+    let right = (b - m) / 6.0 * (fm + 4.0 * frm + fb);
+    let delta = left + right - whole;
+    if depth == 0 || delta.abs() <= 15.0 * tol {
+        // Richardson extrapolation of the two Simpson estimates
+        return (left + right + delta / 15.0, delta.abs() / 15.0);
+    }
+    let (lv, le) = simpson_step(f, a, m, fa, flm, fm, left, tol / 2.0, depth - 1, evaluations);
+    let (rv, re) = simpson_step(f, m, b, fm, frm, fb, right, tol / 2.0, depth - 1, evaluations);
+    (lv + rv, le + re)
+}
+
+/// Adaptive Simpson quadrature of `f` over [a, b] to absolute tolerance `tol`.
+///
+/// `max_depth` limits the number of bisections of any subinterval.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::na::integration::adaptive_simpson;
+/// let result = adaptive_simpson(|x: f64| x.exp(), 0.0, 1.0, 1e-10, 50);
+/// assert!((result.value - (1f64.exp() - 1.0)).abs() < 1e-10);
+/// ```
+pub fn adaptive_simpson<F: Fn(f64) -> f64>(
+    f: F,
+    a: f64,
+    b: f64,
+    tol: f64,
+    max_depth: usize,
+) -> Quadrature {
+    let fa = f(a);
+    let fb = f(b);
+    let fm = f((a + b) / 2.0);
+    let whole = (b - a) / 6.0 * (fa + 4.0 * fm + fb);
+    let mut evaluations = 3;
+    let (value, error) =
+        simpson_step(&f, a, b, fa, fm, fb, whole, tol, max_depth, &mut evaluations);
+    Quadrature { value, error, evaluations }
+}
+
+/// Romberg integration of `f` over [a, b].
+///
+/// Trapezoid estimates with `2^k` panels are extrapolated until two diagonal entries of the
+/// Romberg table differ by less than `tol`, using at most `max_steps` refinements.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::na::integration::romberg;
+/// let result = romberg(|x: f64| 1.0 / (1.0 + x * x), 0.0, 1.0, 1e-12, 20);
+/// assert!((result.value - std::f64::consts::FRAC_PI_4).abs() < 1e-12);
+/// ```
+pub fn romberg<F: Fn(f64) -> f64>(f: F, a: f64, b: f64, tol: f64, max_steps: usize) -> Quadrature {
+    let mut h = b - a;
+    let mut previous = vec![h / 2.0 * (f(a) + f(b))];
+    let mut evaluations = 2;
+    let mut error = f64::INFINITY;
+    for k in 1..=max_steps {
+        h /= 2.0;
+        let n_new = 1usize << (k - 1);
+        let sum = (0..n_new).map(|i| f(a + (2 * i + 1) as f64 * h)).sum::<f64>();
+        evaluations += n_new;
+        let mut current = vec![previous[0] / 2.0 + h * sum];
+        let mut factor = 1.0;
+        for j in 1..=k {
+            factor *= 4.0;
+            current.push(current[j - 1] + (current[j - 1] - previous[j - 1]) / (factor - 1.0));
+        }
+        error = (current[k] - previous[k - 1]).abs();
+        previous = current;
+        if error < tol {
+            break;
+        }
+    }
+    Quadrature { value: previous[previous.len() - 1], error, evaluations }
+}
+
+/// Integrate sampled data with the trapezoidal rule.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::na::integration::trapz;
+/// let time = [0.0, 1.0, 3.0];
+/// let signal = [0.0, 1.0, 3.0];
+/// assert_eq!(trapz(&time, &signal), 4.5);
+/// ```
+pub fn trapz<X: Into<f64> + Copy, Y: Into<f64> + Copy>(time: &[X], signal: &[Y]) -> f64 {
+    try_trapz(time, signal).unwrap_or_else(|e| panic!("Cannot integrate: {}", e))
+}
+
+/// Same as `trapz`, but returns an error if time and signal have different lengths.
+pub fn try_trapz<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    time: &[X],
+    signal: &[Y],
+) -> Result<f64> {
+    check_same_len(time.len(), signal.len())?;
+    Ok((1..time.len())
+        .map(|i| {
+            (time[i].into() - time[i - 1].into()) * (signal[i].into() + signal[i - 1].into()) / 2.0
+        })
+        .sum())
+}
+
+/// Running trapezoidal integral of sampled data, starting from 0 at `time[0]`.
+///
+/// The result has the same length as `signal`.
+pub fn cumulative_trapz<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    time: &[X],
+    signal: &[Y],
+) -> Vec<f64> {
+    try_cumulative_trapz(time, signal).unwrap_or_else(|e| panic!("Cannot integrate: {}", e))
+}
+
+/// Same as `cumulative_trapz`, but returns an error if time and signal have different lengths.
+pub fn try_cumulative_trapz<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    time: &[X],
+    signal: &[Y],
+) -> Result<Vec<f64>> {
+    check_same_len(time.len(), signal.len())?;
+    let mut result = Vec::with_capacity(signal.len());
+    let mut sum = 0.0;
+    for i in 0..signal.len() {
+        if i > 0 {
+            sum += (time[i].into() - time[i - 1].into())
+                * (signal[i].into() + signal[i - 1].into())
+                / 2.0;
+        }
+        result.push(sum);
+    }
+    Ok(result)
+}
+
+/// Integrate sampled data with the composite Simpson's rule for non-uniform steps.
+///
+/// Pairs of intervals are integrated by the quadratic through their three samples. For an
+/// odd number of intervals the last one is integrated with the quadratic through the last
+/// three samples, so the rule stays exact for quadratics.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::na::integration::simpson;
+/// let time = [0.0, 0.5, 2.0, 3.0];
+/// let signal = time.iter().map(|t| t * t).collect::<Vec<f64>>();
+/// assert!((simpson(&time, &signal) - 9.0).abs() < 1e-12);
+/// ```
+pub fn simpson<X: Into<f64> + Copy, Y: Into<f64> + Copy>(time: &[X], signal: &[Y]) -> f64 {
+    try_simpson(time, signal).unwrap_or_else(|e| panic!("Cannot integrate: {}", e))
+}
+
+/// Same as `simpson`, but returns an error if time and signal have different lengths.
+pub fn try_simpson<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    time: &[X],
+    signal: &[Y],
+) -> Result<f64> {
+    check_same_len(time.len(), signal.len())?;
+    let n = time.len();
+    if n < 3 {
+        return try_trapz(time, signal);
+    }
+    let x = time.iter().map(|&t| t.into()).collect::<Vec<f64>>();
+    let y = signal.iter().map(|&s| s.into()).collect::<Vec<f64>>();
+    let intervals = n - 1;
+    let mut result = 0.0;
+    for i in (0..intervals - 1).step_by(2) {
+        let h0 = x[i + 1] - x[i];
+        let h1 = x[i + 2] - x[i + 1];
+        result += (h0 + h1) / 6.0
+            * ((2.0 - h1 / h0) * y[i]
+                + (h0 + h1).powi(2) / (h0 * h1) * y[i + 1]
+                + (2.0 - h0 / h1) * y[i + 2]);
+    }
+    if intervals % 2 == 1 {
+        let h0 = x[n - 2] - x[n - 3];
+        let h1 = x[n - 1] - x[n - 2];
+        let alpha = (2.0 * h1 * h1 + 3.0 * h0 * h1) / (6.0 * (h0 + h1));
+        let beta = (h1 * h1 + 3.0 * h0 * h1) / (6.0 * h0);
+        let eta = h1.powi(3) / (6.0 * h0 * (h0 + h1));
+        result += alpha * y[n - 1] + beta * y[n - 2] - eta * y[n - 3];
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use approx::assert_relative_eq;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_gauss_legendre_nodes() {
+        let (nodes, weights) = gauss_legendre_nodes(3);
+        assert_relative_eq!(nodes[0], -(0.6f64.sqrt()), epsilon = 1e-14);
+        assert_relative_eq!(nodes[1], 0.0, epsilon = 1e-14);
+        assert_relative_eq!(nodes[2], 0.6f64.sqrt(), epsilon = 1e-14);
+        assert_relative_eq!(weights[0], 5.0 / 9.0, epsilon = 1e-14);
+        assert_relative_eq!(weights[1], 8.0 / 9.0, epsilon = 1e-14);
+        for n in 1..30 {
+            let (_, weights) = gauss_legendre_nodes(n);
+            assert_relative_eq!(weights.iter().sum::<f64>(), 2.0, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_gauss_legendre() {
+        // exact for degree 2n - 1
+        let result = gauss_legendre(|x| x.powi(5) - 2.0 * x.powi(3) + 1.0, -1.0, 2.0, 3);
+        assert_relative_eq!(result, 10.5 - 7.5 + 3.0, epsilon = 1e-12);
+        assert_relative_eq!(gauss_legendre(|x| x.sin(), 0.0, PI, 20), 2.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_gauss_kronrod() {
+        let result = gauss_kronrod(|x| x.sqrt(), 0.0, 1.0, 1e-10, 1e-10, 100);
+        assert_relative_eq!(result.value, 2.0 / 3.0, epsilon = 1e-10);
+        assert!(result.error < 1e-10);
+        let result = gauss_kronrod(|x| (-x * x).exp(), -10.0, 10.0, 1e-12, 0.0, 100);
+        assert_relative_eq!(result.value, PI.sqrt(), epsilon = 1e-12);
+        assert_eq!(result.evaluations % 15, 0);
+        let result =
+            gauss_kronrod(|x| if x > 0.7 { f64::NAN } else { x }, 0.0, 1.0, 1e-10, 1e-10, 50);
+        assert!(result.value.is_nan() && result.error.is_nan());
+    }
+
+    #[test]
+    fn test_adaptive_simpson() {
+        let result = adaptive_simpson(|x| x.cos(), 0.0, PI / 2.0, 1e-12, 50);
+        assert_relative_eq!(result.value, 1.0, epsilon = 1e-12);
+        let result = adaptive_simpson(|x| 1.0 / x, 1.0, 100.0, 1e-9, 50);
+        assert_relative_eq!(result.value, 100f64.ln(), epsilon = 1e-9);
+        assert!(result.evaluations > 3);
+    }
+
+    #[test]
+    fn test_romberg() {
+        let result = romberg(|x| x.exp(), 0.0, 2.0, 1e-12, 20);
+        assert_relative_eq!(result.value, 2f64.exp() - 1.0, epsilon = 1e-11);
+        let result = romberg(|x| 3.0 * x * x, 0.0, 1.0, 1e-12, 20);
+        assert_relative_eq!(result.value, 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_trapz() {
+        let time = vec![0.0, 1.0, 2.0, 4.0];
+        let signal = vec![1.0, 1.0, 1.0, 1.0];
+        assert_relative_eq!(trapz(&time, &signal), 4.0);
+        let time = vec![0, 1, 2, 4];
+        let signal = vec![0, 1, 2, 4];
+        assert_relative_eq!(trapz(&time, &signal), 8.0);
+        assert_relative_eq!(trapz(&[1.0], &[3.0]), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_trapz_panic() {
+        trapz(&[0.0, 1.0], &[1.0]);
+    }
+
+    #[test]
+    fn test_try_sampled() {
+        let mismatch = Err(Error::LengthMismatch { left: 3, right: 2 });
+        assert_eq!(try_trapz(&[0.0, 1.0, 2.0], &[1.0, 1.0]), mismatch);
+        assert_eq!(try_simpson(&[0.0, 1.0, 2.0], &[1.0, 1.0]), mismatch);
+        assert_eq!(
+            try_cumulative_trapz(&[0.0, 1.0, 2.0], &[1.0, 1.0]),
+            Err(Error::LengthMismatch { left: 3, right: 2 })
+        );
+        assert_eq!(try_simpson(&[0.0, 1.0], &[1.0, 1.0]), Ok(1.0));
+    }
+
+    #[test]
+    fn test_cumulative_trapz() {
+        let time = vec![0.0, 1.0, 3.0, 4.0];
+        let signal = vec![2.0, 2.0, 2.0, 2.0];
+        let result = cumulative_trapz(&time, &signal);
+        let expected = [0.0, 2.0, 6.0, 8.0];
+        assert_eq!(result.len(), signal.len());
+        for i in 0..expected.len() {
+            assert_relative_eq!(result[i], expected[i]);
+        }
+        assert_relative_eq!(result[3], trapz(&time, &signal));
+    }
+
+    #[test]
+    fn test_simpson() {
+        let time = vec![0.0, 0.3, 1.0, 1.5, 2.5];
+        let signal = time.iter().map(|t| t * t - t).collect::<Vec<f64>>();
+        assert_relative_eq!(
+            simpson(&time, &signal),
+            2.5f64.powi(3) / 3.0 - 2.5 * 2.5 / 2.0,
+            epsilon = 1e-12
+        );
+        let time = vec![0.0, 0.3, 1.0, 1.5, 2.5, 2.7];
+        let signal = time.iter().map(|t| 3.0 * t * t).collect::<Vec<f64>>();
+        assert_relative_eq!(simpson(&time, &signal), 2.7f64.powi(3), epsilon = 1e-12);
+        let time = (0..=100).map(|i| i as f64 * PI / 100.0).collect::<Vec<f64>>();
+        let signal = time.iter().map(|t| t.sin()).collect::<Vec<f64>>();
+        assert_relative_eq!(simpson(&time, &signal), 2.0, epsilon = 1e-7);
+        let time = vec![0, 1, 2];
+        let signal = vec![0, 1, 4];
+        assert_relative_eq!(simpson(&time, &signal), 8.0 / 3.0, epsilon = 1e-12);
+    }
+}
@@ -1,6 +1,90 @@
+//! Interpolation
+//!
+//! `series_linear_interp` resamples a series in one call. An `Interpolator` is fitted once
+//! and evaluates cubic splines, monotone PCHIP, Akima or step curves together with their
+//! derivatives and integrals.
+
+use crate::error::{check_len, check_no_nan, check_same_len, Error, Result};
+
+/// What an interpolator returns outside of its knots.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Exterpolation {
+    /// Extend the first and last piece, step interpolators hold the first and last value
     Linear,
+    /// Hold the first and last value
     Saturation,
+    /// NaN
+    Nan,
+    /// Return `Error::OutOfRange`
+    Error,
+    /// Repeat the series with period `time[last] - time[0]`, so the last knot should close
+    /// the cycle, e.g. a daily profile sampled from 00:00 to 24:00
+    Periodic,
+    /// A fixed value
+    Constant(f64),
+    /// Mirror the series at its ends
+    Reflect,
+}
+
+/// Where a time lands on an interpolated curve under an `Exterpolation`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Extended {
+    /// On the curve at `x`, which runs backwards if `mirrored`
+    Curve { x: f64, mirrored: bool },
+    /// Flat at the value of the curve at the given end knot
+    Hold(f64),
+    /// Flat at the given value
+    Constant(f64),
+}
+
+fn extend(time: &[f64], x: f64, exterp: Exterpolation) -> Result<Extended> {
+    let (start, end) = (time[0], time[time.len() - 1]);
+    if x.is_nan() || (start..=end).contains(&x) {
+        return Ok(Extended::Curve { x, mirrored: false });
+    }
+    let period = end - start;
+    let extended = match exterp {
+        Exterpolation::Linear => Extended::Curve { x, mirrored: false },
+        Exterpolation::Saturation => Extended::Hold(x.clamp(start, end)),
+        Exterpolation::Periodic | Exterpolation::Reflect if period == 0.0 => Extended::Hold(start),
+        Exterpolation::Nan => Extended::Constant(f64::NAN),
+        Exterpolation::Error => return Err(Error::OutOfRange { value: x, start, end }),
+        Exterpolation::Periodic => {
+            Extended::Curve { x: start + (x - start).rem_euclid(period), mirrored: false }
+        }
+        Exterpolation::Constant(value) => Extended::Constant(value),
+        Exterpolation::Reflect => {
+            let u = (x - start).rem_euclid(2.0 * period);
+            if u <= period {
+                Extended::Curve { x: start + u, mirrored: false }
+            } else {
+                Extended::Curve { x: start + 2.0 * period - u, mirrored: true }
+            }
+        }
+    };
+    Ok(extended)
+}
+
+/// `extend` every query, together with a position on the knots to `locate` it at.
+fn extend_series(
+    time: &[f64],
+    desired_time: &[f64],
+    exterp: Exterpolation,
+) -> Result<(Vec<Extended>, Vec<usize>)> {
+    let (start, end) = (time[0], time[time.len() - 1]);
+    let extended =
+        desired_time.iter().map(|&x| extend(time, x, exterp)).collect::<Result<Vec<_>>>()?;
+    let positions = extended
+        .iter()
+        .zip(desired_time.iter())
+        .map(|(e, &x)| match *e {
+            Extended::Curve { x, .. } => x,
+            Extended::Hold(x) => x,
+            Extended::Constant(_) => x.clamp(start, end),
+        })
+        .collect::<Vec<f64>>();
+    let intervals = locate(time, &positions);
+    Ok((extended, intervals))
 }
 
 fn point_linear_interp<T: Into<f64> + Copy, S: Into<f64> + Copy>(
@@ -13,52 +97,521 @@ fn point_linear_interp<T: Into<f64> + Copy, S: Into<f64> + Copy>(
     y0.into() + (x.into() - x0.into()) * (y1.into() - y0.into()) / (x1.into() - x0.into())
 }
 
+/// Linear interpolation of the series `(orig_time, orig_signal)` at `desired_time`.
 pub fn series_linear_interp<X: Into<f64> + Copy, Y: Into<f64> + Copy, Z: Into<f64> + Copy>(
     orig_time: &[X],
     orig_signal: &[Y],
     desired_time: &[Z],
     exterp: Exterpolation,
 ) -> Vec<f64> {
-    let mut result: Vec<f64> = vec![];
-    for &item in desired_time {
-        if item.into() < orig_time[0].into() {
-            match exterp {
-                Exterpolation::Linear => result.push(point_linear_interp(
-                    item.into(),
-                    orig_time[0].into(),
-                    orig_signal[0].into(),
-                    orig_time[1].into(),
-                    orig_signal[1].into(),
-                )),
-                Exterpolation::Saturation => result.push(orig_signal[0].into()),
-            }
-        }
-        for i in 0..orig_time.len() - 1 {
-            if item.into() >= orig_time[i].into() && item.into() <= orig_time[i + 1].into() {
-                result.push(point_linear_interp(
-                    item.into(),
-                    orig_time[i].into(),
-                    orig_signal[i].into(),
-                    orig_time[i + 1].into(),
-                    orig_signal[i + 1].into(),
-                ));
-                break;
-            }
-        }
-        if item.into() > orig_time[orig_time.len() - 1].into() {
-            match exterp {
-                Exterpolation::Linear => result.push(point_linear_interp(
-                    item.into(),
-                    orig_time[orig_time.len() - 2].into(),
-                    orig_signal[orig_signal.len() - 2].into(),
-                    orig_time[orig_time.len() - 1].into(),
-                    orig_signal[orig_signal.len() - 1].into(),
-                )),
-                Exterpolation::Saturation => result.push(orig_signal[orig_signal.len() - 1].into()),
-            }
-        }
-    }
-    result
+    try_series_linear_interp(orig_time, orig_signal, desired_time, exterp)
+        .unwrap_or_else(|e| panic!("Cannot interpolate: {}", e))
+}
+
+/// Same as `series_linear_interp`, but returns an error if there are less than 2 original
+/// samples, time and signal have different lengths, contain NaN or time is not strictly
+/// increasing, or for a desired time out of range with `Exterpolation::Error`.
+///
+/// Every desired time gives exactly one value, NaN for a NaN time. Each value is found by
+/// binary search, or in a single merge-walk over the knots if `desired_time` is sorted.
+pub fn try_series_linear_interp<X: Into<f64> + Copy, Y: Into<f64> + Copy, Z: Into<f64> + Copy>(
+    orig_time: &[X],
+    orig_signal: &[Y],
+    desired_time: &[Z],
+    exterp: Exterpolation,
+) -> Result<Vec<f64>> {
+    let (time, signal) = check_knots(orig_time, orig_signal, 2)?;
+    let desired_time = desired_time.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    let (extended, intervals) = extend_series(&time, &desired_time, exterp)?;
+    let result = extended
+        .into_iter()
+        .zip(intervals)
+        .map(|(e, i)| match e {
+            Extended::Curve { x, .. } | Extended::Hold(x) => {
+                point_linear_interp(x, time[i], signal[i], time[i + 1], signal[i + 1])
+            }
+            Extended::Constant(value) => value,
+        })
+        .collect();
+    Ok(result)
+}
+
+/// Index of the interval of `time` containing `x`, the first or last one outside of `time`.
+fn interval(time: &[f64], x: f64) -> usize {
+    time.partition_point(|&t| t <= x).saturating_sub(1).min(time.len().saturating_sub(2))
+}
+
+/// `interval` of every query, walking along `time` once if `queries` is sorted.
+fn locate(time: &[f64], queries: &[f64]) -> Vec<usize> {
+    if !queries.windows(2).all(|w| w[0] <= w[1]) {
+        return queries.iter().map(|&x| interval(time, x)).collect();
+    }
+    let last = time.len().saturating_sub(2);
+    let mut i = 0;
+    queries
+        .iter()
+        .map(|&x| {
+            while i < last && time[i + 1] <= x {
+                i += 1;
+            }
+            i
+        })
+        .collect()
+}
+
+/// Boundary condition of a cubic spline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplineBoundary {
+    /// Zero second derivative at both ends
+    Natural,
+    /// Given first derivatives at the first and last knot
+    Clamped(f64, f64),
+    /// Continuous third derivative at the second and second to last knot, needs 4 knots
+    NotAKnot,
+}
+
+/// Which neighbouring knot a step interpolator takes its value from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepMode {
+    Previous,
+    Next,
+    /// The closer knot, the previous one at the midpoint
+    Nearest,
+}
+
+#[derive(Debug, Clone)]
+enum Pieces {
+    /// `[a, b, c, d]` of `a + b*dx + c*dx^2 + d*dx^3` with `dx = x - time[i]` on interval i
+    Cubic(Vec<[f64; 4]>),
+    Step(StepMode),
+}
+
+/// Piecewise interpolant of a series, fitted once and evaluated many times.
+///
+/// Outside of the knots it follows its `Exterpolation`, `Exterpolation::Linear` unless set
+/// with `with_exterpolation`. Evaluation only fails out of range with `Exterpolation::Error`.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::na::interpolation::{Interpolator, SplineBoundary};
+/// let time = [0.0, 1.0, 2.0, 3.0];
+/// let signal = time.map(|t: f64| t.powi(3));
+/// let spline = Interpolator::cubic_spline(&time, &signal, SplineBoundary::NotAKnot).unwrap();
+/// assert!((spline.evaluate(1.5).unwrap() - 3.375).abs() < 1e-12);
+/// assert!((spline.derivative(1.5).unwrap() - 6.75).abs() < 1e-12);
+/// assert!((spline.integral(0.0, 2.0).unwrap() - 4.0).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Interpolator {
+    time: Vec<f64>,
+    signal: Vec<f64>,
+    pieces: Pieces,
+    exterp: Exterpolation,
+    /// Integral from the first knot to each knot
+    cumulative: Vec<f64>,
+}
+
+/// Knots as `f64`, checked to be at least `required`, free of NaN and strictly increasing.
+fn check_knots<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    orig_time: &[X],
+    orig_signal: &[Y],
+    required: usize,
+) -> Result<(Vec<f64>, Vec<f64>)> {
+    check_same_len(orig_time.len(), orig_signal.len())?;
+    check_len(orig_time.len(), required)?;
+    check_no_nan(orig_time)?;
+    check_no_nan(orig_signal)?;
+    let time = orig_time.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    if time.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(Error::InvalidParameter("time must be strictly increasing".to_string()));
+    }
+    Ok((time, orig_signal.iter().map(|&x| x.into()).collect()))
+}
+
+fn secants(time: &[f64], signal: &[f64]) -> Vec<f64> {
+    (0..time.len() - 1).map(|i| (signal[i + 1] - signal[i]) / (time[i + 1] - time[i])).collect()
+}
+
+/// Cubic Hermite pieces through the knots with first derivatives `slopes`.
+fn hermite_pieces(time: &[f64], signal: &[f64], slopes: &[f64]) -> Vec<[f64; 4]> {
+    let delta = secants(time, signal);
+    (0..time.len() - 1)
+        .map(|i| {
+            let h = time[i + 1] - time[i];
+            [
+                signal[i],
+                slopes[i],
+                (3.0 * delta[i] - 2.0 * slopes[i] - slopes[i + 1]) / h,
+                (slopes[i] + slopes[i + 1] - 2.0 * delta[i]) / (h * h),
+            ]
+        })
+        .collect()
+}
+
+/// Thomas algorithm for a tridiagonal system, `lower[0]` and `upper[n - 1]` are unused.
+fn solve_tridiagonal(lower: &[f64], diag: &[f64], upper: &[f64], rhs: &[f64]) -> Vec<f64> {
+    let n = diag.len();
+    let mut c = vec![0.0; n];
+    let mut d = vec![0.0; n];
+    c[0] = upper[0] / diag[0];
+    d[0] = rhs[0] / diag[0];
+    for i in 1..n {
+        let m = diag[i] - lower[i] * c[i - 1];
+        c[i] = upper[i] / m;
+        d[i] = (rhs[i] - lower[i] * d[i - 1]) / m;
+    }
+    for i in (0..n - 1).rev() {
+        d[i] -= c[i] * d[i + 1];
+    }
+    d
+}
+
+fn spline_slopes(time: &[f64], signal: &[f64], boundary: SplineBoundary) -> Vec<f64> {
+    let n = time.len();
+    let h = time.windows(2).map(|w| w[1] - w[0]).collect::<Vec<f64>>();
+    let delta = secants(time, signal);
+    let mut lower = vec![0.0; n];
+    let mut diag = vec![0.0; n];
+    let mut upper = vec![0.0; n];
+    let mut rhs = vec![0.0; n];
+    for i in 1..n - 1 {
+        lower[i] = h[i];
+        diag[i] = 2.0 * (h[i - 1] + h[i]);
+        upper[i] = h[i - 1];
+        rhs[i] = 3.0 * (h[i] * delta[i - 1] + h[i - 1] * delta[i]);
+    }
+    match boundary {
+        SplineBoundary::Natural => {
+            diag[0] = 2.0;
+            upper[0] = 1.0;
+            rhs[0] = 3.0 * delta[0];
+            lower[n - 1] = 1.0;
+            diag[n - 1] = 2.0;
+            rhs[n - 1] = 3.0 * delta[n - 2];
+        }
+        SplineBoundary::Clamped(start, end) => {
+            diag[0] = 1.0;
+            rhs[0] = start;
+            diag[n - 1] = 1.0;
+            rhs[n - 1] = end;
+        }
+        SplineBoundary::NotAKnot => {
+            let d = h[0] + h[1];
+            diag[0] = h[1];
+            upper[0] = d;
+            rhs[0] = ((h[0] + 2.0 * d) * h[1] * delta[0] + h[0] * h[0] * delta[1]) / d;
+            let d = h[n - 3] + h[n - 2];
+            lower[n - 1] = d;
+            diag[n - 1] = h[n - 3];
+            rhs[n - 1] = (h[n - 2] * h[n - 2] * delta[n - 3]
+                + (2.0 * d + h[n - 2]) * h[n - 3] * delta[n - 2])
+                / d;
+        }
+    }
+    solve_tridiagonal(&lower, &diag, &upper, &rhs)
+}
+
+/// Fritsch-Carlson slopes, weighted harmonic means of the secants that are zero at extrema.
+fn pchip_slopes(time: &[f64], signal: &[f64]) -> Vec<f64> {
+    let n = time.len();
+    let delta = secants(time, signal);
+    if n == 2 {
+        return vec![delta[0]; 2];
+    }
+    let h = time.windows(2).map(|w| w[1] - w[0]).collect::<Vec<f64>>();
+    let mut slopes = vec![0.0; n];
+    for i in 1..n - 1 {
+        if delta[i - 1] * delta[i] > 0.0 {
+            let w1 = 2.0 * h[i] + h[i - 1];
+            let w2 = h[i] + 2.0 * h[i - 1];
+            slopes[i] = (w1 + w2) / (w1 / delta[i - 1] + w2 / delta[i]);
+        }
+    }
+    let end_slope = |h0: f64, h1: f64, d0: f64, d1: f64| {
+        let slope = ((2.0 * h0 + h1) * d0 - h0 * d1) / (h0 + h1);
+        if slope * d0 <= 0.0 {
+            0.0
+        } else if d0 * d1 <= 0.0 && slope.abs() > 3.0 * d0.abs() {
+            3.0 * d0
+        } else {
+            slope
+        }
+    };
+    slopes[0] = end_slope(h[0], h[1], delta[0], delta[1]);
+    slopes[n - 1] = end_slope(h[n - 2], h[n - 3], delta[n - 2], delta[n - 3]);
+    slopes
+}
+
+/// Akima slopes, with the secants extended by two on each side by linear extrapolation.
+fn akima_slopes(time: &[f64], signal: &[f64]) -> Vec<f64> {
+    let n = time.len();
+    let delta = secants(time, signal);
+    if n == 2 {
+        return vec![delta[0]; 2];
+    }
+    let mut m = vec![0.0; n + 3];
+    m[2..n + 1].copy_from_slice(&delta);
+    m[1] = 2.0 * m[2] - m[3];
+    m[0] = 2.0 * m[1] - m[2];
+    m[n + 1] = 2.0 * m[n] - m[n - 1];
+    m[n + 2] = 2.0 * m[n + 1] - m[n];
+    (0..n)
+        .map(|i| {
+            let w1 = (m[i + 3] - m[i + 2]).abs();
+            let w2 = (m[i + 1] - m[i]).abs();
+            if w1 + w2 == 0.0 {
+                (m[i + 1] + m[i + 2]) / 2.0
+            } else {
+                (w1 * m[i + 1] + w2 * m[i + 2]) / (w1 + w2)
+            }
+        })
+        .collect()
+}
+
+impl Interpolator {
+    fn from_pieces(time: Vec<f64>, signal: Vec<f64>, pieces: Pieces) -> Self {
+        let mut interpolator = Interpolator {
+            time,
+            signal,
+            pieces,
+            exterp: Exterpolation::Linear,
+            cumulative: vec![],
+        };
+        let mut cumulative = vec![0.0; interpolator.time.len()];
+        for i in 1..cumulative.len() {
+            let h = interpolator.time[i] - interpolator.time[i - 1];
+            cumulative[i] = cumulative[i - 1] + interpolator.piece_integral(i - 1, h);
+        }
+        interpolator.cumulative = cumulative;
+        interpolator
+    }
+
+    /// Piecewise linear interpolation, the same curve as `series_linear_interp`.
+    pub fn linear<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+        orig_time: &[X],
+        orig_signal: &[Y],
+    ) -> Result<Self> {
+        let (time, signal) = check_knots(orig_time, orig_signal, 2)?;
+        let pieces = secants(&time, &signal)
+            .iter()
+            .zip(signal.iter())
+            .map(|(&slope, &y)| [y, slope, 0.0, 0.0])
+            .collect();
+        Ok(Self::from_pieces(time, signal, Pieces::Cubic(pieces)))
+    }
+
+    /// Cubic spline with continuous second derivative.
+    ///
+    /// Needs at least 2 knots, 4 with `SplineBoundary::NotAKnot`.
+    pub fn cubic_spline<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+        orig_time: &[X],
+        orig_signal: &[Y],
+        boundary: SplineBoundary,
+    ) -> Result<Self> {
+        let required = if boundary == SplineBoundary::NotAKnot { 4 } else { 2 };
+        let (time, signal) = check_knots(orig_time, orig_signal, required)?;
+        let slopes = spline_slopes(&time, &signal, boundary);
+        let pieces = hermite_pieces(&time, &signal, &slopes);
+        Ok(Self::from_pieces(time, signal, Pieces::Cubic(pieces)))
+    }
+
+    /// Monotone piecewise cubic Hermite interpolation (PCHIP), which never overshoots the data.
+    pub fn pchip<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+        orig_time: &[X],
+        orig_signal: &[Y],
+    ) -> Result<Self> {
+        let (time, signal) = check_knots(orig_time, orig_signal, 2)?;
+        let slopes = pchip_slopes(&time, &signal);
+        let pieces = hermite_pieces(&time, &signal, &slopes);
+        Ok(Self::from_pieces(time, signal, Pieces::Cubic(pieces)))
+    }
+
+    /// Akima interpolation, a cubic Hermite interpolant whose slopes only depend on nearby
+    /// knots, so outliers do not ripple through the whole curve.
+    pub fn akima<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+        orig_time: &[X],
+        orig_signal: &[Y],
+    ) -> Result<Self> {
+        let (time, signal) = check_knots(orig_time, orig_signal, 2)?;
+        let slopes = akima_slopes(&time, &signal);
+        let pieces = hermite_pieces(&time, &signal, &slopes);
+        Ok(Self::from_pieces(time, signal, Pieces::Cubic(pieces)))
+    }
+
+    /// Piecewise constant interpolation, derivatives are zero everywhere.
+    pub fn step<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+        orig_time: &[X],
+        orig_signal: &[Y],
+        mode: StepMode,
+    ) -> Result<Self> {
+        let (time, signal) = check_knots(orig_time, orig_signal, 1)?;
+        Ok(Self::from_pieces(time, signal, Pieces::Step(mode)))
+    }
+
+    /// Integral of piece `i` from its left knot over a length `dx`.
+    fn piece_integral(&self, i: usize, dx: f64) -> f64 {
+        match &self.pieces {
+            Pieces::Cubic(pieces) => {
+                let [a, b, c, d] = pieces[i];
+                dx * (a + dx * (b / 2.0 + dx * (c / 3.0 + dx * d / 4.0)))
+            }
+            Pieces::Step(mode) => {
+                let h = self.time[i + 1] - self.time[i];
+                let (previous, next) = (self.signal[i], self.signal[i + 1]);
+                match mode {
+                    StepMode::Previous => previous * dx,
+                    StepMode::Next => next * dx,
+                    StepMode::Nearest if dx <= h / 2.0 => previous * dx,
+                    StepMode::Nearest => previous * h / 2.0 + next * (dx - h / 2.0),
+                }
+            }
+        }
+    }
+
+    fn step_value(&self, x: f64, mode: StepMode) -> f64 {
+        let n = self.time.len();
+        let next = self.time.partition_point(|&t| t < x);
+        if next == 0 {
+            return self.signal[0];
+        }
+        if next == n {
+            return self.signal[n - 1];
+        }
+        let previous = if self.time[next] == x { next } else { next - 1 };
+        match mode {
+            StepMode::Previous => self.signal[previous],
+            StepMode::Next => self.signal[next],
+            StepMode::Nearest if x - self.time[previous] <= self.time[next] - x => {
+                self.signal[previous]
+            }
+            StepMode::Nearest => self.signal[next],
+        }
+    }
+
+    /// `derivative_in_interval` of an `Extended` time in interval `i`.
+    fn derivative_extended(&self, i: usize, extended: Extended, order: usize) -> f64 {
+        match extended {
+            Extended::Curve { x, mirrored } if mirrored && order == 1 => {
+                -self.derivative_in_interval(i, x, order)
+            }
+            Extended::Curve { x, .. } => self.derivative_in_interval(i, x, order),
+            Extended::Hold(x) if order == 0 => self.derivative_in_interval(i, x, order),
+            Extended::Constant(value) if order == 0 || value.is_nan() => value,
+            Extended::Hold(_) | Extended::Constant(_) => 0.0,
+        }
+    }
+
+    /// `order`-th derivative at `x` for `order` up to 2.
+    fn derivative_of_order(&self, x: f64, order: usize) -> Result<f64> {
+        let extended = extend(&self.time, x, self.exterp)?;
+        let position = match extended {
+            Extended::Curve { x, .. } | Extended::Hold(x) => x,
+            Extended::Constant(_) => self.time[0],
+        };
+        Ok(self.derivative_extended(interval(&self.time, position), extended, order))
+    }
+
+    /// `order`-th derivative at `x` with `x` already located in interval `i`.
+    fn derivative_in_interval(&self, i: usize, x: f64, order: usize) -> f64 {
+        match &self.pieces {
+            Pieces::Cubic(pieces) => {
+                let [a, b, c, d] = pieces[i];
+                let dx = x - self.time[i];
+                match order {
+                    0 => a + dx * (b + dx * (c + dx * d)),
+                    1 => b + dx * (2.0 * c + dx * 3.0 * d),
+                    _ => 2.0 * c + 6.0 * d * dx,
+                }
+            }
+            Pieces::Step(mode) if order == 0 => self.step_value(x, *mode),
+            Pieces::Step(_) => 0.0,
+        }
+    }
+
+    /// Use `exterp` outside of the knots.
+    pub fn with_exterpolation(mut self, exterp: Exterpolation) -> Self {
+        self.exterp = exterp;
+        self
+    }
+
+    pub fn evaluate<T: Into<f64> + Copy>(&self, x: T) -> Result<f64> {
+        self.derivative_of_order(x.into(), 0)
+    }
+
+    pub fn evaluate_series<T: Into<f64> + Copy>(&self, desired_time: &[T]) -> Result<Vec<f64>> {
+        let desired_time = desired_time.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+        let (extended, intervals) = extend_series(&self.time, &desired_time, self.exterp)?;
+        Ok(intervals
+            .into_iter()
+            .zip(extended)
+            .map(|(i, e)| self.derivative_extended(i, e, 0))
+            .collect())
+    }
+
+    pub fn derivative<T: Into<f64> + Copy>(&self, x: T) -> Result<f64> {
+        self.derivative_of_order(x.into(), 1)
+    }
+
+    pub fn second_derivative<T: Into<f64> + Copy>(&self, x: T) -> Result<f64> {
+        self.derivative_of_order(x.into(), 2)
+    }
+
+    /// Integral from the first knot to `x` along the extended first and last pieces.
+    fn curve_antiderivative(&self, x: f64) -> f64 {
+        let n = self.time.len();
+        if let Pieces::Step(_) = self.pieces {
+            if n == 1 || x <= self.time[0] {
+                return self.signal[0] * (x - self.time[0]);
+            }
+            if x >= self.time[n - 1] {
+                return self.cumulative[n - 1] + self.signal[n - 1] * (x - self.time[n - 1]);
+            }
+        }
+        let i = interval(&self.time, x);
+        self.cumulative[i] + self.piece_integral(i, x - self.time[i])
+    }
+
+    /// Integral from the first knot to `x` under the `Exterpolation`.
+    fn antiderivative(&self, x: f64) -> Result<f64> {
+        let n = self.time.len();
+        let (start, end) = (self.time[0], self.time[n - 1]);
+        let total = self.cumulative[n - 1];
+        let period = end - start;
+        let flat = |start_value: f64, end_value: f64| {
+            if x < start {
+                start_value * (x - start)
+            } else {
+                total + end_value * (x - end)
+            }
+        };
+        let extended = extend(&self.time, x, self.exterp)?;
+        let value = match (self.exterp, extended) {
+            (_, Extended::Curve { .. }) if x.is_nan() || (start..=end).contains(&x) => {
+                self.curve_antiderivative(x)
+            }
+            (_, Extended::Hold(_)) => flat(self.signal[0], self.signal[n - 1]),
+            (_, Extended::Constant(value)) => flat(value, value),
+            (Exterpolation::Periodic, Extended::Curve { x: wrapped, .. }) => {
+                ((x - start) / period).floor() * total + self.curve_antiderivative(wrapped)
+            }
+            (Exterpolation::Reflect, Extended::Curve { x: mirrored_x, mirrored }) => {
+                let cycles = ((x - start) / (2.0 * period)).floor() * 2.0 * total;
+                if mirrored {
+                    cycles + 2.0 * total - self.curve_antiderivative(mirrored_x)
+                } else {
+                    cycles + self.curve_antiderivative(mirrored_x)
+                }
+            }
+            (_, Extended::Curve { .. }) => self.curve_antiderivative(x),
+        };
+        Ok(value)
+    }
+
+    /// Definite integral of the interpolant from `a` to `b`, negative if `b < a`.
+    pub fn integral<A: Into<f64> + Copy, B: Into<f64> + Copy>(&self, a: A, b: B) -> Result<f64> {
+        Ok(self.antiderivative(b.into())? - self.antiderivative(a.into())?)
+    }
 }
 
 #[cfg(test)]
@@ -66,6 +619,7 @@ mod tests {
     use std::vec;
 
     use super::*;
+    use crate::Error;
     use approx::assert_relative_eq;
 
     #[test]
@@ -84,6 +638,56 @@ mod tests {
         assert_relative_eq!(point_linear_interp(1, 0, 1, 1, 0), 0.0);
     }
 
+    #[test]
+    fn test_try_series_linear_interp() {
+        let result = try_series_linear_interp(&[1.0], &[1.0], &[0.5], Exterpolation::Linear);
+        assert_eq!(result, Err(Error::InsufficientData { required: 2, actual: 1 }));
+        let result = try_series_linear_interp(&[1.0, 2.0], &[1.0], &[0.5], Exterpolation::Linear);
+        assert_eq!(result, Err(Error::LengthMismatch { left: 2, right: 1 }));
+        let result = try_series_linear_interp(&[1, 2], &[1, 3], &[1.5], Exterpolation::Saturation);
+        assert_eq!(result, Ok(vec![2.0]));
+    }
+
+    #[test]
+    fn test_series_linear_interp_queries() {
+        let orig_time = [0.0, 1.0, 3.0, 4.0];
+        let orig_signal = [0.0, 2.0, 0.0, 1.0];
+        let desired_time = [3.5, f64::NAN, -1.0, 0.5, 5.0, 2.0, 1.0];
+        let result =
+            series_linear_interp(&orig_time, &orig_signal, &desired_time, Exterpolation::Linear);
+        assert_eq!(result.len(), desired_time.len());
+        assert!(result[1].is_nan());
+        let expected = [0.5, f64::NAN, -2.0, 1.0, 2.0, 1.0, 2.0];
+        for i in [0, 2, 3, 4, 5, 6] {
+            assert_relative_eq!(result[i], expected[i]);
+        }
+        let result = try_series_linear_interp(
+            &[0.0, 2.0, 1.0],
+            &[0.0, 1.0, 2.0],
+            &[0.5],
+            Exterpolation::Linear,
+        );
+        assert!(matches!(result, Err(Error::InvalidParameter(_))));
+        let result =
+            try_series_linear_interp(&[0.0, f64::NAN], &[0.0, 1.0], &[0.5], Exterpolation::Linear);
+        assert_eq!(result, Err(Error::NanValue { index: 1 }));
+
+        let orig_time = (0..100_000).map(|i| i as f64 * 0.1).collect::<Vec<f64>>();
+        let orig_signal = orig_time.iter().map(|t| t.sin()).collect::<Vec<f64>>();
+        let sorted = (0..250_000).map(|i| i as f64 * 0.04 - 5.0).collect::<Vec<f64>>();
+        let mut shuffled = sorted.clone();
+        shuffled.reverse();
+        let forward =
+            series_linear_interp(&orig_time, &orig_signal, &sorted, Exterpolation::Saturation);
+        let mut backward =
+            series_linear_interp(&orig_time, &orig_signal, &shuffled, Exterpolation::Saturation);
+        backward.reverse();
+        assert_eq!(forward.len(), sorted.len());
+        assert_eq!(forward, backward);
+        assert_relative_eq!(forward[0], 0.0);
+        assert_relative_eq!(forward[100_125], orig_signal[40_000], epsilon = 1e-9);
+    }
+
     #[test]
     fn test_series_linear_interp() {
         let orig_time = vec![1.0, 2.0, 3.0, 4.0, 5.0];
@@ -123,4 +727,227 @@ mod tests {
             assert_relative_eq!(result_sat[i], expected[i]);
         }
     }
+
+    #[test]
+    fn test_exterpolation() {
+        let time = [0.0, 1.0, 2.0];
+        let signal = [1.0, 3.0, 2.0];
+        let desired_time = [-1.5, -0.5, 0.5, 2.5, 4.5];
+        let cases = [
+            (Exterpolation::Linear, [-2.0, 0.0, 2.0, 1.5, -0.5]),
+            (Exterpolation::Saturation, [1.0, 1.0, 2.0, 2.0, 2.0]),
+            (Exterpolation::Constant(7.0), [7.0, 7.0, 2.0, 7.0, 7.0]),
+            (Exterpolation::Periodic, [2.0, 2.5, 2.0, 2.0, 2.0]),
+            (Exterpolation::Reflect, [2.5, 2.0, 2.0, 2.5, 2.0]),
+        ];
+        for (exterp, expected) in cases {
+            let result = series_linear_interp(&time, &signal, &desired_time, exterp);
+            let linear = Interpolator::linear(&time, &signal).unwrap().with_exterpolation(exterp);
+            for i in 0..expected.len() {
+                assert_relative_eq!(result[i], expected[i]);
+                assert_relative_eq!(linear.evaluate(desired_time[i]).unwrap(), expected[i]);
+            }
+        }
+        let result = series_linear_interp(&time, &signal, &desired_time, Exterpolation::Nan);
+        assert!(result[0].is_nan() && result[4].is_nan());
+        assert_relative_eq!(result[2], 2.0);
+        let result = try_series_linear_interp(&time, &signal, &desired_time, Exterpolation::Error);
+        assert_eq!(result, Err(Error::OutOfRange { value: -1.5, start: 0.0, end: 2.0 }));
+        let result = try_series_linear_interp(&time, &signal, &[0.0, 2.0], Exterpolation::Error);
+        assert_eq!(result, Ok(vec![1.0, 2.0]));
+    }
+
+    #[test]
+    fn test_interpolator_exterpolation() {
+        let time = [0.0, 1.0, 2.0];
+        let signal = [1.0, 3.0, 2.0];
+        let linear = Interpolator::linear(&time, &signal).unwrap();
+        let total = linear.integral(0.0, 2.0).unwrap();
+        assert_relative_eq!(total, 4.5);
+        let periodic = linear.clone().with_exterpolation(Exterpolation::Periodic);
+        assert_relative_eq!(periodic.integral(-4.0, 6.0).unwrap(), 5.0 * total);
+        assert_relative_eq!(periodic.integral(1.5, 2.5).unwrap(), 1.125 + 0.75);
+        assert_relative_eq!(periodic.derivative(2.5).unwrap(), 2.0);
+        let reflect = linear.clone().with_exterpolation(Exterpolation::Reflect);
+        assert_relative_eq!(reflect.integral(-2.0, 4.0).unwrap(), 3.0 * total);
+        assert_relative_eq!(reflect.integral(2.0, 2.5).unwrap(), 1.125);
+        assert_relative_eq!(reflect.integral(-0.5, 0.0).unwrap(), 0.75);
+        assert_relative_eq!(reflect.derivative(2.5).unwrap(), 1.0);
+        assert_relative_eq!(reflect.derivative(-0.5).unwrap(), -2.0);
+        let saturation = linear.clone().with_exterpolation(Exterpolation::Saturation);
+        assert_relative_eq!(saturation.integral(-1.0, 3.0).unwrap(), total + 1.0 + 2.0);
+        assert_eq!(saturation.derivative(3.0).unwrap(), 0.0);
+        let constant = linear.clone().with_exterpolation(Exterpolation::Constant(-1.0));
+        assert_relative_eq!(constant.integral(-1.0, 3.0).unwrap(), total - 2.0);
+        assert_eq!(constant.second_derivative(3.0).unwrap(), 0.0);
+        let nan = linear.clone().with_exterpolation(Exterpolation::Nan);
+        assert!(nan.integral(-1.0, 1.0).unwrap().is_nan());
+        assert!(nan.derivative(3.0).unwrap().is_nan());
+        assert_relative_eq!(nan.integral(0.0, 1.0).unwrap(), 2.0);
+        let error = linear.with_exterpolation(Exterpolation::Error);
+        assert!(matches!(error.evaluate(3.0), Err(Error::OutOfRange { .. })));
+        assert!(matches!(error.integral(0.0, -1.0), Err(Error::OutOfRange { .. })));
+        assert!(matches!(error.evaluate_series(&[1.0, 3.0]), Err(Error::OutOfRange { .. })));
+
+        let step = Interpolator::step(&time, &signal, StepMode::Previous)
+            .unwrap()
+            .with_exterpolation(Exterpolation::Periodic);
+        assert_eq!(step.evaluate_series(&[2.5, 3.5, -0.5]).unwrap(), [1.0, 3.0, 3.0]);
+        assert_relative_eq!(step.integral(0.0, 4.0).unwrap(), 8.0);
+        let spline = Interpolator::cubic_spline(&time, &signal, SplineBoundary::Natural)
+            .unwrap()
+            .with_exterpolation(Exterpolation::Reflect);
+        assert_relative_eq!(
+            spline.evaluate(-0.3).unwrap(),
+            spline.evaluate(0.3).unwrap(),
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(
+            spline.evaluate(2.4).unwrap(),
+            spline.evaluate(1.6).unwrap(),
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(
+            spline.evaluate(4.4).unwrap(),
+            spline.evaluate(0.4).unwrap(),
+            epsilon = 1e-12
+        );
+    }
+
+    fn cubic(t: f64) -> f64 {
+        t * t * t - 2.0 * t * t + 0.5 * t + 1.0
+    }
+
+    #[test]
+    fn test_cubic_spline() {
+        let time = [0.0, 0.5, 1.5, 2.0, 3.5, 4.0];
+        let signal = time.map(cubic);
+        let not_a_knot =
+            Interpolator::cubic_spline(&time, &signal, SplineBoundary::NotAKnot).unwrap();
+        let clamped =
+            Interpolator::cubic_spline(&time, &signal, SplineBoundary::Clamped(0.5, 32.5)).unwrap();
+        for spline in [not_a_knot, clamped] {
+            for t in [-0.5, 0.0, 0.3, 1.0, 2.7, 4.0, 4.5] {
+                assert_relative_eq!(spline.evaluate(t).unwrap(), cubic(t), epsilon = 1e-10);
+                assert_relative_eq!(
+                    spline.derivative(t).unwrap(),
+                    3.0 * t * t - 4.0 * t + 0.5,
+                    epsilon = 1e-10
+                );
+                assert_relative_eq!(
+                    spline.second_derivative(t).unwrap(),
+                    6.0 * t - 4.0,
+                    epsilon = 1e-10
+                );
+            }
+            let antiderivative = |t: f64| t.powi(4) / 4.0 - 2.0 * t.powi(3) / 3.0 + t * t / 4.0 + t;
+            assert_relative_eq!(
+                spline.integral(0.2, 3.7).unwrap(),
+                antiderivative(3.7) - antiderivative(0.2),
+                epsilon = 1e-10
+            );
+            assert_relative_eq!(
+                spline.integral(3.7, 0.2).unwrap(),
+                -spline.integral(0.2, 3.7).unwrap()
+            );
+        }
+        let natural = Interpolator::cubic_spline(&time, &signal, SplineBoundary::Natural).unwrap();
+        assert_relative_eq!(natural.second_derivative(0.0).unwrap(), 0.0, epsilon = 1e-10);
+        assert_relative_eq!(natural.second_derivative(4.0).unwrap(), 0.0, epsilon = 1e-10);
+        for (&t, &y) in time.iter().zip(signal.iter()) {
+            assert_relative_eq!(natural.evaluate(t).unwrap(), y, epsilon = 1e-12);
+        }
+        let eps = 1e-9;
+        for t in [0.5, 1.5, 2.0, 3.5] {
+            assert_relative_eq!(
+                natural.derivative(t - eps).unwrap(),
+                natural.derivative(t + eps).unwrap(),
+                epsilon = 1e-5
+            );
+            assert_relative_eq!(
+                natural.second_derivative(t - eps).unwrap(),
+                natural.second_derivative(t + eps).unwrap(),
+                epsilon = 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn test_shape_preserving() {
+        let time = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let signal = [0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+        let pchip = Interpolator::pchip(&time, &signal).unwrap();
+        let akima = Interpolator::akima(&time, &signal).unwrap();
+        let spline = Interpolator::cubic_spline(&time, &signal, SplineBoundary::Natural).unwrap();
+        let samples = (0..=600).map(|i| i as f64 / 100.0).collect::<Vec<f64>>();
+        for interpolator in [&pchip, &akima] {
+            let values = interpolator.evaluate_series(&samples).unwrap();
+            assert!(values.windows(2).all(|w| w[1] >= w[0] - 1e-12));
+            assert!(values.iter().all(|&y| (-1e-12..=1.0 + 1e-12).contains(&y)));
+            assert_relative_eq!(interpolator.evaluate(1.5).unwrap(), 0.0, epsilon = 1e-12);
+            assert_relative_eq!(interpolator.integral(0.0, 6.0).unwrap(), 3.5, epsilon = 1e-12);
+        }
+        assert!(spline.evaluate_series(&samples).unwrap().iter().any(|&y| y < -1e-3));
+        let linear = [0.0, 1.0, 2.0, 3.0];
+        let pchip = Interpolator::pchip(&time[..4], &linear).unwrap();
+        let akima = Interpolator::akima(&time[..4], &linear).unwrap();
+        assert_relative_eq!(pchip.evaluate(2.5).unwrap(), 2.5);
+        assert_relative_eq!(akima.evaluate(2.5).unwrap(), 2.5);
+        assert_relative_eq!(akima.derivative(0.5).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_step_and_linear() {
+        let time = [0, 1, 3];
+        let signal = [1.0, 2.0, 4.0];
+        let previous = Interpolator::step(&time, &signal, StepMode::Previous).unwrap();
+        let next = Interpolator::step(&time, &signal, StepMode::Next).unwrap();
+        let nearest = Interpolator::step(&time, &signal, StepMode::Nearest).unwrap();
+        let desired_time = [-1.0, 0.0, 0.5, 1.0, 1.9, 2.0, 2.1, 3.0, 4.0];
+        assert_eq!(
+            previous.evaluate_series(&desired_time).unwrap(),
+            [1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, 4.0, 4.0]
+        );
+        assert_eq!(
+            next.evaluate_series(&desired_time).unwrap(),
+            [1.0, 1.0, 2.0, 2.0, 4.0, 4.0, 4.0, 4.0, 4.0]
+        );
+        assert_eq!(
+            nearest.evaluate_series(&desired_time).unwrap(),
+            [1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 4.0, 4.0, 4.0]
+        );
+        assert_eq!(previous.derivative(0.5).unwrap(), 0.0);
+        assert_relative_eq!(previous.integral(-1, 4).unwrap(), 1.0 + 1.0 + 4.0 + 4.0);
+        assert_relative_eq!(next.integral(0.5, 2).unwrap(), 1.0 + 4.0);
+        assert_relative_eq!(nearest.integral(0, 3).unwrap(), 0.5 + 1.0 + 2.0 + 4.0);
+        let linear = Interpolator::linear(&time, &signal).unwrap();
+        let expected = series_linear_interp(&time, &signal, &desired_time, Exterpolation::Linear);
+        for (result, expected) in
+            linear.evaluate_series(&desired_time).unwrap().iter().zip(expected)
+        {
+            assert_relative_eq!(*result, expected);
+        }
+        assert_relative_eq!(linear.integral(0, 3).unwrap(), 1.5 + 6.0);
+        let single = Interpolator::step(&[2.0], &[5.0], StepMode::Nearest).unwrap();
+        assert_eq!(single.evaluate(0.0).unwrap(), 5.0);
+        assert_relative_eq!(single.integral(0.0, 3.0).unwrap(), 15.0);
+    }
+
+    #[test]
+    fn test_interpolator_errors() {
+        let result = Interpolator::cubic_spline(
+            &[0.0, 1.0, 2.0],
+            &[0.0, 1.0, 0.0],
+            SplineBoundary::NotAKnot,
+        );
+        assert_eq!(result.unwrap_err(), Error::InsufficientData { required: 4, actual: 3 });
+        let result = Interpolator::pchip(&[0.0, 2.0, 1.0], &[0.0, 1.0, 0.0]);
+        assert!(matches!(result, Err(Error::InvalidParameter(_))));
+        let result = Interpolator::akima(&[0.0, 1.0, 1.0], &[0.0, 1.0, 0.0]);
+        assert!(matches!(result, Err(Error::InvalidParameter(_))));
+        let result = Interpolator::linear(&[0.0, 1.0], &[0.0, f64::NAN]);
+        assert_eq!(result.unwrap_err(), Error::NanValue { index: 1 });
+        let result = Interpolator::step(&[0.0, 1.0], &[0.0], StepMode::Previous);
+        assert_eq!(result.unwrap_err(), Error::LengthMismatch { left: 2, right: 1 });
+    }
 }
@@ -1,4 +1,7 @@
 //! Numeric analysis
 
+pub mod differential_eq;
 pub mod differentiation;
+pub mod fft;
+pub mod integration;
 pub mod interpolation;
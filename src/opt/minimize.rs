@@ -0,0 +1,158 @@
+//! Derivative-free minimization
+
+/// Settings of `nelder_mead`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NelderMeadOptions {
+    pub max_iterations: usize,
+    /// Stop once the simplex is smaller than this in every coordinate and `f_tol` is met
+    pub x_tol: f64,
+    /// Stop once the values at the vertices of the simplex differ by less than this and
+    /// `x_tol` is met
+    pub f_tol: f64,
+    /// Offset of the initial vertices from the starting point, relative to its coordinates
+    pub initial_step: f64,
+}
+
+impl Default for NelderMeadOptions {
+    fn default() -> Self {
+        NelderMeadOptions { max_iterations: 10_000, x_tol: 1e-8, f_tol: 1e-10, initial_step: 0.05 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minimum {
+    pub x: Vec<f64>,
+    pub value: f64,
+    pub iterations: usize,
+    /// Whether the tolerances were met before `max_iterations`
+    pub converged: bool,
+}
+
+/// Minimize `f` from `x0` with the adaptive Nelder-Mead simplex method of Gao and Han.
+///
+/// NaN values of `f` count as infinitely large, so `f` can reject infeasible points by
+/// returning NaN or infinity.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::opt::minimize::{nelder_mead, NelderMeadOptions};
+/// let rosenbrock = |x: &[f64]| (1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0] * x[0]).powi(2);
+/// let minimum = nelder_mead(rosenbrock, &[-1.2, 1.0], &NelderMeadOptions::default());
+/// assert!((minimum.x[0] - 1.0).abs() < 1e-4 && (minimum.x[1] - 1.0).abs() < 1e-4);
+/// ```
+pub fn nelder_mead<F: Fn(&[f64]) -> f64>(f: F, x0: &[f64], options: &NelderMeadOptions) -> Minimum {
+    let value = |x: &[f64]| {
+        let v = f(x);
+        if v.is_nan() {
+            f64::INFINITY
+        } else {
+            v
+        }
+    };
+    let n = x0.len();
+    if n == 0 {
+        return Minimum { x: vec![], value: value(x0), iterations: 0, converged: true };
+    }
+    let dim = n as f64;
+    let (alpha, beta, gamma, delta) =
+        (1.0, 1.0 + 2.0 / dim, 0.75 - 1.0 / (2.0 * dim), 1.0 - 1.0 / dim);
+    let mut simplex = vec![x0.to_vec()];
+    for i in 0..n {
+        let mut vertex = x0.to_vec();
+        vertex[i] += if x0[i] == 0.0 { 0.00025 } else { options.initial_step * x0[i] };
+        simplex.push(vertex);
+    }
+    let mut values = simplex.iter().map(|x| value(x)).collect::<Vec<f64>>();
+    let point = |from: &[f64], to: &[f64], t: f64| {
+        from.iter().zip(to.iter()).map(|(a, b)| a + t * (b - a)).collect::<Vec<f64>>()
+    };
+
+    let mut iterations = 0;
+    let mut converged = false;
+    while iterations < options.max_iterations {
+        let mut order = (0..=n).collect::<Vec<usize>>();
+        order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+        simplex = order.iter().map(|&i| simplex[i].clone()).collect();
+        values = order.iter().map(|&i| values[i]).collect();
+
+        let x_spread = simplex[1..]
+            .iter()
+            .flat_map(|v| v.iter().zip(simplex[0].iter()).map(|(a, b)| (a - b).abs()))
+            .fold(0.0, f64::max);
+        let f_spread = (values[n] - values[0]).abs();
+        if x_spread <= options.x_tol && f_spread <= options.f_tol {
+            converged = true;
+            break;
+        }
+        iterations += 1;
+
+        let mut centroid = vec![0.0; n];
+        for vertex in simplex[..n].iter() {
+            for (c, v) in centroid.iter_mut().zip(vertex.iter()) {
+                *c += v / dim;
+            }
+        }
+        let reflected = point(&centroid, &simplex[n], -alpha);
+        let f_reflected = value(&reflected);
+        if f_reflected < values[0] {
+            let expanded = point(&centroid, &simplex[n], -beta);
+            let f_expanded = value(&expanded);
+            if f_expanded < f_reflected {
+                simplex[n] = expanded;
+                values[n] = f_expanded;
+            } else {
+                simplex[n] = reflected;
+                values[n] = f_reflected;
+            }
+            continue;
+        }
+        if f_reflected < values[n - 1] {
+            simplex[n] = reflected;
+            values[n] = f_reflected;
+            continue;
+        }
+        // outside contraction towards the reflected point if it improves the worst vertex
+        let direction = if f_reflected < values[n] { -gamma } else { gamma };
+        let contracted = point(&centroid, &simplex[n], direction);
+        let f_contracted = value(&contracted);
+        if f_contracted < values[n].min(f_reflected) {
+            simplex[n] = contracted;
+            values[n] = f_contracted;
+            continue;
+        }
+        for i in 1..=n {
+            simplex[i] = point(&simplex[0], &simplex[i], delta);
+            values[i] = value(&simplex[i]);
+        }
+    }
+    let best = (0..=n).min_by(|&a, &b| values[a].total_cmp(&values[b])).unwrap_or(0);
+    Minimum { x: simplex[best].clone(), value: values[best], iterations, converged }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_nelder_mead() {
+        let quadratic = |x: &[f64]| (x[0] - 3.0).powi(2) + 2.0 * (x[1] + 1.0).powi(2) + 5.0;
+        let minimum = nelder_mead(quadratic, &[0.0, 0.0], &NelderMeadOptions::default());
+        assert!(minimum.converged);
+        assert_relative_eq!(minimum.x[0], 3.0, epsilon = 1e-4);
+        assert_relative_eq!(minimum.x[1], -1.0, epsilon = 1e-4);
+        assert_relative_eq!(minimum.value, 5.0, epsilon = 1e-8);
+
+        let bounded = |x: &[f64]| if x[0] < 1.0 { f64::NAN } else { x[0] * x[0] };
+        let minimum = nelder_mead(bounded, &[4.0], &NelderMeadOptions::default());
+        assert_relative_eq!(minimum.x[0], 1.0, epsilon = 1e-4);
+
+        let minimum = nelder_mead(|_: &[f64]| 2.0, &[], &NelderMeadOptions::default());
+        assert_eq!(minimum.value, 2.0);
+
+        let sphere = |x: &[f64]| x.iter().map(|v| (v - 1.0).powi(2)).sum::<f64>();
+        let minimum = nelder_mead(sphere, &[0.0; 6], &NelderMeadOptions::default());
+        assert!(minimum.x.iter().all(|v| (v - 1.0).abs() < 1e-3));
+    }
+}
@@ -1,3 +1,4 @@
 //! Optimization
 
+pub mod minimize;
 pub mod utils;
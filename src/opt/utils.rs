@@ -1,3 +1,4 @@
+use crate::error::{check_len, Error, Result};
 use find_peaks::PeakFinder;
 
 pub fn local_max<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
@@ -8,7 +9,10 @@ pub fn local_max<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
     let mut fp = PeakFinder::new(&signal);
     fp.with_min_prominence(prominence.into());
     let peaks = fp.find_peaks();
-    peaks.iter().map(|x| x.middle_position()).collect::<Vec<usize>>()
+    // newer `find_peaks` releases order peaks by prominence, keep them in signal order
+    let mut positions = peaks.iter().map(|x| x.middle_position()).collect::<Vec<usize>>();
+    positions.sort_unstable();
+    positions
 }
 
 /// Find argmax of slice.
@@ -23,7 +27,12 @@ pub fn local_max<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
 /// assert_eq!(argmax(&a), 3);
 /// ```
 pub fn argmax<T: Copy + PartialOrd>(x: &[T]) -> usize {
-    assert!(!x.is_empty());
+    try_argmax(x).unwrap_or_else(|e| panic!("Cannot find argmax: {}", e))
+}
+
+/// Same as `argmax`, but returns an error for an empty slice.
+pub fn try_argmax<T: Copy + PartialOrd>(x: &[T]) -> Result<usize> {
+    check_len(x.len(), 1)?;
 
     let mut max_index = 0;
     let mut max = x[max_index];
@@ -34,17 +43,25 @@ pub fn argmax<T: Copy + PartialOrd>(x: &[T]) -> usize {
             max = *v;
         }
     }
-    max_index
+    Ok(max_index)
 }
 
 pub fn argsort<T: PartialOrd>(data: &[T], reverse: bool) -> Vec<usize> {
+    try_argsort(data, reverse).unwrap_or_else(|e| panic!("Cannot sort: {}", e))
+}
+
+/// Same as `argsort`, but returns an error if some values are not comparable, e.g. NaN.
+pub fn try_argsort<T: PartialOrd>(data: &[T], reverse: bool) -> Result<Vec<usize>> {
+    if let Some(index) = (0..data.len()).find(|&i| data[i].partial_cmp(&data[i]).is_none()) {
+        return Err(Error::NanValue { index });
+    }
     let mut indices = (0..data.len()).collect::<Vec<_>>();
     //indices.sort_by_key(|&i| &data[i]);  // not work for float
     indices.sort_by(|&i, &j| data[i].partial_cmp(&data[j]).unwrap());
     if reverse {
         indices.reverse();
     }
-    indices
+    Ok(indices)
 }
 
 #[cfg(test)]
@@ -70,6 +87,19 @@ mod tests {
         let a = vec![1.0, 2.0, 3.0, 0.4];
         assert_eq!(argmax(&a), 2);
     }
+    #[test]
+    fn test_try_argmax() {
+        let empty: Vec<f64> = vec![];
+        assert_eq!(try_argmax(&empty), Err(Error::InsufficientData { required: 1, actual: 0 }));
+        assert_eq!(try_argmax(&[1, 3, 2]), Ok(1));
+    }
+
+    #[test]
+    fn test_try_argsort() {
+        assert_eq!(try_argsort(&[1.0, f64::NAN, 0.0], false), Err(Error::NanValue { index: 1 }));
+        assert_eq!(try_argsort(&[1.0, 2.0, 0.0], true), Ok(vec![1, 0, 2]));
+    }
+
     #[test]
     fn test_argsort() {
         let data = vec![-5, 4, 1, -3, 2];
@@ -0,0 +1,196 @@
+//! Autocorrelation
+//!
+//! Sample autocorrelation (ACF) and partial autocorrelation (PACF) of a single series, with
+//! the confidence bands to tell significant lags from white noise.
+
+use crate::error::{check_len, check_no_nan, Error, Result};
+use crate::na::fft::RealFftPlan;
+use crate::stats::distribution::normal_quantile;
+use crate::stats::estimator::is_constant;
+use num::Complex;
+
+/// Autocovariances `sum(x[i] * x[i + k])` of a zero-mean series for lags `0..=max_lag`.
+fn lagged_products(x: &[f64], max_lag: usize) -> Vec<f64> {
+    let n = x.len();
+    // direct sums are cheaper than two FFTs of twice the length for a few lags
+    if ((max_lag + 1) as f64) <= 4.0 * (n as f64).log2() {
+        return (0..=max_lag).map(|k| (0..n - k).map(|i| x[i] * x[i + k]).sum()).collect();
+    }
+    let plan = RealFftPlan::new(2 * n);
+    let mut padded = x.to_vec();
+    padded.resize(2 * n, 0.0);
+    let power =
+        plan.forward(&padded).iter().map(|x| Complex::new(x.norm_sqr(), 0.0)).collect::<Vec<_>>();
+    plan.inverse(&power).iter().take(max_lag + 1).map(|x| x / (2 * n) as f64).collect()
+}
+
+fn check_lag(max_lag: usize, len: usize) -> Result<()> {
+    if max_lag >= len {
+        return Err(Error::InvalidParameter(format!(
+            "max_lag {} must be less than the series length {}",
+            max_lag, len
+        )));
+    }
+    Ok(())
+}
+
+/// Autocorrelation of `series` for lags `0..=max_lag`.
+///
+/// The biased estimate divides every lagged sum by the series length, the unbiased one by the
+/// number of its terms. Long series are computed by FFT.
+///
+/// Returns an error if the series is constant, contains NaN or has no more than `max_lag`
+/// samples.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::stats::autocorrelation::acf;
+/// let r = acf(&[1.0, 2.0, 3.0, 4.0], 2, true).unwrap();
+/// assert_eq!(r, [1.0, 0.25, -0.3]);
+/// ```
+pub fn acf<T: Into<f64> + Copy>(series: &[T], max_lag: usize, biased: bool) -> Result<Vec<f64>> {
+    check_len(series.len(), 2)?;
+    check_no_nan(series)?;
+    check_lag(max_lag, series.len())?;
+    let n = series.len();
+    let mean = series.iter().map(|&x| x.into()).sum::<f64>() / n as f64;
+    let centered = series.iter().map(|&x| x.into() - mean).collect::<Vec<f64>>();
+    if is_constant(&centered) {
+        return Err(Error::ConstantSeries);
+    }
+    let products = lagged_products(&centered, max_lag);
+    let variance = products[0] / n as f64;
+    Ok(products
+        .iter()
+        .enumerate()
+        .map(|(k, c)| {
+            let terms = if biased { n } else { n - k };
+            c / terms as f64 / variance
+        })
+        .collect())
+}
+
+/// Partial autocorrelation of `series` for lags `0..=max_lag`, solved from the biased
+/// autocorrelation by the Durbin-Levinson recursion.
+///
+/// Returns an error under the same conditions as `acf`.
+pub fn pacf<T: Into<f64> + Copy>(series: &[T], max_lag: usize) -> Result<Vec<f64>> {
+    let r = acf(series, max_lag, true)?;
+    let mut result = vec![1.0; max_lag + 1];
+    let mut phi: Vec<f64> = vec![];
+    let mut error = 1.0;
+    for k in 1..=max_lag {
+        let numerator = r[k] - phi.iter().enumerate().map(|(j, p)| p * r[k - 1 - j]).sum::<f64>();
+        let reflection = if error > 0.0 { numerator / error } else { 0.0 };
+        let previous = phi.clone();
+        for j in 0..phi.len() {
+            phi[j] -= reflection * previous[previous.len() - 1 - j];
+        }
+        phi.push(reflection);
+        error *= 1.0 - reflection * reflection;
+        result[k] = reflection;
+    }
+    Ok(result)
+}
+
+/// Half width of the `1 - alpha` confidence band of each lag of `acf` computed from `n`
+/// samples, by Bartlett's formula assuming the series is a moving average of lower order.
+///
+/// Lag 0 has a zero width.
+pub fn acf_confidence_band(acf: &[f64], n: usize, alpha: f64) -> Vec<f64> {
+    let z = normal_quantile(1.0 - alpha / 2.0);
+    let mut sum = 0.0;
+    let mut band = vec![0.0; acf.len()];
+    for k in 1..acf.len() {
+        band[k] = z * ((1.0 + 2.0 * sum) / n as f64).sqrt();
+        sum += acf[k] * acf[k];
+    }
+    band
+}
+
+/// Half width of the `1 - alpha` confidence band of the partial autocorrelation of `n`
+/// samples of white noise, the same for every lag.
+pub fn pacf_confidence_band(n: usize, alpha: f64) -> f64 {
+    normal_quantile(1.0 - alpha / 2.0) / (n as f64).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn ar1(n: usize, phi: f64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut x = vec![0.0; n];
+        for i in 1..n {
+            x[i] = phi * x[i - 1] + rng.gen_range(-0.5..0.5);
+        }
+        x
+    }
+
+    #[test]
+    fn test_acf() {
+        let series = [2.0, 4.0, 3.0, 7.0, 5.0, 1.0, 6.0];
+        let centered = series.iter().map(|x| x - 4.0).collect::<Vec<f64>>();
+        let c0 = centered.iter().map(|x| x * x).sum::<f64>();
+        let biased = acf(&series, 6, true).unwrap();
+        let unbiased = acf(&series, 6, false).unwrap();
+        for k in 0..7 {
+            let ck = (0..7 - k).map(|i| centered[i] * centered[i + k]).sum::<f64>();
+            assert_relative_eq!(biased[k], ck / c0, epsilon = 1e-12);
+            assert_relative_eq!(unbiased[k], ck / c0 * 7.0 / (7 - k) as f64, epsilon = 1e-12);
+        }
+        let long = ar1(5000, 0.8);
+        let fast = acf(&long, 200, true).unwrap();
+        let direct = acf(&long, 5, true).unwrap();
+        assert!(fast.len() == 201);
+        for k in 0..=5 {
+            assert_relative_eq!(fast[k], direct[k], epsilon = 1e-10);
+        }
+        assert_relative_eq!(fast[1], 0.8, epsilon = 0.05);
+        assert_relative_eq!(fast[2], 0.64, epsilon = 0.05);
+    }
+
+    #[test]
+    fn test_acf_errors() {
+        assert_eq!(acf(&[1.0; 5], 2, true), Err(Error::ConstantSeries));
+        assert!(matches!(acf(&[1.0, 2.0, 3.0], 3, true), Err(Error::InvalidParameter(_))));
+        assert_eq!(acf(&[1.0, f64::NAN, 3.0], 1, true), Err(Error::NanValue { index: 1 }));
+        assert_eq!(pacf(&[1.0], 0), Err(Error::InsufficientData { required: 2, actual: 1 }));
+    }
+
+    #[test]
+    fn test_pacf() {
+        let series = [2.0, 4.0, 3.0, 7.0, 5.0, 1.0, 6.0, 3.0];
+        let r = acf(&series, 3, true).unwrap();
+        let p = pacf(&series, 3).unwrap();
+        assert_eq!(p[0], 1.0);
+        assert_relative_eq!(p[1], r[1], epsilon = 1e-12);
+        assert_relative_eq!(p[2], (r[2] - r[1] * r[1]) / (1.0 - r[1] * r[1]), epsilon = 1e-12);
+        let long = ar1(5000, 0.6);
+        let p = pacf(&long, 5).unwrap();
+        let band = pacf_confidence_band(long.len(), 0.05);
+        assert_relative_eq!(p[1], 0.6, epsilon = 0.05);
+        assert!(p[2..].iter().all(|x| x.abs() < 2.0 * band));
+    }
+
+    #[test]
+    fn test_confidence_band() {
+        let band = acf_confidence_band(&[1.0, 0.5, 0.2], 100, 0.05);
+        assert_eq!(band[0], 0.0);
+        assert_relative_eq!(band[1], 0.195_996_398_454_005_4, epsilon = 1e-12);
+        assert_relative_eq!(
+            band[2],
+            1.959_963_984_540_054 * (1.5f64 / 100.0).sqrt(),
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(
+            pacf_confidence_band(400, 0.05),
+            0.097_998_199_227_002_7,
+            epsilon = 1e-12
+        );
+    }
+}
@@ -0,0 +1,386 @@
+//! Probability distributions
+//!
+//! Cumulative distribution and quantile functions needed by tests and confidence intervals.
+//! Arguments outside of the support give NaN.
+
+use std::f64::consts::{PI, SQRT_2};
+
+/// Natural logarithm of the gamma function for `x > 0`, by the Lanczos approximation.
+pub fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x.is_nan() || x <= 0.0 {
+        return f64::NAN;
+    }
+    if x < 0.5 {
+        // reflection formula
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + G + 0.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Regularized lower incomplete gamma function P(a, x).
+pub fn gamma_p(a: f64, x: f64) -> f64 {
+    if a.is_nan() || x.is_nan() || a <= 0.0 || x < 0.0 {
+        return f64::NAN;
+    }
+    if x < a + 1.0 {
+        gamma_series(a, x)
+    } else {
+        1.0 - gamma_continued_fraction(a, x)
+    }
+}
+
+/// Regularized upper incomplete gamma function Q(a, x) = 1 - P(a, x).
+pub fn gamma_q(a: f64, x: f64) -> f64 {
+    if a.is_nan() || x.is_nan() || a <= 0.0 || x < 0.0 {
+        return f64::NAN;
+    }
+    if x < a + 1.0 {
+        1.0 - gamma_series(a, x)
+    } else {
+        gamma_continued_fraction(a, x)
+    }
+}
+
+fn gamma_series(a: f64, x: f64) -> f64 {
+    if x == 0.0 {
+        return 0.0;
+    }
+    let mut term = 1.0 / a;
+    let mut sum = term;
+    let mut n = a;
+    for _ in 0..1000 {
+        n += 1.0;
+        term *= x / n;
+        sum += term;
+        if term.abs() < sum.abs() * f64::EPSILON {
+            break;
+        }
+    }
+    sum * (-x + a * x.ln() - ln_gamma(a)).exp()
+}
+
+/// Modified Lentz evaluation of the continued fraction of Q(a, x).
+fn gamma_continued_fraction(a: f64, x: f64) -> f64 {
+    let tiny = f64::MIN_POSITIVE / f64::EPSILON;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / tiny;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..1000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = b + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < f64::EPSILON {
+            break;
+        }
+    }
+    (-x + a * x.ln() - ln_gamma(a)).exp() * h
+}
+
+/// Regularized incomplete beta function I_x(a, b).
+pub fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if a.is_nan() || b.is_nan() || x.is_nan() || a <= 0.0 || b <= 0.0 || !(0.0..=1.0).contains(&x) {
+        return f64::NAN;
+    }
+    if x == 0.0 || x == 1.0 {
+        return x;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // the continued fraction converges quickly on the side of the mean
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// Modified Lentz evaluation of the continued fraction of I_x(a, b).
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let tiny = f64::MIN_POSITIVE / f64::EPSILON;
+    let clamp = |v: f64| if v.abs() < tiny { tiny } else { v };
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut h = d;
+    for m in 1..1000 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + even * d);
+        c = clamp(1.0 + even / c);
+        h *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + odd * d);
+        c = clamp(1.0 + odd / c);
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < f64::EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+/// Density of the standard normal distribution.
+pub fn normal_pdf(x: f64) -> f64 {
+    (-x * x / 2.0).exp() / (SQRT_2 * PI.sqrt())
+}
+
+/// Cumulative distribution function of the standard normal distribution.
+pub fn normal_cdf(x: f64) -> f64 {
+    if x.is_nan() {
+        return f64::NAN;
+    }
+    let tail = 0.5 * gamma_q(0.5, x * x / 2.0);
+    if x < 0.0 {
+        tail
+    } else {
+        1.0 - tail
+    }
+}
+
+/// Quantile function of the standard normal distribution, by Acklam's approximation refined by
+/// one Halley step.
+pub fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    if p.is_nan() || !(0.0..=1.0).contains(&p) {
+        return f64::NAN;
+    }
+    if p == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p == 1.0 {
+        return f64::INFINITY;
+    }
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    let x = if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    };
+    let e = normal_cdf(x) - p;
+    let u = e * (2.0 * PI).sqrt() * (x * x / 2.0).exp();
+    x - u / (1.0 + x * u / 2.0)
+}
+
+/// Survival function `1 - cdf` of the chi-square distribution with `df` degrees of freedom.
+pub fn chi_square_sf(x: f64, df: f64) -> f64 {
+    if x <= 0.0 && df > 0.0 {
+        return 1.0;
+    }
+    gamma_q(df / 2.0, x / 2.0)
+}
+
+/// Density of Student's t distribution with `df` degrees of freedom.
+pub fn student_t_pdf(x: f64, df: f64) -> f64 {
+    if df.is_nan() || df <= 0.0 {
+        return f64::NAN;
+    }
+    (ln_gamma((df + 1.0) / 2.0)
+        - ln_gamma(df / 2.0)
+        - 0.5 * (df * PI).ln()
+        - (df + 1.0) / 2.0 * (1.0 + x * x / df).ln())
+    .exp()
+}
+
+/// Cumulative distribution function of Student's t distribution with `df` degrees of freedom.
+pub fn student_t_cdf(x: f64, df: f64) -> f64 {
+    if x.is_nan() || df.is_nan() || df <= 0.0 {
+        return f64::NAN;
+    }
+    let tail = 0.5 * incomplete_beta(df / 2.0, 0.5, df / (df + x * x));
+    if x < 0.0 {
+        tail
+    } else {
+        1.0 - tail
+    }
+}
+
+/// Quantile function of Student's t distribution with `df` degrees of freedom, by Newton steps
+/// on the tail probability from the normal quantile, kept in a bracket by bisection.
+pub fn student_t_quantile(p: f64, df: f64) -> f64 {
+    if p.is_nan() || df.is_nan() || !(0.0..=1.0).contains(&p) || df <= 0.0 {
+        return f64::NAN;
+    }
+    if p == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p == 1.0 {
+        return f64::INFINITY;
+    }
+    if p < 0.5 {
+        return -student_t_quantile(1.0 - p, df);
+    }
+    // solve tail(t) = q for t >= 0 on the decreasing upper tail, which keeps small q accurate
+    let q = 1.0 - p;
+    let tail = |t: f64| 0.5 * incomplete_beta(df / 2.0, 0.5, df / (df + t * t));
+    let (mut lo, mut hi) = (0.0, normal_quantile(p).max(1.0));
+    while tail(hi) > q {
+        lo = hi;
+        hi *= 2.0;
+    }
+    let mut t = normal_quantile(p).clamp(lo, hi);
+    for _ in 0..100 {
+        let f = tail(t) - q;
+        if f > 0.0 {
+            lo = t;
+        } else {
+            hi = t;
+        }
+        let newton = t + f / student_t_pdf(t, df);
+        let next = if newton > lo && newton < hi { newton } else { (lo + hi) / 2.0 };
+        if (next - t).abs() <= 1e-15 * t.abs().max(1.0) {
+            return next;
+        }
+        t = next;
+    }
+    t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_ln_gamma() {
+        assert_relative_eq!(ln_gamma(1.0), 0.0, epsilon = 1e-14);
+        assert_relative_eq!(ln_gamma(5.0), 24f64.ln(), epsilon = 1e-13);
+        assert_relative_eq!(ln_gamma(0.5), PI.sqrt().ln(), epsilon = 1e-14);
+        assert_relative_eq!(ln_gamma(0.1), 2.252_712_651_734_206, epsilon = 1e-13);
+        assert!(ln_gamma(0.0).is_nan());
+    }
+
+    #[test]
+    fn test_gamma() {
+        assert_relative_eq!(gamma_p(1.0, 2.0), 1.0 - (-2f64).exp(), epsilon = 1e-15);
+        assert_relative_eq!(gamma_q(1.0, 20.0), (-20f64).exp(), max_relative = 1e-12);
+        assert_relative_eq!(gamma_p(3.0, 2.5) + gamma_q(3.0, 2.5), 1.0, epsilon = 1e-15);
+        assert!(gamma_p(-1.0, 1.0).is_nan());
+    }
+
+    #[test]
+    fn test_normal() {
+        assert_relative_eq!(normal_cdf(0.0), 0.5, epsilon = 1e-15);
+        assert_relative_eq!(normal_cdf(1.959_963_984_540_054), 0.975, epsilon = 1e-14);
+        assert_relative_eq!(normal_cdf(-8.0), 6.220_960_574_271_785e-16, max_relative = 1e-10);
+        assert_relative_eq!(normal_quantile(0.975), 1.959_963_984_540_054, epsilon = 1e-13);
+        assert_relative_eq!(normal_quantile(0.5), 0.0, epsilon = 1e-15);
+        assert_relative_eq!(normal_quantile(1e-10), -6.361_340_902_404_056, epsilon = 1e-10);
+        assert_eq!(normal_quantile(1.0), f64::INFINITY);
+        assert!(normal_quantile(1.5).is_nan());
+        for p in [0.001, 0.02, 0.3, 0.9, 0.999] {
+            assert_relative_eq!(normal_cdf(normal_quantile(p)), p, max_relative = 1e-13);
+        }
+        assert_relative_eq!(normal_pdf(0.0), 0.398_942_280_401_432_7, epsilon = 1e-15);
+    }
+
+    #[test]
+    fn test_student_t() {
+        assert_relative_eq!(incomplete_beta(2.0, 3.0, 0.4), 0.5248, epsilon = 1e-14);
+        assert_relative_eq!(incomplete_beta(0.5, 0.5, 0.25), 1.0 / 3.0, epsilon = 1e-14);
+        assert!(incomplete_beta(1.0, 1.0, 1.5).is_nan());
+        // Cauchy for one degree of freedom
+        assert_relative_eq!(student_t_cdf(1.0, 1.0), 0.75, epsilon = 1e-14);
+        assert_relative_eq!(student_t_pdf(0.0, 1.0), 1.0 / PI, epsilon = 1e-14);
+        assert_relative_eq!(
+            student_t_quantile(0.975, 1.0),
+            12.706_204_736_174_704,
+            epsilon = 1e-10
+        );
+        assert_relative_eq!(
+            student_t_quantile(0.975, 10.0),
+            2.228_138_851_986_274,
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(
+            student_t_quantile(0.05, 3.0),
+            -2.353_363_434_801_823_7,
+            epsilon = 1e-12
+        );
+        // closed form for two degrees of freedom
+        let p = 1.0 - 1e-8f64;
+        let expected = (2.0 * p - 1.0) / (2.0 * p * (1.0 - p)).sqrt();
+        assert_relative_eq!(student_t_quantile(p, 2.0), expected, max_relative = 1e-9);
+        assert_relative_eq!(student_t_quantile(0.99, 1e6), normal_quantile(0.99), epsilon = 1e-5);
+        assert_eq!(student_t_quantile(0.5, 4.0), 0.0);
+        for p in [0.001, 0.2, 0.7, 0.999] {
+            assert_relative_eq!(
+                student_t_cdf(student_t_quantile(p, 5.0), 5.0),
+                p,
+                max_relative = 1e-12
+            );
+        }
+    }
+
+    #[test]
+    fn test_chi_square_sf() {
+        assert_relative_eq!(chi_square_sf(3.841_458_820_694_124, 1.0), 0.05, epsilon = 1e-13);
+        assert_relative_eq!(chi_square_sf(18.307_038_053_275_146, 10.0), 0.05, epsilon = 1e-13);
+        assert_relative_eq!(chi_square_sf(2.0, 2.0), (-1f64).exp(), epsilon = 1e-15);
+        assert_eq!(chi_square_sf(0.0, 3.0), 1.0);
+    }
+}
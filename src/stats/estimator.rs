@@ -1,3 +1,6 @@
+use crate::error::{check_len, check_no_nan, check_same_len, Error, Result};
+use crate::stats::missing::{apply_nan_policy, NanPolicy};
+
 /// Calculate the mean/average value of a &[<Into<f64> + Copy>].
 ///
 /// Returns a f64 value.
@@ -10,25 +13,64 @@
 /// assert_eq!(mean(&a), 2.5);
 /// ```
 pub fn mean<T: Into<f64> + Copy>(series: &[T]) -> f64 {
-    if series.is_empty() {
-        panic!("Cannot calculate mean of empty series");
+    try_mean(series).unwrap_or_else(|e| panic!("Cannot calculate mean: {}", e))
+}
+
+/// Same as `mean`, but returns an error for an empty series instead of panicking.
+pub fn try_mean<T: Into<f64> + Copy>(series: &[T]) -> Result<f64> {
+    check_len(series.len(), 1)?;
+    Ok(series.iter().fold(0.0, |acc, x| acc + (*x).into()) / series.len() as f64)
+}
+
+/// Mean under a `NanPolicy`, with `Skip` it is the mean of the non-NaN samples.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::stats::estimator::mean_with_nan_policy;
+/// use mathbox::stats::missing::NanPolicy;
+/// let a = vec![1.0, f64::NAN, 3.0];
+/// assert!(mean_with_nan_policy(&a, NanPolicy::Propagate).unwrap().is_nan());
+/// assert_eq!(mean_with_nan_policy(&a, NanPolicy::Skip), Ok(2.0));
+/// assert!(mean_with_nan_policy(&a, NanPolicy::Error).is_err());
+/// ```
+pub fn mean_with_nan_policy<T: Into<f64> + Copy>(series: &[T], policy: NanPolicy) -> Result<f64> {
+    check_len(series.len(), 1)?;
+    match apply_nan_policy(series, policy)? {
+        Some(values) => try_mean(&values),
+        None => Ok(f64::NAN),
     }
-    series.iter().fold(0.0, |acc, x| acc + (*x).into()) / series.len() as f64
 }
 
 pub fn var<T: Into<f64> + Copy>(series: &[T], biased: bool) -> f64 {
-    let mean = mean(series);
+    try_var(series, biased).unwrap_or_else(|e| panic!("Cannot calculate var: {}", e))
+}
+
+/// Same as `var`, the unbiased variance needs at least 2 samples.
+pub fn try_var<T: Into<f64> + Copy>(series: &[T], biased: bool) -> Result<f64> {
+    check_len(series.len(), if biased { 1 } else { 2 })?;
+    let mean = try_mean(series)?;
     let mut sum = 0.0;
     for &value in series {
         sum += (value.into() - mean).powi(2);
     }
     if biased {
-        sum / series.len() as f64
+        Ok(sum / series.len() as f64)
     } else {
-        if series.len() == 1 {
-            panic!("Cannot calculate unbiased var of 1 number");
-        }
-        sum / (series.len() - 1) as f64
+        Ok(sum / (series.len() - 1) as f64)
+    }
+}
+
+/// Variance under a `NanPolicy`, with `Skip` it is the variance of the non-NaN samples.
+pub fn var_with_nan_policy<T: Into<f64> + Copy>(
+    series: &[T],
+    biased: bool,
+    policy: NanPolicy,
+) -> Result<f64> {
+    check_len(series.len(), if biased { 1 } else { 2 })?;
+    match apply_nan_policy(series, policy)? {
+        Some(values) => try_var(&values, biased),
+        None => Ok(f64::NAN),
     }
 }
 
@@ -36,35 +78,74 @@ pub fn std<T: Into<f64> + Copy>(series: &[T], biased: bool) -> f64 {
     var(series, biased).sqrt()
 }
 
+pub fn try_std<T: Into<f64> + Copy>(series: &[T], biased: bool) -> Result<f64> {
+    Ok(try_var(series, biased)?.sqrt())
+}
+
+pub fn std_with_nan_policy<T: Into<f64> + Copy>(
+    series: &[T],
+    biased: bool,
+    policy: NanPolicy,
+) -> Result<f64> {
+    Ok(var_with_nan_policy(series, biased, policy)?.sqrt())
+}
+
 pub fn median<T: Into<f64> + Copy>(series: &[T]) -> f64 {
-    let mut series = series.to_vec();
-    series.sort_by(|a, b| (*a).into().partial_cmp(&(*b).into()).unwrap());
-    if series.len() % 2 == 0 {
-        (series[series.len() / 2 - 1].into() + series[series.len() / 2].into()) / 2.0
+    try_median(series).unwrap_or_else(|e| panic!("Cannot calculate median: {}", e))
+}
+
+/// Same as `median`, but returns an error for an empty series or a NaN.
+pub fn try_median<T: Into<f64> + Copy>(series: &[T]) -> Result<f64> {
+    check_len(series.len(), 1)?;
+    check_no_nan(series)?;
+    let mut series = series.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    series.sort_by(|a, b| a.total_cmp(b));
+    if series.len().is_multiple_of(2) {
+        Ok((series[series.len() / 2 - 1] + series[series.len() / 2]) / 2.0)
     } else {
-        series[series.len() / 2].into()
+        Ok(series[series.len() / 2])
     }
 }
 
+/// Median under a `NanPolicy`, with `Propagate` a NaN makes the median NaN instead of an
+/// error like `try_median`.
+pub fn median_with_nan_policy<T: Into<f64> + Copy>(series: &[T], policy: NanPolicy) -> Result<f64> {
+    check_len(series.len(), 1)?;
+    match apply_nan_policy(series, policy)? {
+        Some(values) => try_median(&values),
+        None => Ok(f64::NAN),
+    }
+}
+
+pub(crate) fn is_constant(series: &[f64]) -> bool {
+    (series.iter().copied().fold(f64::NAN, f64::max)
+        - series.iter().copied().fold(f64::NAN, f64::min))
+    .abs()
+        < 1e-10
+}
+
 pub fn pcc<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
     x: &[X],
     y: &[Y],
     lag_max: usize,
 ) -> Vec<(isize, f64)> {
+    try_pcc(x, y, lag_max)
+        .unwrap_or_else(|e| panic!("Cannot calculate pearson correlation coefficient: {}", e))
+}
+
+/// Same as `pcc`, but returns an error for empty, constant or different length series.
+pub fn try_pcc<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    x: &[X],
+    y: &[Y],
+    lag_max: usize,
+) -> Result<Vec<(isize, f64)>> {
     let i = x.len();
-    if i != y.len() {
-        panic!("Cannot calculate pearson correlation coefficient with different length series");
-    }
+    check_same_len(i, y.len())?;
+    check_len(i, 1)?;
     let x = x.iter().map(|&x| x.into()).collect::<Vec<f64>>();
     let y = y.iter().map(|&y| y.into()).collect::<Vec<f64>>();
-    if (x.iter().copied().fold(f64::NAN, f64::max) - x.iter().copied().fold(f64::NAN, f64::min))
-        .abs()
-        < 1e-10
-        || (y.iter().copied().fold(f64::NAN, f64::max) - y.iter().copied().fold(f64::NAN, f64::min))
-            .abs()
-            < 1e-10
-    {
-        panic!("Cannot calculate pearson correlation coefficient with constant series");
+    if is_constant(&x) || is_constant(&y) {
+        return Err(Error::ConstantSeries);
     }
     let mut lag_max = lag_max;
     if lag_max >= i {
@@ -77,42 +158,38 @@ pub fn pcc<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
     new_x.append(&mut x.to_vec());
     new_x.append(&mut add_x.to_vec());
     for k in i - lag_max - 1..i + lag_max {
-        let x_lag = new_x[k..(k + i)].to_vec();
-        if (x_lag.iter().copied().fold(f64::NAN, f64::max)
-            - x_lag.iter().copied().fold(f64::NAN, f64::min))
-        .abs()
-            < 1e-10
-        {
+        let x_lag = &new_x[k..(k + i)];
+        if is_constant(x_lag) {
             continue;
         }
-        result.push((
-            k as isize + 1 - i as isize,
-            pearson_correlation_coefficient(&y, &new_x[k..(k + i)]),
-        ));
+        result.push((k as isize + 1 - i as isize, try_pearson_correlation_coefficient(&y, x_lag)?));
     }
-    result
+    Ok(result)
 }
 
 pub fn pearson_correlation_coefficient<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
     x: &[X],
     y: &[Y],
 ) -> f64 {
-    if x.len() != y.len() {
-        panic!("Cannot calculate pearson correlation coefficient with different length series");
-    }
+    try_pearson_correlation_coefficient(x, y)
+        .unwrap_or_else(|e| panic!("Cannot calculate pearson correlation coefficient: {}", e))
+}
+
+/// Same as `pearson_correlation_coefficient`, but returns an error for empty, constant or
+/// different length series.
+pub fn try_pearson_correlation_coefficient<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    x: &[X],
+    y: &[Y],
+) -> Result<f64> {
+    check_same_len(x.len(), y.len())?;
+    check_len(x.len(), 1)?;
     let x = x.iter().map(|&x| x.into()).collect::<Vec<f64>>();
     let y = y.iter().map(|&y| y.into()).collect::<Vec<f64>>();
-    if (x.iter().copied().fold(f64::NAN, f64::max) - x.iter().copied().fold(f64::NAN, f64::min))
-        .abs()
-        < 1e-10
-        || (y.iter().copied().fold(f64::NAN, f64::max) - y.iter().copied().fold(f64::NAN, f64::min))
-            .abs()
-            < 1e-10
-    {
-        panic!("Cannot calculate pearson correlation coefficient with constant series");
+    if is_constant(&x) || is_constant(&y) {
+        return Err(Error::ConstantSeries);
     }
-    let x_mean = mean(&x);
-    let y_mean = mean(&y);
+    let x_mean = try_mean(&x)?;
+    let y_mean = try_mean(&y)?;
     let mut x_qsum = 0.0;
     let mut y_qsum = 0.0;
     let mut xy_qsum = 0.0;
@@ -121,7 +198,7 @@ pub fn pearson_correlation_coefficient<X: Into<f64> + Copy, Y: Into<f64> + Copy>
         y_qsum += (y[i] - y_mean).powi(2);
         xy_qsum += (x[i] - x_mean) * (y[i] - y_mean);
     }
-    xy_qsum / (x_qsum * y_qsum).sqrt()
+    Ok(xy_qsum / (x_qsum * y_qsum).sqrt())
 }
 
 fn pairwise_distance_sumavg<X: Into<f64> + Copy, Y: Into<f64> + Copy>(x: &[X], y: &[Y]) -> f64 {
@@ -151,6 +228,17 @@ pub fn energy_distance<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
     }
 }
 
+/// Same as `energy_distance`, but returns an error instead of NaN for an empty sample.
+pub fn try_energy_distance<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
+    x: &[X],
+    y: &[Y],
+    normalized: bool,
+) -> Result<f64> {
+    check_len(x.len(), 1)?;
+    check_len(y.len(), 1)?;
+    Ok(energy_distance(x, y, normalized))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -192,10 +280,78 @@ mod tests {
     fn test_std() {
         let series = vec![1.0, 2.0, 3.0, 4.0, 5.0];
         assert_relative_eq!(std(&series, false), 1.58113883008, epsilon = 1e-6);
-        assert_eq!(std(&series, true), 1.4142135623730951);
+        assert_eq!(std(&series, true), std::f64::consts::SQRT_2);
         let series = vec![1, 2, 3, 4, 5];
         assert_relative_eq!(std(&series, false), 1.58113883008, epsilon = 1e-6);
-        assert_eq!(std(&series, true), 1.4142135623730951);
+        assert_eq!(std(&series, true), std::f64::consts::SQRT_2);
+    }
+
+    #[test]
+    fn test_try_estimators() {
+        let empty: Vec<f64> = vec![];
+        assert_eq!(try_mean(&empty), Err(Error::InsufficientData { required: 1, actual: 0 }));
+        assert_eq!(try_var(&[1.0], false), Err(Error::InsufficientData { required: 2, actual: 1 }));
+        assert_eq!(try_var(&[1.0], true), Ok(0.0));
+        assert_eq!(try_std(&[1, 3], true), Ok(1.0));
+        assert_eq!(try_median(&empty), Err(Error::InsufficientData { required: 1, actual: 0 }));
+        assert_eq!(try_median(&[1.0, f64::NAN, 2.0]), Err(Error::NanValue { index: 1 }));
+        assert_eq!(try_median(&[3.0, 1.0, 2.0, 4.0]), Ok(2.5));
+        assert_eq!(
+            try_energy_distance(&empty, &[1.0], false),
+            Err(Error::InsufficientData { required: 1, actual: 0 })
+        );
+    }
+
+    #[test]
+    fn test_nan_policy() {
+        let series = [1.0, f64::NAN, 2.0, 6.0];
+        assert!(mean_with_nan_policy(&series, NanPolicy::Propagate).unwrap().is_nan());
+        assert_eq!(mean_with_nan_policy(&series, NanPolicy::Skip), Ok(3.0));
+        assert_eq!(
+            mean_with_nan_policy(&series, NanPolicy::Error),
+            Err(Error::NanValue { index: 1 })
+        );
+        assert!(var_with_nan_policy(&series, false, NanPolicy::Propagate).unwrap().is_nan());
+        assert_eq!(var_with_nan_policy(&series, false, NanPolicy::Skip), Ok(7.0));
+        assert_eq!(std_with_nan_policy(&series, true, NanPolicy::Skip), Ok((14.0f64 / 3.0).sqrt()));
+        assert!(median_with_nan_policy(&series, NanPolicy::Propagate).unwrap().is_nan());
+        assert_eq!(median_with_nan_policy(&series, NanPolicy::Skip), Ok(2.0));
+        assert_eq!(
+            median_with_nan_policy(&series, NanPolicy::Error),
+            Err(Error::NanValue { index: 1 })
+        );
+        let all_nan = [f64::NAN, f64::NAN];
+        assert_eq!(
+            mean_with_nan_policy(&all_nan, NanPolicy::Skip),
+            Err(Error::InsufficientData { required: 1, actual: 0 })
+        );
+        assert_eq!(mean_with_nan_policy(&[1, 2], NanPolicy::Error), Ok(1.5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_median_panic() {
+        median(&[1.0, f64::NAN]);
+    }
+
+    #[test]
+    fn test_try_pearson_correlation_coefficient() {
+        let x = [1.0, 2.0, 3.0];
+        assert_eq!(
+            try_pearson_correlation_coefficient(&x, &[1.0, 2.0]),
+            Err(Error::LengthMismatch { left: 3, right: 2 })
+        );
+        assert_eq!(
+            try_pearson_correlation_coefficient(&x, &[1.0, 1.0, 1.0]),
+            Err(Error::ConstantSeries)
+        );
+        assert_eq!(try_pearson_correlation_coefficient(&x, &x), Ok(1.0));
+        assert_eq!(try_pcc(&x, &[2, 2, 2], 1), Err(Error::ConstantSeries));
+        let empty: Vec<f64> = vec![];
+        assert_eq!(
+            try_pcc(&empty, &empty, 1),
+            Err(Error::InsufficientData { required: 1, actual: 0 })
+        );
     }
 
     #[test]
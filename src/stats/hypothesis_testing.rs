@@ -1,3 +1,6 @@
+use crate::error::{Error, Result};
+use crate::stats::autocorrelation::acf;
+use crate::stats::distribution::chi_square_sf;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 
@@ -30,11 +33,72 @@ pub fn permutation_test<X: Into<f64> + Copy, Y: Into<f64> + Copy>(
     probability <= p_value.into()
 }
 
+/// Outcome of a portmanteau test for autocorrelation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortmanteauTest {
+    pub statistic: f64,
+    /// Degrees of freedom of the chi-square distribution of `statistic`
+    pub df: usize,
+    /// Probability of a statistic at least as large if the series is white noise
+    pub p_value: f64,
+}
+
+fn portmanteau<T: Into<f64> + Copy>(
+    series: &[T],
+    lags: usize,
+    model_df: usize,
+    ljung_box: bool,
+) -> Result<PortmanteauTest> {
+    if lags <= model_df {
+        return Err(Error::InvalidParameter("lags must be larger than model_df".to_string()));
+    }
+    let n = series.len() as f64;
+    let r = acf(series, lags, true)?;
+    let statistic = if ljung_box {
+        n * (n + 2.0) * (1..=lags).map(|k| r[k] * r[k] / (n - k as f64)).sum::<f64>()
+    } else {
+        n * r[1..].iter().map(|x| x * x).sum::<f64>()
+    };
+    let df = lags - model_df;
+    Ok(PortmanteauTest { statistic, df, p_value: chi_square_sf(statistic, df as f64) })
+}
+
+/// Ljung-Box test of the autocorrelation of `series` up to lag `lags`.
+///
+/// For the residuals of a fitted model, `model_df` is its number of parameters, e.g. `p + q`
+/// for an ARMA(p, q) model, which is taken off the degrees of freedom.
+///
+/// # Examples
+///
+/// ```
+/// use mathbox::stats::hypothesis_testing::ljung_box;
+/// let alternating = (0..100).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 }).collect::<Vec<_>>();
+/// assert!(ljung_box(&alternating, 10, 0).unwrap().p_value < 0.01);
+/// ```
+pub fn ljung_box<T: Into<f64> + Copy>(
+    series: &[T],
+    lags: usize,
+    model_df: usize,
+) -> Result<PortmanteauTest> {
+    portmanteau(series, lags, model_df, true)
+}
+
+/// Box-Pierce test, the large sample form of `ljung_box`.
+pub fn box_pierce<T: Into<f64> + Copy>(
+    series: &[T],
+    lags: usize,
+    model_df: usize,
+) -> Result<PortmanteauTest> {
+    portmanteau(series, lags, model_df, false)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::stats::estimator::mean;
-    //use approx::assert_relative_eq;
+    use approx::assert_relative_eq;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
 
     #[test]
     fn test_permutation_test() {
@@ -43,19 +107,43 @@ mod tests {
         let hypothesis = |a: &[f64], b: &[f64]| mean(a) - mean(b);
         let p_value = 0.05;
         let n_permutations = 1000;
-        assert_eq!(permutation_test(&a, &b, &hypothesis, p_value, n_permutations), false);
+        assert!(!permutation_test(&a, &b, &hypothesis, p_value, n_permutations));
         let a = vec![1.01, 1.02, 1.03, 1.04, 1.05];
         let b = vec![0.99, 1.0, 1.01, 1.0, 1.01];
         let hypothesis = |a: &[f64], b: &[f64]| mean(a) - mean(b);
         let p_value = 0.05;
         let n_permutations = 1000;
-        assert_eq!(permutation_test(&a, &b, &hypothesis, p_value, n_permutations), true);
+        assert!(permutation_test(&a, &b, &hypothesis, p_value, n_permutations));
 
         let a = vec![1, 2, 3, 4, 5];
         let b = vec![11, 12, 13, 14, 15, 11, 12, 13, 14, 15];
         let hypothesis = |a: &[i32], b: &[i32]| mean(a) - mean(b);
         let p_value = 0.05;
         let n_permutations = 1000;
-        assert_eq!(permutation_test(&a, &b, &hypothesis, p_value, n_permutations), false);
+        assert!(!permutation_test(&a, &b, &hypothesis, p_value, n_permutations));
+    }
+
+    #[test]
+    fn test_portmanteau() {
+        let series = [2.0, 4.0, 3.0, 7.0, 5.0, 1.0, 6.0, 3.0, 4.0, 5.0];
+        let r = acf(&series, 3, true).unwrap();
+        let lb = ljung_box(&series, 3, 1).unwrap();
+        let expected = 120.0 * (r[1] * r[1] / 9.0 + r[2] * r[2] / 8.0 + r[3] * r[3] / 7.0);
+        assert_relative_eq!(lb.statistic, expected, epsilon = 1e-12);
+        assert_eq!(lb.df, 2);
+        assert_relative_eq!(lb.p_value, (-expected / 2.0).exp(), epsilon = 1e-12);
+        let bp = box_pierce(&series, 3, 0).unwrap();
+        assert_relative_eq!(bp.statistic, 10.0 * (r[1] * r[1] + r[2] * r[2] + r[3] * r[3]));
+        assert!(bp.statistic < lb.statistic);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let noise = (0..500).map(|_| rng.gen_range(-1.0..1.0)).collect::<Vec<f64>>();
+        assert!(ljung_box(&noise, 10, 0).unwrap().p_value > 0.05);
+        let mut walk = noise.clone();
+        for i in 1..walk.len() {
+            walk[i] += walk[i - 1];
+        }
+        assert!(ljung_box(&walk, 10, 0).unwrap().p_value < 1e-6);
+        assert!(matches!(ljung_box(&series, 2, 2), Err(Error::InvalidParameter(_))));
     }
 }
@@ -0,0 +1,66 @@
+//! Missing values
+//!
+//! Gaps in telemetry are usually encoded as NaN. Functions taking a `NanPolicy` decide with it
+//! whether such samples poison the result, are left out, or are rejected.
+
+use crate::error::{check_no_nan, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NanPolicy {
+    /// NaN samples propagate into the result, e.g. the mean of a series with a NaN is NaN
+    #[default]
+    Propagate,
+    /// NaN samples are left out of the computation
+    Skip,
+    /// NaN samples are rejected with `Error::NanValue`
+    Error,
+}
+
+/// Values of `series` to compute on under `policy`.
+///
+/// Returns `None` if the result must be NaN, i.e. the series contains NaN and the policy is
+/// `Propagate`.
+pub(crate) fn apply_nan_policy<T: Into<f64> + Copy>(
+    series: &[T],
+    policy: NanPolicy,
+) -> Result<Option<Vec<f64>>> {
+    let values = series.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+    if !values.iter().any(|x| x.is_nan()) {
+        return Ok(Some(values));
+    }
+    match policy {
+        NanPolicy::Propagate => Ok(None),
+        NanPolicy::Skip => Ok(Some(values.into_iter().filter(|x| !x.is_nan()).collect())),
+        NanPolicy::Error => check_no_nan(series).map(|_| None),
+    }
+}
+
+/// Indices and values of the non-NaN samples of `series`.
+pub(crate) fn valid_samples<T: Into<f64> + Copy>(series: &[T]) -> (Vec<usize>, Vec<f64>) {
+    series.iter().map(|&x| x.into()).enumerate().filter(|(_, x)| !x.is_nan()).unzip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_apply_nan_policy() {
+        let series = [1.0, f64::NAN, 3.0];
+        assert_eq!(apply_nan_policy(&series, NanPolicy::Propagate), Ok(None));
+        assert_eq!(apply_nan_policy(&series, NanPolicy::Skip), Ok(Some(vec![1.0, 3.0])));
+        assert_eq!(apply_nan_policy(&series, NanPolicy::Error), Err(Error::NanValue { index: 1 }));
+        for policy in [NanPolicy::Propagate, NanPolicy::Skip, NanPolicy::Error] {
+            assert_eq!(apply_nan_policy(&[1, 2], policy), Ok(Some(vec![1.0, 2.0])));
+        }
+        assert_eq!(NanPolicy::default(), NanPolicy::Propagate);
+    }
+
+    #[test]
+    fn test_valid_samples() {
+        let (indices, values) = valid_samples(&[f64::NAN, 1.0, f64::NAN, 3.0]);
+        assert_eq!(indices, [1, 3]);
+        assert_eq!(values, [1.0, 3.0]);
+    }
+}
@@ -1,4 +1,7 @@
 //! Statistics module
 
+pub mod autocorrelation;
+pub mod distribution;
 pub mod estimator;
 pub mod hypothesis_testing;
+pub mod missing;